@@ -17,9 +17,15 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     tonic_build::configure()
         .file_descriptor_set_path(out_dir.join("file_descriptor_set.pb"))
-        .compile(&["../proto/p2p/p2p.proto", "../proto/p2p/v1/p2p_service.proto"], &[
-            "../proto", "proto",
-        ])?;
+        .compile(
+            &[
+                "../proto/p2p/p2p.proto",
+                "../proto/p2p/v1/p2p_service.proto",
+                "../proto/google/rpc/status.proto",
+                "../proto/google/rpc/error_details.proto",
+            ],
+            &["../proto", "proto"],
+        )?;
 
     Ok(())
 }
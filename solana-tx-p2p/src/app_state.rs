@@ -1,7 +1,11 @@
+use std::sync::Arc;
+
 use axum::extract::FromRef;
 use tokio::sync::mpsc;
 
-use crate::service::{DefaultPeerService, PeerService, PeerWorkerInboundEvent};
+use crate::service::{
+    DefaultPeerService, EventBroadcaster, PeerService, PeerWorkerInboundEvent, Store,
+};
 
 pub trait AppState: Clone + Send + Sync + 'static {
     type PeerService: PeerService + FromRef<Self> + Send + Sync + Clone;
@@ -16,8 +20,12 @@ pub struct DefaultAppState {
 
 impl DefaultAppState {
     #[must_use]
-    pub const fn new(peer_worker_inbound_sender: mpsc::Sender<PeerWorkerInboundEvent>) -> Self {
-        Self { peer_service: DefaultPeerService::new(peer_worker_inbound_sender) }
+    pub fn new(
+        peer_worker_inbound_sender: mpsc::Sender<PeerWorkerInboundEvent>,
+        store: Arc<dyn Store>,
+        events: EventBroadcaster,
+    ) -> Self {
+        Self { peer_service: DefaultPeerService::new(peer_worker_inbound_sender, store, events) }
     }
 }
 
@@ -1,14 +1,19 @@
-use std::{process, sync::Arc, time::Duration};
+use std::{path::PathBuf, process, sync::Arc, time::Duration};
 
-use clap::Args;
+use clap::{Args, ValueEnum};
 use futures_util::TryFutureExt;
-use snafu::ResultExt;
-use solana_sdk::pubkey::Pubkey;
+use snafu::{OptionExt, ResultExt};
+use solana_sdk::{pubkey::Pubkey, signature::read_keypair_file, signer::Signer as _};
 use solana_tx_p2p::{
     service::{
-        create_solana_client, start_heartbeat_trigger, start_message_trigger, PeerWorker,
-        PeerWorkerInboundEvent, RRElectionWorker, RRElectionWorkerType, SolanaRelayer,
-        SolanaSigner,
+        create_solana_client, fetch_address_lookup_tables, start_heartbeat_trigger,
+        start_message_trigger, BatchConfig, BlockhashCache, ComputeBudgetConfig, ComputeUnitPrice,
+        ConfirmationTracker, DurableNonce, DurableNonceConfig, EventBroadcaster, FrostConfig,
+        FundingConfig, FundingStrategy, HelloWorldInstructionBuilder, InstructionBuilder,
+        MemoInstructionBuilder, MemoryStore, MultisigConfig, PeerWorker, PeerWorkerInboundEvent,
+        RRElectionWorker, RRElectionWorkerType, RawInstructionBuilder, RedbStore, RelayRetryConfig,
+        RelayerFeePayerConfig, RpcPool, RpcPoolConfig, SigningPolicyConfig, SolanaRelayer,
+        SolanaSigner, Store, StorePruner, StoreWriter, WalletFunding, MAX_COMPUTE_UNIT_LIMIT,
     },
     ShutdownSignal, SignalHandleBuilder,
 };
@@ -59,25 +64,710 @@ pub struct NodeCmd {
     )]
     heartbeat_duration: humantime::Duration,
 
+    /// Delay before a newly elected signer takes over pending messages received
+    /// before its election
+    #[arg(
+        name = "signer-takeover-delay",
+        long,
+        env = env::SIGNER_TAKEOVER_DELAY,
+        default_value = "3s"
+    )]
+    signer_takeover_delay: humantime::Duration,
+
+    /// How far back a newly joining peer catches up relayed transactions and
+    /// pending signed messages from an established peer
+    #[arg(
+        name = "catchup-window",
+        long,
+        env = env::CATCHUP_WINDOW,
+        default_value = "10m"
+    )]
+    catchup_window: humantime::Duration,
+
+    #[command(flatten)]
+    multisig: Multisig,
+
+    #[command(flatten)]
+    frost: Frost,
+
+    #[command(flatten)]
+    policy: Policy,
+
+    #[command(flatten)]
+    durable_nonce: DurableNonceArgs,
+
+    #[command(flatten)]
+    compute_budget: ComputeBudgetArgs,
+
+    #[command(flatten)]
+    batch: Batch,
+
+    #[command(flatten)]
+    relay_retry: RelayRetry,
+
+    #[command(flatten)]
+    relayer_fee_payer: RelayerFeePayer,
+
+    #[command(flatten)]
+    store: StoreArgs,
+
+    #[command(flatten)]
+    funding: Funding,
+
     #[command(flatten)]
     solana: Solana,
 }
 
+#[derive(Args, Debug)]
+pub struct Multisig {
+    /// Number of peers (M) required to sign each transaction out of the
+    /// connected peers (N), including the signer, multisig is disabled if not
+    /// set
+    #[arg(
+        name = "multisig-threshold",
+        long,
+        env = env::MULTISIG_THRESHOLD,
+        value_parser = clap::value_parser!(u8).range(2..)
+    )]
+    threshold: Option<u8>,
+
+    /// How long the signer waits for a co-signer
+    #[arg(
+        name = "multisig-timeout",
+        long,
+        env = env::MULTISIG_TIMEOUT,
+        default_value = "5s"
+    )]
+    timeout: humantime::Duration,
+
+    /// How many signing rounds a message waits for enough co-signers before
+    /// it is rejected
+    #[arg(
+        name = "multisig-max-attempts",
+        long,
+        env = env::MULTISIG_MAX_ATTEMPTS,
+        default_value_t = 20,
+        value_parser = clap::value_parser!(u16).range(1..)
+    )]
+    max_attempts: u16,
+}
+
+impl Multisig {
+    fn config(&self) -> Option<MultisigConfig> {
+        self.threshold.map(|threshold| MultisigConfig {
+            threshold: threshold.into(),
+            timeout: *self.timeout,
+            max_attempts: self.max_attempts.into(),
+        })
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct Frost {
+    /// Number of participants (T) required to sign each transaction with the
+    /// FROST group key generated by `dkg`, FROST is disabled if not set
+    #[arg(
+        name = "frost-threshold",
+        long,
+        env = env::FROST_THRESHOLD,
+        value_parser = clap::value_parser!(u16).range(2..),
+        conflicts_with = "multisig-threshold"
+    )]
+    threshold: Option<u16>,
+
+    /// How long the coordinator waits for a participant in each round
+    #[arg(
+        name = "frost-timeout",
+        long,
+        env = env::FROST_TIMEOUT,
+        default_value = "5s"
+    )]
+    timeout: humantime::Duration,
+
+    /// Directory where the FROST key share is persisted
+    #[arg(
+        name = "frost-key-share-dir",
+        long,
+        env = env::FROST_KEY_SHARE_DIR,
+        default_value = "frost-key-shares"
+    )]
+    key_share_dir: PathBuf,
+
+    /// Allow `dkg` to replace the existing FROST key share, which loses the
+    /// group key and the funds at its address. The previous key share is
+    /// archived in the key share directory
+    #[arg(name = "frost-allow-rekey", long, env = env::FROST_ALLOW_REKEY)]
+    allow_rekey: bool,
+}
+
+impl Frost {
+    fn config(&self) -> Option<FrostConfig> {
+        self.threshold.map(|threshold| FrostConfig {
+            threshold,
+            timeout: *self.timeout,
+            key_share_dir: self.key_share_dir.clone(),
+            allow_rekey: self.allow_rekey,
+        })
+    }
+}
+
+/// Signing policy, messages violating any policy are rejected by the signer
+#[derive(Args, Debug)]
+pub struct Policy {
+    /// Comma separated program IDs which instructions can invoke, any program
+    /// is allowed if not set
+    #[arg(
+        name = "policy-allowed-program-ids",
+        long,
+        env = env::POLICY_ALLOWED_PROGRAM_IDS,
+        value_delimiter = ','
+    )]
+    allowed_program_ids: Vec<Pubkey>,
+
+    /// Max instructions of each message
+    #[arg(
+        name = "policy-max-instructions",
+        long,
+        env = env::POLICY_MAX_INSTRUCTIONS
+    )]
+    max_instructions: Option<usize>,
+
+    /// Max size of message data in bytes
+    #[arg(
+        name = "policy-max-payload-size",
+        long,
+        env = env::POLICY_MAX_PAYLOAD_SIZE
+    )]
+    max_payload_size: Option<usize>,
+
+    /// Max messages signed for each origin peer in the rate limit window
+    #[arg(
+        name = "policy-rate-limit",
+        long,
+        env = env::POLICY_RATE_LIMIT
+    )]
+    rate_limit: Option<usize>,
+
+    #[arg(
+        name = "policy-rate-limit-window",
+        long,
+        env = env::POLICY_RATE_LIMIT_WINDOW,
+        default_value = "60s"
+    )]
+    rate_limit_window: humantime::Duration,
+
+    /// Max lamports spent by the fee payer in the spend window, including
+    /// signature fees
+    #[arg(
+        name = "policy-spend-cap",
+        long,
+        env = env::POLICY_SPEND_CAP
+    )]
+    spend_cap: Option<u64>,
+
+    #[arg(
+        name = "policy-spend-window",
+        long,
+        env = env::POLICY_SPEND_WINDOW,
+        default_value = "1h"
+    )]
+    spend_window: humantime::Duration,
+}
+
+impl Policy {
+    fn config(&self) -> SigningPolicyConfig {
+        SigningPolicyConfig {
+            allowed_program_ids: self.allowed_program_ids.iter().copied().collect(),
+            max_instructions: self.max_instructions,
+            max_payload_size: self.max_payload_size,
+            rate_limit: self.rate_limit,
+            rate_limit_window: *self.rate_limit_window,
+            spend_cap: self.spend_cap,
+            spend_window: *self.spend_window,
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct DurableNonceArgs {
+    /// Number of durable nonce accounts owned by the node, transactions are
+    /// signed against durable nonces instead of recent blockhashes if set
+    #[arg(
+        name = "durable-nonce-accounts",
+        long,
+        env = env::DURABLE_NONCE_ACCOUNTS,
+        value_parser = clap::value_parser!(u8).range(1..),
+        conflicts_with = "frost-threshold"
+    )]
+    accounts: Option<u8>,
+
+    /// How often nonce values are refreshed
+    #[arg(
+        name = "durable-nonce-refresh-interval",
+        long,
+        env = env::DURABLE_NONCE_REFRESH_INTERVAL,
+        default_value = "5s"
+    )]
+    refresh_interval: humantime::Duration,
+
+    /// How long a nonce is leased by a signed transaction which does not
+    /// advance it
+    #[arg(
+        name = "durable-nonce-lease-timeout",
+        long,
+        env = env::DURABLE_NONCE_LEASE_TIMEOUT,
+        default_value = "2m"
+    )]
+    lease_timeout: humantime::Duration,
+}
+
+impl DurableNonceArgs {
+    fn config(&self) -> Option<DurableNonceConfig> {
+        self.accounts.map(|accounts| DurableNonceConfig {
+            accounts,
+            refresh_interval: *self.refresh_interval,
+            lease_timeout: *self.lease_timeout,
+        })
+    }
+}
+
+/// Compute budget instructions prepended to signed transactions
+#[derive(Args, Debug)]
+pub struct ComputeBudgetArgs {
+    /// Max compute units consumed by each transaction, the runtime default if
+    /// not set
+    #[arg(
+        name = "compute-unit-limit",
+        long,
+        env = env::COMPUTE_UNIT_LIMIT,
+        value_parser = clap::value_parser!(u32).range(1..=i64::from(MAX_COMPUTE_UNIT_LIMIT))
+    )]
+    limit: Option<u32>,
+
+    /// Fixed compute unit price in micro-lamports
+    #[arg(
+        name = "compute-unit-price",
+        long,
+        env = env::COMPUTE_UNIT_PRICE,
+        conflicts_with = "compute-unit-price-max"
+    )]
+    price: Option<u64>,
+
+    /// Ceiling of the compute unit price in micro-lamports, the price is
+    /// estimated from recent prioritization fees of the writable accounts if
+    /// set
+    #[arg(
+        name = "compute-unit-price-max",
+        long,
+        env = env::COMPUTE_UNIT_PRICE_MAX
+    )]
+    max_price: Option<u64>,
+}
+
+impl ComputeBudgetArgs {
+    const fn config(&self) -> ComputeBudgetConfig {
+        let unit_price = match (self.price, self.max_price) {
+            (Some(price), _) => Some(ComputeUnitPrice::Fixed(price)),
+            (None, Some(max)) => Some(ComputeUnitPrice::Dynamic { max }),
+            (None, None) => None,
+        };
+        ComputeBudgetConfig { unit_limit: self.limit, unit_price }
+    }
+}
+
+/// Pending messages packed into one transaction
+#[derive(Args, Debug)]
+pub struct Batch {
+    /// Max messages packed into one transaction, batching is disabled if not
+    /// set. Messages are split into more transactions if exceeding the
+    /// transaction size limit
+    #[arg(
+        name = "batch-max-messages",
+        long,
+        env = env::BATCH_MAX_MESSAGES,
+        value_parser = clap::value_parser!(u8).range(2..),
+        conflicts_with_all = ["multisig-threshold", "frost-threshold"]
+    )]
+    max_messages: Option<u8>,
+
+    /// How long the signer waits for more messages before signing a batch
+    /// which is not full
+    #[arg(
+        name = "batch-max-delay",
+        long,
+        env = env::BATCH_MAX_DELAY,
+        default_value = "200ms"
+    )]
+    max_delay: humantime::Duration,
+}
+
+impl Batch {
+    fn config(&self) -> Option<BatchConfig> {
+        self.max_messages.map(|max_messages| BatchConfig {
+            max_messages: max_messages.into(),
+            max_delay: *self.max_delay,
+        })
+    }
+}
+
+/// Retries of transactions which the relayer fails to send
+#[derive(Args, Debug)]
+pub struct RelayRetry {
+    /// Attempts to send a transaction before it is dead-lettered, failures
+    /// which never succeed are dead-lettered at the first attempt
+    #[arg(
+        name = "relay-max-attempts",
+        long,
+        env = env::RELAY_MAX_ATTEMPTS,
+        value_parser = clap::value_parser!(u32).range(1..),
+        default_value_t = 5
+    )]
+    max_attempts: u32,
+
+    /// Backoff after the first failed attempt, doubled after each attempt
+    #[arg(
+        name = "relay-initial-backoff",
+        long,
+        env = env::RELAY_INITIAL_BACKOFF,
+        default_value = "1s"
+    )]
+    initial_backoff: humantime::Duration,
+
+    /// Max backoff between attempts
+    #[arg(
+        name = "relay-max-backoff",
+        long,
+        env = env::RELAY_MAX_BACKOFF,
+        default_value = "30s"
+    )]
+    max_backoff: humantime::Duration,
+
+    /// Simulate transactions before sending them, transactions failing the
+    /// simulation are reported as `simulationFailed` instead of being sent
+    #[arg(name = "relay-simulate", long, env = env::RELAY_SIMULATE)]
+    simulate: bool,
+}
+
+impl RelayRetry {
+    fn config(&self) -> RelayRetryConfig {
+        RelayRetryConfig {
+            max_attempts: self.max_attempts,
+            initial_backoff: *self.initial_backoff,
+            max_backoff: *self.max_backoff,
+        }
+    }
+}
+
+/// Transaction fees paid by the relayer instead of the signer
+#[derive(Args, Debug)]
+pub struct RelayerFeePayer {
+    /// The relayer pays the fee of transactions partially signed by the
+    /// signer, so that only relayers need funding
+    #[arg(
+        name = "relayer-fee-payer",
+        long,
+        env = env::RELAYER_FEE_PAYER,
+        conflicts_with_all = ["multisig-threshold", "frost-threshold"]
+    )]
+    enabled: bool,
+
+    /// How long the signer waits for the fee payer signature of the relayer
+    #[arg(
+        name = "relayer-fee-payer-timeout",
+        long,
+        env = env::RELAYER_FEE_PAYER_TIMEOUT,
+        default_value = "5s"
+    )]
+    timeout: humantime::Duration,
+
+    /// Max lamports paid by the relayer for each transaction, including
+    /// signature fees and prioritization fees
+    #[arg(
+        name = "relayer-fee-payer-max-fee",
+        long,
+        env = env::RELAYER_FEE_PAYER_MAX_FEE,
+        default_value_t = 100_000
+    )]
+    max_fee: u64,
+}
+
+impl RelayerFeePayer {
+    fn config(&self) -> Option<RelayerFeePayerConfig> {
+        self.enabled
+            .then(|| RelayerFeePayerConfig { timeout: *self.timeout, max_fee: self.max_fee })
+    }
+}
+
+/// Storage of signed messages, relayed transactions and message lifecycles
+#[derive(Args, Debug)]
+pub struct StoreArgs {
+    /// Where records are kept
+    #[arg(
+        name = "store",
+        long,
+        env = env::STORE,
+        value_enum,
+        default_value_t = StoreKind::Memory
+    )]
+    kind: StoreKind,
+
+    /// Database file of `redb` store, created if it does not exist
+    #[arg(
+        name = "store-path",
+        long,
+        env = env::STORE_PATH,
+        default_value = "solana-tx-p2p.redb"
+    )]
+    path: PathBuf,
+
+    /// How long records are kept
+    #[arg(
+        name = "store-retention",
+        long,
+        env = env::STORE_RETENTION,
+        default_value = "24h"
+    )]
+    retention: humantime::Duration,
+
+    /// How often records older than the retention are removed
+    #[arg(
+        name = "store-prune-interval",
+        long,
+        env = env::STORE_PRUNE_INTERVAL,
+        default_value = "1m"
+    )]
+    prune_interval: humantime::Duration,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum StoreKind {
+    /// Records are kept in the memory and lost on restart
+    Memory,
+    /// Records are kept in an embedded `redb` database file
+    Redb,
+}
+
+impl StoreArgs {
+    fn open(&self) -> Result<Arc<dyn Store>> {
+        Ok(match self.kind {
+            StoreKind::Memory => Arc::new(MemoryStore::new()),
+            StoreKind::Redb => {
+                Arc::new(RedbStore::open(&self.path).context(error::OpenStoreSnafu)?)
+            }
+        })
+    }
+}
+
+/// Funding of the wallet of the node
+#[derive(Args, Debug)]
+pub struct Funding {
+    /// How the wallet is funded when the balance is below
+    /// `funding-min-balance`
+    #[arg(
+        name = "funding-strategy",
+        long,
+        env = env::FUNDING_STRATEGY,
+        value_enum,
+        default_value_t = FundingStrategyKind::Airdrop
+    )]
+    strategy: FundingStrategyKind,
+
+    /// Lamports requested by airdrop or transferred from the treasury
+    #[arg(
+        name = "funding-amount",
+        long,
+        env = env::FUNDING_AMOUNT,
+        default_value_t = 1_000_000_000
+    )]
+    amount: u64,
+
+    /// The wallet is funded if the balance is below this lamports
+    #[arg(
+        name = "funding-min-balance",
+        long,
+        env = env::FUNDING_MIN_BALANCE,
+        default_value_t = 100_000_000
+    )]
+    min_balance: u64,
+
+    /// How long funding waits for the transaction to be confirmed
+    #[arg(
+        name = "funding-timeout",
+        long,
+        env = env::FUNDING_TIMEOUT,
+        default_value = "60s"
+    )]
+    timeout: humantime::Duration,
+
+    /// Keypair file of the treasury, required by `transfer` strategy
+    #[arg(
+        name = "funding-treasury-keypair",
+        long,
+        env = env::FUNDING_TREASURY_KEYPAIR,
+        required_if_eq("funding-strategy", "transfer")
+    )]
+    treasury_keypair: Option<PathBuf>,
+
+    /// How often the balance is checked in background, the wallet is topped
+    /// up or an alert is raised if the balance is low. The balance is only
+    /// checked at startup if not set
+    #[arg(
+        name = "funding-monitor-interval",
+        long,
+        env = env::FUNDING_MONITOR_INTERVAL
+    )]
+    monitor_interval: Option<humantime::Duration>,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum FundingStrategyKind {
+    /// Never fund nor check the wallet
+    None,
+    /// Request airdrop from the faucet of the cluster
+    Airdrop,
+    /// Transfer from the treasury keypair
+    Transfer,
+    /// Require the minimum balance funded externally
+    MinBalance,
+}
+
+impl Funding {
+    fn config(&self) -> Result<FundingConfig> {
+        let strategy = match self.strategy {
+            FundingStrategyKind::None => FundingStrategy::None,
+            FundingStrategyKind::Airdrop => FundingStrategy::Airdrop { amount: self.amount },
+            FundingStrategyKind::Transfer => {
+                let path = self.treasury_keypair.clone().unwrap_or_default();
+                let treasury = read_keypair_file(&path).map_err(|err| {
+                    error::ReadTreasuryKeypairSnafu { path, message: err.to_string() }.build()
+                })?;
+                FundingStrategy::Transfer { treasury: Arc::new(treasury), amount: self.amount }
+            }
+            FundingStrategyKind::MinBalance => FundingStrategy::MinBalance,
+        };
+        Ok(FundingConfig {
+            strategy,
+            min_balance: self.min_balance,
+            timeout: *self.timeout,
+            monitor_interval: self.monitor_interval.as_deref().copied(),
+        })
+    }
+}
+
 #[derive(Args, Debug)]
 pub struct Solana {
+    /// Program ID of `solana-hello-world`, required by `hello-world`
+    /// instruction builder
     #[arg(
         name = "solana-program-id",
         long,
         env = env::SOLANA_PROGRAM_ID
     )]
-    program_id: Pubkey,
+    program_id: Option<Pubkey>,
 
+    /// How to turn a message into the instructions of a transaction
+    #[arg(
+        name = "solana-instruction-builder",
+        long,
+        env = env::SOLANA_INSTRUCTION_BUILDER,
+        value_enum,
+        default_value_t = InstructionBuilderKind::HelloWorld
+    )]
+    instruction_builder: InstructionBuilderKind,
+
+    /// Comma separated RPC endpoints in order of preference, requests fail
+    /// over to the next endpoint if the endpoint is unreachable or unhealthy
     #[arg(
         name = "solana-rpc-url",
         long,
-        env = env::SOLANA_RPC_URL
+        env = env::SOLANA_RPC_URL,
+        value_delimiter = ',',
+        required = true
+    )]
+    rpc_urls: Vec<String>,
+
+    /// Comma separated RPC endpoints dedicated to sending transactions in
+    /// order of preference, transactions are sent through `solana-rpc-url` if
+    /// not set
+    #[arg(
+        name = "solana-send-rpc-url",
+        long,
+        env = env::SOLANA_SEND_RPC_URL,
+        value_delimiter = ','
+    )]
+    send_rpc_urls: Vec<String>,
+
+    /// How often the health and the slot of RPC endpoints are checked
+    #[arg(
+        name = "solana-rpc-health-check-interval",
+        long,
+        env = env::SOLANA_RPC_HEALTH_CHECK_INTERVAL,
+        default_value = "5s"
+    )]
+    rpc_health_check_interval: humantime::Duration,
+
+    /// RPC endpoints lagging behind the highest slot of all endpoints by more
+    /// slots are unhealthy
+    #[arg(
+        name = "solana-rpc-max-slot-lag",
+        long,
+        env = env::SOLANA_RPC_MAX_SLOT_LAG,
+        default_value_t = 50
+    )]
+    rpc_max_slot_lag: u64,
+
+    /// How often the latest blockhash is polled
+    #[arg(
+        name = "solana-blockhash-refresh-interval",
+        long,
+        env = env::SOLANA_BLOCKHASH_REFRESH_INTERVAL,
+        default_value = "2s"
     )]
-    rpc_url: String,
+    blockhash_refresh_interval: humantime::Duration,
+
+    /// Comma separated address lookup tables, v0 transactions loading accounts
+    /// from the tables are signed instead of legacy transactions if set.
+    /// Co-signed transactions are always legacy transactions
+    #[arg(
+        name = "solana-address-lookup-tables",
+        long,
+        env = env::SOLANA_ADDRESS_LOOKUP_TABLES,
+        value_delimiter = ',',
+        conflicts_with_all = ["multisig-threshold", "frost-threshold"]
+    )]
+    address_lookup_tables: Vec<Pubkey>,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum InstructionBuilderKind {
+    /// Borsh encoded string for `solana-hello-world` program
+    HelloWorld,
+    /// UTF-8 message for SPL Memo program
+    Memo,
+    /// Bincode serialized instructions supplied by client
+    Raw,
+}
+
+impl Solana {
+    fn rpc_pool_config(&self) -> RpcPoolConfig {
+        RpcPoolConfig {
+            read_urls: self.rpc_urls.clone(),
+            send_urls: self.send_rpc_urls.clone(),
+            health_check_interval: *self.rpc_health_check_interval,
+            max_slot_lag: self.rpc_max_slot_lag,
+        }
+    }
+
+    fn instruction_builder(&self) -> Result<Arc<dyn InstructionBuilder>> {
+        Ok(match self.instruction_builder {
+            InstructionBuilderKind::HelloWorld => {
+                let program_id = self.program_id.context(error::MissingProgramIdSnafu)?;
+                Arc::new(HelloWorldInstructionBuilder::new(program_id))
+            }
+            InstructionBuilderKind::Memo => Arc::new(MemoInstructionBuilder),
+            InstructionBuilderKind::Raw => Arc::new(RawInstructionBuilder),
+        })
+    }
 }
 
 impl NodeCmd {
@@ -100,7 +790,7 @@ impl NodeCmd {
             let (stdin_sender, stdin_receiver) = mpsc::channel(10);
 
             tracing::info!("Initializing P2P Node");
-            let _peer_worker_inbound_sender =
+            let (_peer_worker_inbound_sender, _store, _events) =
                 self.start_node(&mut join_set, shutdown_signal, stdin_receiver).await?;
 
             // stdin reader
@@ -147,15 +837,29 @@ impl NodeCmd {
         join_set: &mut JoinSet<solana_tx_p2p::Result<()>>,
         shutdown_signal: ShutdownSignal,
         stdin_receiver: mpsc::Receiver<String>,
-    ) -> Result<mpsc::Sender<PeerWorkerInboundEvent>> {
+    ) -> Result<(mpsc::Sender<PeerWorkerInboundEvent>, Arc<dyn Store>, EventBroadcaster)> {
         let Self {
             message_duration,
             relay_leader_duration,
             signing_leader_duration,
             heartbeat_duration,
+            signer_takeover_delay,
+            catchup_window,
+            multisig,
+            frost,
+            policy,
+            durable_nonce,
+            compute_budget,
+            batch,
+            relay_retry,
+            relayer_fee_payer,
+            store,
+            funding,
             solana,
         } = self;
 
+        let instruction_builder = solana.instruction_builder()?;
+
         let peers = Arc::new(RwLock::new(Vec::new()));
         let signer = Arc::new(RwLock::new("signer".to_string()));
         let relayer = Arc::new(RwLock::new("relayer".to_string()));
@@ -170,12 +874,83 @@ impl NodeCmd {
 
         let (solana_signer_inbound_sender, solana_signer_inbound_receiver) = mpsc::channel(100);
         let (solana_relayer_inbound_sender, solana_relayer_inbound_receiver) = mpsc::channel(100);
+        let (confirmation_tracker_inbound_sender, confirmation_tracker_inbound_receiver) =
+            mpsc::channel(100);
         let (keypair, solana_keypair) = PeerWorker::generate_keypair();
 
+        tracing::info!("Initializing Solana RPC pool");
+        let rpc_pool = RpcPool::new(solana.rpc_pool_config());
+        join_set
+            .build_task()
+            .name("rpc pool")
+            .spawn(rpc_pool.clone().start(shutdown_signal.clone()).err_into())
+            .context(error::SpawnSnafu { name: "rpc pool".to_string() })?;
+
         tracing::info!("Initializing Solana client");
-        let solana_client = create_solana_client(&solana.rpc_url, solana_keypair.clone())
-            .await
-            .context(error::CreateSolanaClientSnafu)?;
+        let solana_client = create_solana_client(rpc_pool);
+
+        tracing::info!("Funding wallet");
+        let funding = funding.config()?;
+        let wallet_funding =
+            WalletFunding::new(funding.clone(), solana_client.clone(), solana_keypair.pubkey());
+        wallet_funding.fund().await.context(error::FundWalletSnafu)?;
+        join_set
+            .build_task()
+            .name("balance monitor")
+            .spawn(wallet_funding.start(shutdown_signal.clone()).err_into())
+            .context(error::SpawnSnafu { name: "balance monitor".to_string() })?;
+
+        tracing::info!("Initializing blockhash cache");
+        let blockhash_cache =
+            BlockhashCache::new(solana_client.clone(), *solana.blockhash_refresh_interval);
+        join_set
+            .build_task()
+            .name("blockhash cache")
+            .spawn(blockhash_cache.clone().start(shutdown_signal.clone()).err_into())
+            .context(error::SpawnSnafu { name: "blockhash cache".to_string() })?;
+
+        let durable_nonce = match durable_nonce.config() {
+            Some(config) => {
+                tracing::info!("Initializing durable nonce accounts");
+                let durable_nonce =
+                    DurableNonce::new(config, solana_keypair.clone(), solana_client.clone())
+                        .await
+                        .context(error::CreateDurableNonceSnafu)?;
+                Some(durable_nonce)
+            }
+            None => None,
+        };
+
+        let address_lookup_tables = if solana.address_lookup_tables.is_empty() {
+            None
+        } else {
+            tracing::info!("Fetching address lookup tables");
+            let address_lookup_tables =
+                fetch_address_lookup_tables(&solana_client, &solana.address_lookup_tables)
+                    .await
+                    .context(error::FetchAddressLookupTablesSnafu)?;
+            Some(address_lookup_tables)
+        };
+
+        tracing::info!("Initializing store");
+        let retention = store.retention;
+        let prune_interval = store.prune_interval;
+        let store = store.open()?;
+        let (store_write_sender, store_write_receiver) = mpsc::channel(1000);
+        let store_writer = StoreWriter::new(store.clone(), store_write_receiver);
+        join_set
+            .build_task()
+            .name("store writer")
+            .spawn(store_writer.start().err_into())
+            .context(error::SpawnSnafu { name: "store writer".to_string() })?;
+        let store_pruner = StorePruner::new(store.clone(), *retention, *prune_interval);
+        join_set
+            .build_task()
+            .name("store pruner")
+            .spawn(store_pruner.start(shutdown_signal.clone()).err_into())
+            .context(error::SpawnSnafu { name: "store pruner".to_string() })?;
+
+        let events = EventBroadcaster::new(shutdown_signal.clone());
 
         tracing::info!("Initializing PeerWorker");
         let peer_worker = PeerWorker::new(
@@ -189,6 +964,10 @@ impl NodeCmd {
             signer_election_worker_inbound_sender,
             solana_relayer_inbound_sender,
             solana_signer_inbound_sender,
+            store.clone(),
+            store_write_sender,
+            events.clone(),
+            *catchup_window,
             solana_client.clone(),
         );
         let peer_worker_inbound_sender = peer_worker.peer_worker_inbound_sender();
@@ -210,6 +989,7 @@ impl NodeCmd {
             relayer_heartbeat_receiver,
             peers.clone(),
             peer_worker_inbound_sender.clone(),
+            events.clone(),
         );
         join_set
             .build_task()
@@ -225,8 +1005,9 @@ impl NodeCmd {
             *signing_leader_duration,
             signer_election_worker_inbound_receiver,
             signer_heartbeat_receiver,
-            peers,
+            peers.clone(),
             peer_worker_inbound_sender.clone(),
+            events.clone(),
         );
         join_set
             .build_task()
@@ -237,12 +1018,26 @@ impl NodeCmd {
         tracing::info!("Initializing SolanaSigner");
         let solana_signer = SolanaSigner::new(
             peer_id,
-            signer,
+            signer.clone(),
+            relayer.clone(),
             solana_keypair.clone(),
+            peers,
             peer_worker_inbound_sender.clone(),
-            solana.program_id,
+            events.clone(),
+            instruction_builder,
             solana_client.clone(),
             solana_signer_inbound_receiver,
+            *signer_takeover_delay,
+            multisig.config(),
+            frost.config(),
+            funding,
+            policy.config(),
+            durable_nonce,
+            blockhash_cache.clone(),
+            address_lookup_tables,
+            compute_budget.config(),
+            batch.config(),
+            relayer_fee_payer.config(),
         );
         join_set
             .build_task()
@@ -250,13 +1045,37 @@ impl NodeCmd {
             .spawn(solana_signer.start(shutdown_signal.clone()).err_into())
             .context(error::SpawnSnafu { name: "solana signer".to_string() })?;
 
+        tracing::info!("Initializing confirmation tracker");
+        let confirmation_tracker = ConfirmationTracker::new(
+            peer_id,
+            peer_worker_inbound_sender.clone(),
+            events.clone(),
+            solana_client.clone(),
+            blockhash_cache.clone(),
+            confirmation_tracker_inbound_receiver,
+        );
+        join_set
+            .build_task()
+            .name("confirmation tracker")
+            .spawn(confirmation_tracker.start(shutdown_signal.clone()).err_into())
+            .context(error::SpawnSnafu { name: "confirmation tracker".to_string() })?;
+
         tracing::info!("Initializing SolanaRelayer");
         let solana_relayer = SolanaRelayer::new(
             peer_id,
             relayer,
+            signer,
+            solana_keypair,
             peer_worker_inbound_sender.clone(),
+            events.clone(),
             solana_client,
+            blockhash_cache,
             solana_relayer_inbound_receiver,
+            confirmation_tracker_inbound_sender,
+            store.clone(),
+            relay_retry.config(),
+            relay_retry.simulate,
+            relayer_fee_payer.config(),
         );
         join_set
             .build_task()
@@ -292,6 +1111,6 @@ impl NodeCmd {
             )
             .context(error::SpawnSnafu { name: "heartbeat trigger".to_string() })?;
 
-        Ok(peer_worker_inbound_sender)
+        Ok((peer_worker_inbound_sender, store, events))
     }
 }
@@ -20,9 +20,66 @@ prefixed_string_constant! {
     pub const RELAY_LEADER_DURATION;
     pub const SIGNING_LEADER_DURATION;
     pub const HEARTBEAT_DURATION;
+    pub const SIGNER_TAKEOVER_DELAY;
+    pub const CATCHUP_WINDOW;
+
+    pub const MULTISIG_THRESHOLD;
+    pub const MULTISIG_TIMEOUT;
+    pub const MULTISIG_MAX_ATTEMPTS;
+
+    pub const FROST_THRESHOLD;
+    pub const FROST_TIMEOUT;
+    pub const FROST_KEY_SHARE_DIR;
+    pub const FROST_ALLOW_REKEY;
+
+    pub const POLICY_ALLOWED_PROGRAM_IDS;
+    pub const POLICY_MAX_INSTRUCTIONS;
+    pub const POLICY_MAX_PAYLOAD_SIZE;
+    pub const POLICY_RATE_LIMIT;
+    pub const POLICY_RATE_LIMIT_WINDOW;
+    pub const POLICY_SPEND_CAP;
+    pub const POLICY_SPEND_WINDOW;
+
+    pub const DURABLE_NONCE_ACCOUNTS;
+    pub const DURABLE_NONCE_REFRESH_INTERVAL;
+    pub const DURABLE_NONCE_LEASE_TIMEOUT;
+
+    pub const COMPUTE_UNIT_LIMIT;
+    pub const COMPUTE_UNIT_PRICE;
+    pub const COMPUTE_UNIT_PRICE_MAX;
+
+    pub const BATCH_MAX_MESSAGES;
+    pub const BATCH_MAX_DELAY;
+
+    pub const RELAY_MAX_ATTEMPTS;
+    pub const RELAY_INITIAL_BACKOFF;
+    pub const RELAY_MAX_BACKOFF;
+    pub const RELAY_SIMULATE;
+
+    pub const RELAYER_FEE_PAYER;
+    pub const RELAYER_FEE_PAYER_TIMEOUT;
+    pub const RELAYER_FEE_PAYER_MAX_FEE;
+
+    pub const STORE;
+    pub const STORE_PATH;
+    pub const STORE_RETENTION;
+    pub const STORE_PRUNE_INTERVAL;
+
+    pub const FUNDING_STRATEGY;
+    pub const FUNDING_AMOUNT;
+    pub const FUNDING_MIN_BALANCE;
+    pub const FUNDING_TIMEOUT;
+    pub const FUNDING_TREASURY_KEYPAIR;
+    pub const FUNDING_MONITOR_INTERVAL;
 
     pub const SOLANA_PROGRAM_ID;
     pub const SOLANA_RPC_URL;
+    pub const SOLANA_SEND_RPC_URL;
+    pub const SOLANA_RPC_HEALTH_CHECK_INTERVAL;
+    pub const SOLANA_RPC_MAX_SLOT_LAG;
+    pub const SOLANA_BLOCKHASH_REFRESH_INTERVAL;
+    pub const SOLANA_ADDRESS_LOOKUP_TABLES;
+    pub const SOLANA_INSTRUCTION_BUILDER;
 }
 
 #[macro_export]
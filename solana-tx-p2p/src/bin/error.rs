@@ -1,8 +1,7 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, path::PathBuf};
 
-use http::Uri;
 use snafu::{Backtrace, Snafu};
-use solana_tx_p2p::fmt_backtrace_with_source;
+use solana_tx_p2p::{fmt_backtrace, fmt_backtrace_with_source};
 
 /// Result type alias for the CLI.
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -36,14 +35,33 @@ pub enum Error {
     Spawn { name: Cow<'static, str>, source: std::io::Error, backtrace: Backtrace },
 
     #[snafu(display(
-        "Can not connect grpc channel for `{endpoint}`{}",
+        "Fail to read treasury keypair from `{}`: {message}{}",
+        path.display(),
+        fmt_backtrace(backtrace)
+    ))]
+    ReadTreasuryKeypair { path: PathBuf, message: String, backtrace: Backtrace },
+
+    #[snafu(display("Fail to open store{}", fmt_backtrace_with_source(backtrace, source)))]
+    OpenStore { source: solana_tx_p2p::service::error::Error, backtrace: Backtrace },
+
+    #[snafu(display("Fail to fund wallet{}", fmt_backtrace_with_source(backtrace, source)))]
+    FundWallet { source: solana_tx_p2p::service::error::Error, backtrace: Backtrace },
+
+    #[snafu(display(
+        "Fail to create durable nonce accounts{}",
         fmt_backtrace_with_source(backtrace, source)
     ))]
-    ConnectGrpcChannel { endpoint: Uri, source: tonic::transport::Error, backtrace: Backtrace },
+    CreateDurableNonce { source: solana_tx_p2p::service::error::Error, backtrace: Backtrace },
 
     #[snafu(display(
-        "Can not create solana client{}",
+        "Fail to fetch address lookup tables{}",
         fmt_backtrace_with_source(backtrace, source)
     ))]
-    CreateSolanaClient { source: solana_tx_p2p::service::error::Error, backtrace: Backtrace },
+    FetchAddressLookupTables { source: solana_tx_p2p::service::error::Error, backtrace: Backtrace },
+
+    #[snafu(display(
+        "`solana-program-id` is required by `hello-world` instruction builder{}",
+        fmt_backtrace(backtrace)
+    ))]
+    MissingProgramId { backtrace: Backtrace },
 }
@@ -1,9 +1,14 @@
+use std::time::SystemTime;
+
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures_util::{stream::BoxStream, StreamExt as _};
 use tonic::{Request, Response, Result, Status};
 
 use crate::{
-    proto::peer::{self as proto},
-    service,
+    model,
+    proto::peer::{self as proto, v1::get_message_lifecycle_request::Key},
+    service::{self, error::ErrorKind},
 };
 
 pub struct PeerService<T>
@@ -25,33 +30,55 @@ impl<T> proto::v1::PeerService for PeerService<T>
 where
     T: service::PeerService + Send + Sync + 'static,
 {
+    type SubscribeEventsStream = BoxStream<'static, Result<proto::NodeEvent, Status>>;
+
+    async fn submit_message(
+        &self,
+        request: Request<proto::v1::SubmitMessageRequest>,
+    ) -> Result<Response<proto::v1::SubmitMessageResponse>, Status> {
+        let data = request.into_inner().data;
+        let message_id = self.inner.submit_message(data).await?;
+
+        Ok(Response::new(proto::v1::SubmitMessageResponse { message_id: message_id.to_string() }))
+    }
+
     async fn discovery_peers(
         &self,
-        _request: Request<()>,
+        request: Request<proto::v1::ListQuery>,
     ) -> Result<Response<proto::v1::Peers>, Status> {
-        let peers = self.inner.discovery_peers().await?;
+        let query = list_query(request.into_inner())?;
+        let peers = self.inner.discovery_peers(&query).await?;
 
-        Ok(Response::new(proto::v1::Peers { peers }))
+        Ok(Response::new(proto::v1::Peers {
+            peers: peers.items,
+            next_page_token: peers.next_page_token.unwrap_or_default(),
+        }))
     }
 
     async fn list_signed_messages(
         &self,
-        _request: Request<()>,
-    ) -> Result<Response<proto::Transactions>, Status> {
-        let signed_messages = self.inner.list_signed_messages().await?;
+        request: Request<proto::v1::ListQuery>,
+    ) -> Result<Response<proto::v1::SignedMessages>, Status> {
+        let query = list_query(request.into_inner())?;
+        let signed_messages = self.inner.list_signed_messages(&query).await?;
 
-        Ok(Response::new(proto::Transactions {
-            transactions: signed_messages.into_iter().map(Into::into).collect(),
+        Ok(Response::new(proto::v1::SignedMessages {
+            transactions: signed_messages.items.into_iter().map(Into::into).collect(),
+            next_page_token: signed_messages.next_page_token.unwrap_or_default(),
         }))
     }
 
     async fn list_relayed_transactions(
         &self,
-        _request: Request<()>,
+        request: Request<proto::v1::ListQuery>,
     ) -> Result<Response<proto::v1::RelayTransactions>, Status> {
-        let signatures = self.inner.list_relayed_transactions().await?;
+        let query = list_query(request.into_inner())?;
+        let relayed_transactions = self.inner.list_relayed_transactions(&query).await?;
 
-        Ok(Response::new(proto::v1::RelayTransactions { signatures }))
+        Ok(Response::new(proto::v1::RelayTransactions {
+            relayed_transactions: relayed_transactions.items.into_iter().map(Into::into).collect(),
+            next_page_token: relayed_transactions.next_page_token.unwrap_or_default(),
+        }))
     }
 
     async fn get_relayed_transaction(
@@ -63,4 +90,100 @@ where
 
         Ok(Response::new(tx.into()))
     }
+
+    async fn get_message_lifecycle(
+        &self,
+        request: Request<proto::v1::GetMessageLifecycleRequest>,
+    ) -> Result<Response<proto::MessageLifecycle>, Status> {
+        let lifecycle = match request.into_inner().key {
+            Some(Key::MessageId(message_id)) => {
+                self.inner.get_message_lifecycle(&message_id).await?
+            }
+            Some(Key::Signature(signature)) => {
+                self.inner.get_message_lifecycle_by_signature(&signature).await?
+            }
+            None => {
+                return Err(ErrorKind::InvalidArgument.grpc_status(
+                    "MISSING_LIFECYCLE_KEY",
+                    "`message_id` or `signature` is required".to_string(),
+                ))
+            }
+        };
+
+        Ok(Response::new(lifecycle.into()))
+    }
+
+    async fn list_dead_letters(
+        &self,
+        _request: Request<()>,
+    ) -> Result<Response<proto::v1::DeadLetters>, Status> {
+        let dead_letters = self.inner.list_dead_letters().await?;
+
+        Ok(Response::new(proto::v1::DeadLetters {
+            dead_letters: dead_letters.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn replay_dead_letter(
+        &self,
+        request: Request<proto::v1::ReplayDeadLetterRequest>,
+    ) -> Result<Response<proto::DeadLetter>, Status> {
+        let signature = request.into_inner().signature;
+        let dead_letter = self.inner.replay_dead_letter(&signature).await?;
+
+        Ok(Response::new(dead_letter.into()))
+    }
+
+    async fn subscribe_events(
+        &self,
+        request: Request<proto::v1::SubscribeEventsRequest>,
+    ) -> Result<Response<Self::SubscribeEventsStream>, Status> {
+        let request = request.into_inner();
+        let types = request
+            .types()
+            .map(|event_type| {
+                model::NodeEventType::try_from(event_type).map_err(|event_type| {
+                    ErrorKind::InvalidArgument.grpc_status(
+                        "INVALID_EVENT_TYPE",
+                        format!("Event type `{}` is invalid", event_type.as_str_name()),
+                    )
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        let filter = model::EventFilter { types, from_sequence: request.from_sequence };
+        let subscription = self.inner.subscribe_events(filter).await?;
+
+        Ok(Response::new(
+            subscription
+                .into_stream()
+                .map(|result| result.map(Into::into).map_err(Into::into))
+                .boxed(),
+        ))
+    }
+}
+
+/// Empty fields of the request are unset in the query
+fn list_query(query: proto::v1::ListQuery) -> Result<model::ListQuery, Status> {
+    let non_empty = |value: String| Some(value).filter(|value| !value.is_empty());
+    let time = |timestamp: Option<prost_types::Timestamp>, field: &str| {
+        timestamp
+            .map(|timestamp| {
+                SystemTime::try_from(timestamp).map(DateTime::<Utc>::from).map_err(|err| {
+                    ErrorKind::InvalidArgument
+                        .grpc_status("INVALID_TIMESTAMP", format!("`{field}` is invalid: {err}"))
+                })
+            })
+            .transpose()
+    };
+
+    let order = query.order().into();
+    Ok(model::ListQuery {
+        since: time(query.since, "since")?,
+        until: time(query.until, "until")?,
+        order,
+        page_token: non_empty(query.page_token),
+        limit: Some(query.limit).filter(|limit| *limit > 0),
+        signer: non_empty(query.signer),
+        relayer: non_empty(query.relayer),
+    })
 }
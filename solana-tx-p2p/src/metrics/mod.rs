@@ -2,10 +2,106 @@ use std::net::SocketAddr;
 
 use axum::{response::IntoResponse, routing, routing::Router};
 use hyper::server::conn::AddrIncoming;
+use lazy_static::lazy_static;
+use prometheus::{
+    register_histogram_vec, register_int_counter_vec, register_int_gauge, register_int_gauge_vec,
+    Encoder as _, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, TextEncoder,
+};
 use snafu::ResultExt;
 
 use crate::{app_state::AppState, error, error::Result, ShutdownSignal};
 
+lazy_static! {
+    /// Co-sign requests sent by the multisig coordinator, labeled by `result`:
+    /// `signed`, `rejected`, `timeout` or `failed`
+    pub static ref MULTISIG_COSIGN_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "solana_tx_p2p_multisig_cosign_requests_total",
+        "Co-sign requests sent by the multisig coordinator",
+        &["result"]
+    )
+    .expect("metric can be registered");
+
+    /// Messages rejected in multisig mode as not enough co-signers are
+    /// available after all attempts, labeled by `reason`:
+    /// `insufficient_signers` or `missing_signers`
+    pub static ref MULTISIG_UNSIGNED_MESSAGES: IntCounterVec = register_int_counter_vec!(
+        "solana_tx_p2p_multisig_unsigned_messages_total",
+        "Messages which can not be signed in multisig mode",
+        &["reason"]
+    )
+    .expect("metric can be registered");
+
+    /// Events which the peer worker drops as the receiver falls behind,
+    /// labeled by `receiver`: `signer`, `relayer` or `store`
+    pub static ref PEER_WORKER_DROPPED_EVENTS: IntCounterVec = register_int_counter_vec!(
+        "solana_tx_p2p_peer_worker_dropped_events_total",
+        "Events which the peer worker drops as the receiver falls behind",
+        &["receiver"]
+    )
+    .expect("metric can be registered");
+
+    /// Messages rejected by signing policy, labeled by `reason`:
+    /// `program_not_allowed`, `too_many_instructions`, `payload_too_large`,
+    /// `rate_limited` or `spend_cap_exceeded`
+    pub static ref POLICY_REJECTED_MESSAGES: IntCounterVec = register_int_counter_vec!(
+        "solana_tx_p2p_policy_rejected_messages_total",
+        "Messages rejected by signing policy, invalid instructions or the transaction size limit",
+        &["reason"]
+    )
+    .expect("metric can be registered");
+
+    /// Latency of requests to Solana RPC endpoints, labeled by `endpoint` and
+    /// `method`
+    pub static ref RPC_REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+        "solana_tx_p2p_rpc_request_duration_seconds",
+        "Latency of requests to Solana RPC endpoints",
+        &["endpoint", "method"]
+    )
+    .expect("metric can be registered");
+
+    /// Failed requests to Solana RPC endpoints, labeled by `endpoint` and
+    /// `method`
+    pub static ref RPC_REQUEST_ERRORS: IntCounterVec = register_int_counter_vec!(
+        "solana_tx_p2p_rpc_request_errors_total",
+        "Failed requests to Solana RPC endpoints",
+        &["endpoint", "method"]
+    )
+    .expect("metric can be registered");
+
+    /// Whether Solana RPC endpoints are healthy, labeled by `endpoint`
+    pub static ref RPC_ENDPOINT_HEALTHY: IntGaugeVec = register_int_gauge_vec!(
+        "solana_tx_p2p_rpc_endpoint_healthy",
+        "Whether Solana RPC endpoints are healthy",
+        &["endpoint"]
+    )
+    .expect("metric can be registered");
+
+    /// Slots which Solana RPC endpoints lag behind the highest slot of all
+    /// endpoints, labeled by `endpoint`
+    pub static ref RPC_ENDPOINT_SLOT_LAG: IntGaugeVec = register_int_gauge_vec!(
+        "solana_tx_p2p_rpc_endpoint_slot_lag",
+        "Slots which Solana RPC endpoints lag behind the highest slot",
+        &["endpoint"]
+    )
+    .expect("metric can be registered");
+
+    /// Balance of the wallet of the node in lamports
+    pub static ref WALLET_BALANCE: IntGauge = register_int_gauge!(
+        "solana_tx_p2p_wallet_balance_lamports",
+        "Balance of the wallet of the node in lamports"
+    )
+    .expect("metric can be registered");
+
+    /// Checks finding the balance of the wallet below the minimum balance,
+    /// labeled by `action`: `topped_up`, `top_up_failed` or `alert`
+    pub static ref WALLET_LOW_BALANCE: IntCounterVec = register_int_counter_vec!(
+        "solana_tx_p2p_wallet_low_balance_total",
+        "Checks finding the balance of the wallet below the minimum balance",
+        &["action"]
+    )
+    .expect("metric can be registered");
+}
+
 /// # Errors
 /// * if server error
 pub async fn run<S>(
@@ -20,6 +116,7 @@ where
         .route("/startz", routing::get(startz))
         .route("/livez", routing::get(livez))
         .route("/readyz", routing::get(readyz))
+        .route("/metrics", routing::get(metrics))
         .with_state(app_state)
         .into_make_service_with_connect_info::<SocketAddr>();
 
@@ -50,3 +147,14 @@ async fn livez() -> impl IntoResponse { "TODO" }
 // SAFETY: clippy::unused_async: axum requires such function signature
 #[allow(clippy::unused_async)]
 async fn readyz() -> impl IntoResponse { "TODO" }
+
+// SAFETY: clippy::unused_async: axum requires such function signature
+#[allow(clippy::unused_async)]
+async fn metrics() -> impl IntoResponse {
+    let mut buffer = Vec::new();
+    if let Err(err) = TextEncoder::new().encode(&prometheus::gather(), &mut buffer) {
+        tracing::error!("Fail to encode metrics: {err}");
+    }
+
+    buffer
+}
@@ -0,0 +1,10 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Leader elected by the round-robin election and the end of its term,
+/// gossiped to peers so that they follow the same leader
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderSyncInfo {
+    pub leader: String,
+    pub next_round_time: DateTime<Utc>,
+}
@@ -0,0 +1,246 @@
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    model::{CommitmentStatus, RelayedTransaction},
+    proto::peer as proto,
+};
+
+/// Activity of this node, numbered in the order it is published
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeEvent {
+    /// sequence number of the event on this node, starting from 1
+    pub sequence: u64,
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub kind: NodeEventKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum NodeEventKind {
+    /// A message is submitted to this node or gossiped by a peer
+    MessageReceived(MessageReceived),
+    /// The signer of this node signs a transaction
+    TransactionSigned(TransactionSigned),
+    /// The relayer of this node sends a transaction
+    TransactionRelayed(RelayedTransaction),
+    /// The commitment status of a transaction relayed by this node changes
+    ConfirmationStatusChanged(ConfirmationStatusChanged),
+    /// A relayer or signer leader is elected or synced from a peer
+    LeaderElected(LeaderElected),
+    PeerJoined(PeerConnection),
+    PeerLeft(PeerConnection),
+}
+
+impl NodeEventKind {
+    #[must_use]
+    pub const fn event_type(&self) -> NodeEventType {
+        match self {
+            Self::MessageReceived(_) => NodeEventType::MessageReceived,
+            Self::TransactionSigned(_) => NodeEventType::TransactionSigned,
+            Self::TransactionRelayed(_) => NodeEventType::TransactionRelayed,
+            Self::ConfirmationStatusChanged(_) => NodeEventType::ConfirmationStatusChanged,
+            Self::LeaderElected(_) => NodeEventType::LeaderElected,
+            Self::PeerJoined(_) => NodeEventType::PeerJoined,
+            Self::PeerLeft(_) => NodeEventType::PeerLeft,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum NodeEventType {
+    MessageReceived,
+    TransactionSigned,
+    TransactionRelayed,
+    ConfirmationStatusChanged,
+    LeaderElected,
+    PeerJoined,
+    PeerLeft,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageReceived {
+    pub message_id: String,
+    /// peer ID of the node which the message is submitted to
+    pub origin: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionSigned {
+    pub signature: String,
+    pub message_ids: Vec<String>,
+    pub signer: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfirmationStatusChanged {
+    pub signature: String,
+    pub slot: Option<u64>,
+    pub status: CommitmentStatus,
+    /// set if the transaction fails
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LeaderElected {
+    pub role: LeaderRole,
+    pub leader: String,
+    /// the next leader is elected at the time
+    pub next_round_time: DateTime<Utc>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum LeaderRole {
+    Relayer,
+    Signer,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerConnection {
+    pub peer_id: String,
+}
+
+/// Events subscribed by a client
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EventFilter {
+    /// events of all types are subscribed if empty
+    pub types: Vec<NodeEventType>,
+    /// events kept by this node from the sequence number are replayed before
+    /// new events, only new events are subscribed if unset
+    pub from_sequence: Option<u64>,
+}
+
+/// Filters of event streams of the REST API
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct EventQuery {
+    /// comma separated event types, e.g. `peerJoined,peerLeft`, events of all
+    /// types are streamed if unset
+    pub types: Option<String>,
+    /// events kept by this node from the sequence number are replayed before
+    /// new events. Server-Sent Events resume after `Last-Event-ID` if unset
+    pub from_sequence: Option<u64>,
+}
+
+impl EventFilter {
+    #[must_use]
+    pub fn matches(&self, event: &NodeEvent) -> bool {
+        self.types.is_empty() || self.types.contains(&event.kind.event_type())
+    }
+}
+
+impl From<NodeEvent> for proto::NodeEvent {
+    fn from(NodeEvent { sequence, timestamp, kind }: NodeEvent) -> Self {
+        use proto::node_event::Event;
+
+        let event = match kind {
+            NodeEventKind::MessageReceived(MessageReceived { message_id, origin }) => {
+                Event::MessageReceived(proto::MessageReceived { message_id, origin })
+            }
+            NodeEventKind::TransactionSigned(TransactionSigned {
+                signature,
+                message_ids,
+                signer,
+            }) => Event::TransactionSigned(proto::TransactionSigned {
+                signature,
+                message_ids,
+                signer,
+            }),
+            NodeEventKind::TransactionRelayed(relayed_transaction) => {
+                Event::TransactionRelayed(relayed_transaction.into())
+            }
+            NodeEventKind::ConfirmationStatusChanged(ConfirmationStatusChanged {
+                signature,
+                slot,
+                status,
+                error,
+            }) => Event::ConfirmationStatusChanged(proto::ConfirmationStatusChanged {
+                signature,
+                slot,
+                status: proto::CommitmentStatus::from(status).into(),
+                error: error.unwrap_or_default(),
+            }),
+            NodeEventKind::LeaderElected(LeaderElected { role, leader, next_round_time }) => {
+                Event::LeaderElected(proto::LeaderElected {
+                    role: proto::LeaderRole::from(role).into(),
+                    leader,
+                    next_round_time: Some(SystemTime::from(next_round_time).into()),
+                })
+            }
+            NodeEventKind::PeerJoined(PeerConnection { peer_id }) => {
+                Event::PeerJoined(proto::PeerConnection { peer_id })
+            }
+            NodeEventKind::PeerLeft(PeerConnection { peer_id }) => {
+                Event::PeerLeft(proto::PeerConnection { peer_id })
+            }
+        };
+
+        Self { sequence, timestamp: Some(SystemTime::from(timestamp).into()), event: Some(event) }
+    }
+}
+
+impl From<LeaderRole> for proto::LeaderRole {
+    fn from(role: LeaderRole) -> Self {
+        match role {
+            LeaderRole::Relayer => Self::Relayer,
+            LeaderRole::Signer => Self::Signer,
+        }
+    }
+}
+
+impl TryFrom<proto::NodeEventType> for NodeEventType {
+    type Error = proto::NodeEventType;
+
+    fn try_from(event_type: proto::NodeEventType) -> Result<Self, Self::Error> {
+        match event_type {
+            proto::NodeEventType::Unspecified => Err(event_type),
+            proto::NodeEventType::MessageReceived => Ok(Self::MessageReceived),
+            proto::NodeEventType::TransactionSigned => Ok(Self::TransactionSigned),
+            proto::NodeEventType::TransactionRelayed => Ok(Self::TransactionRelayed),
+            proto::NodeEventType::ConfirmationStatusChanged => Ok(Self::ConfirmationStatusChanged),
+            proto::NodeEventType::LeaderElected => Ok(Self::LeaderElected),
+            proto::NodeEventType::PeerJoined => Ok(Self::PeerJoined),
+            proto::NodeEventType::PeerLeft => Ok(Self::PeerLeft),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Utc};
+
+    use super::{NodeEvent, NodeEventKind, PeerConnection};
+
+    #[test]
+    fn test_node_event_json() {
+        let event = NodeEvent {
+            sequence: 1,
+            timestamp: DateTime::<Utc>::UNIX_EPOCH,
+            kind: NodeEventKind::PeerJoined(PeerConnection { peer_id: "peer".to_string() }),
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "sequence": 1,
+                "timestamp": "1970-01-01T00:00:00Z",
+                "type": "peerJoined",
+                "peerId": "peer",
+            })
+        );
+        assert_eq!(serde_json::from_value::<NodeEvent>(json).unwrap(), event);
+    }
+}
@@ -0,0 +1,66 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use solana_sdk::{message::Message, pubkey::Pubkey, signature::Signature};
+
+use crate::model::RawMessage;
+
+/// Compressed Edwards point or little-endian scalar
+pub type FrostBytes = [u8; 32];
+
+/// Request of FROST distributed key generation and signing, sent by the
+/// coordinator to participants. Participants are identified by base58 encoded
+/// string of `PeerId`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FrostRequest {
+    /// Start a key generation session
+    DkgRound1 { session_id: u64, participants: Vec<String>, threshold: u16 },
+    /// Round 1 packages of all participants
+    DkgRound2 { session_id: u64, packages: BTreeMap<String, DkgRound1Package> },
+    /// Encrypted secret shares sent to the participant, keyed by sender
+    DkgFinalize { session_id: u64, shares: BTreeMap<String, FrostBytes> },
+    /// Generate signing nonces for a signing session
+    Commit { session_id: u64, group_public_key: Pubkey },
+    /// Sign the transaction message built from the raw message
+    Sign {
+        session_id: u64,
+        raw_message: RawMessage,
+        message: Message,
+        commitments: BTreeMap<String, SigningCommitments>,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FrostResponse {
+    DkgRound1(DkgRound1Package),
+    /// Encrypted secret shares for other participants, keyed by recipient
+    DkgRound2(BTreeMap<String, FrostBytes>),
+    DkgFinalized(Pubkey),
+    Commitments(SigningCommitments),
+    SignatureShare(FrostBytes),
+    Rejected(String),
+}
+
+/// Commitments of the secret polynomial, proof of knowledge of the secret and
+/// public key for encrypting secret shares
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DkgRound1Package {
+    pub commitments: Vec<FrostBytes>,
+    pub proof_commitment: FrostBytes,
+    pub proof_response: FrostBytes,
+    pub encryption_key: FrostBytes,
+    /// signature of the package by the identity key of the participant, so
+    /// that the coordinator cannot replace the encryption key and decrypt the
+    /// shares
+    pub signature: Signature,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SigningCommitments {
+    pub hiding: FrostBytes,
+    pub binding: FrostBytes,
+}
@@ -0,0 +1,372 @@
+use std::{fmt, str::FromStr, time::SystemTime};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{
+    hash::{hashv, Hash, ParseHashError},
+    transaction::VersionedTransaction,
+};
+use utoipa::ToSchema;
+
+use crate::proto::peer as proto;
+
+/// Content-addressed identifier of a raw message, the SHA-256 hash of its
+/// origin, creation time and data
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+#[serde(try_from = "String", into = "String")]
+pub struct MessageId(Hash);
+
+impl MessageId {
+    #[must_use]
+    pub fn new(origin: &str, created_at: &DateTime<Utc>, data: &[u8]) -> Self {
+        let created_at = created_at.timestamp_micros().to_le_bytes();
+        Self(hashv(&[origin.as_bytes(), &created_at, data]))
+    }
+}
+
+impl fmt::Display for MessageId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { self.0.fmt(f) }
+}
+
+impl FromStr for MessageId {
+    type Err = ParseHashError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> { Hash::from_str(s).map(Self) }
+}
+
+impl TryFrom<String> for MessageId {
+    type Error = ParseHashError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> { value.parse() }
+}
+
+impl From<MessageId> for String {
+    fn from(message_id: MessageId) -> Self { message_id.to_string() }
+}
+
+/// Raw message published to the p2p network and consumed by the signer
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RawMessage {
+    pub id: MessageId,
+    /// base58 encoded string of origin `PeerId`
+    pub origin: String,
+    pub created_at: DateTime<Utc>,
+    pub data: Vec<u8>,
+}
+
+impl RawMessage {
+    #[must_use]
+    pub fn new(origin: String, data: Vec<u8>) -> Self {
+        let created_at = Utc::now();
+        let id = MessageId::new(&origin, &created_at, &data);
+        Self { id, origin, created_at, data }
+    }
+
+    /// Whether `id` matches the content of the message
+    #[must_use]
+    pub fn verify_id(&self) -> bool {
+        self.id == MessageId::new(&self.origin, &self.created_at, &self.data)
+    }
+}
+
+/// Transaction signed from raw messages, one instruction set per message in
+/// order if the messages are batched
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedMessage {
+    pub message_ids: Vec<MessageId>,
+    #[serde(with = "bincode_base64")]
+    pub transaction: VersionedTransaction,
+    #[serde(default)]
+    pub fee: TransactionFee,
+    /// base58 encoded string of signer `PeerId`, empty if the signer is
+    /// unknown
+    #[serde(default)]
+    pub signer: String,
+}
+
+/// Fee paid by the fee payer of a signed transaction
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionFee {
+    /// lamports paid for signatures
+    pub signature_fee: u64,
+    /// compute unit limit requested by the transaction, the runtime default if
+    /// not set
+    pub compute_unit_limit: Option<u32>,
+    /// compute unit price in micro-lamports
+    pub compute_unit_price: u64,
+    /// lamports paid for priority, the compute unit price multiplied by the
+    /// compute unit limit
+    pub priority_fee: u64,
+}
+
+impl From<TransactionFee> for proto::TransactionFee {
+    fn from(
+        TransactionFee {
+            signature_fee,
+            compute_unit_limit,
+            compute_unit_price,
+            priority_fee,
+        }: TransactionFee,
+    ) -> Self {
+        Self { signature_fee, compute_unit_limit, compute_unit_price, priority_fee }
+    }
+}
+
+/// Versioned transactions are encoded with bincode, the serde implementation of
+/// `VersionedMessage` only round trips in binary formats
+pub(super) mod bincode_base64 {
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+    use serde::{de::Error as _, ser::Error as _, Deserialize, Deserializer, Serializer};
+    use solana_sdk::transaction::VersionedTransaction;
+
+    pub fn serialize<S: Serializer>(
+        transaction: &VersionedTransaction,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let bytes = bincode::serialize(transaction).map_err(S::Error::custom)?;
+        serializer.serialize_str(&BASE64.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<VersionedTransaction, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        let bytes = BASE64.decode(encoded).map_err(D::Error::custom)?;
+        bincode::deserialize(&bytes).map_err(D::Error::custom)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum MessageStage {
+    Received,
+    Signed,
+    /// The transaction is sent to the cluster by the relayer
+    Relayed,
+    /// The transaction is processed by the connected node
+    Processed,
+    Confirmed,
+    Finalized,
+    /// The transaction is signed by a co-signer in multisig mode
+    Cosigned,
+    /// A co-signer does not sign the transaction in time in multisig mode
+    CosignerMissing,
+    /// The signer rejects the message by signing policy
+    Rejected,
+    /// The blockhash of the signed transaction expires before being relayed,
+    /// the message is signed again
+    Expired,
+    /// The relayer gives up sending the transaction, it is kept as a dead
+    /// letter until replayed
+    DeadLettered,
+    /// The transaction is executed with an error
+    Failed,
+    /// The simulation of the transaction fails, it is not relayed
+    SimulationFailed,
+}
+
+impl fmt::Display for MessageStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Received => write!(f, "Received"),
+            Self::Signed => write!(f, "Signed"),
+            Self::Relayed => write!(f, "Relayed"),
+            Self::Processed => write!(f, "Processed"),
+            Self::Confirmed => write!(f, "Confirmed"),
+            Self::Finalized => write!(f, "Finalized"),
+            Self::Cosigned => write!(f, "Cosigned"),
+            Self::CosignerMissing => write!(f, "CosignerMissing"),
+            Self::Rejected => write!(f, "Rejected"),
+            Self::Expired => write!(f, "Expired"),
+            Self::DeadLettered => write!(f, "DeadLettered"),
+            Self::Failed => write!(f, "Failed"),
+            Self::SimulationFailed => write!(f, "SimulationFailed"),
+        }
+    }
+}
+
+/// A stage reached by a message, broadcast to the p2p network by the peer which
+/// performed it, or by the multisig coordinator for co-signer stages
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageLifecycleEvent {
+    pub message_id: MessageId,
+    pub stage: MessageStage,
+    /// base58 encoded string of the `PeerId` which performed the stage, or the
+    /// co-signer for co-signer stages
+    pub peer_id: String,
+    /// base58 encoded string of transaction signature
+    pub signature: Option<String>,
+    /// why the message is rejected, dead-lettered or failed
+    #[serde(default)]
+    pub reason: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl MessageLifecycleEvent {
+    #[must_use]
+    pub fn new(
+        message_id: MessageId,
+        stage: MessageStage,
+        peer_id: String,
+        signature: Option<String>,
+    ) -> Self {
+        Self { message_id, stage, peer_id, signature, reason: None, timestamp: Utc::now() }
+    }
+
+    #[must_use]
+    pub fn with_reason(mut self, reason: String) -> Self {
+        self.reason = Some(reason);
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageStageRecord {
+    pub stage: MessageStage,
+    /// base58 encoded string of the `PeerId` which performed the stage
+    pub peer_id: String,
+    /// why the message is rejected, dead-lettered or failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageLifecycle {
+    /// base58 encoded string of message ID
+    #[schema(value_type = String)]
+    pub message_id: MessageId,
+    /// base58 encoded string of transaction signature
+    pub signature: Option<String>,
+    /// stages reached by the message, ordered by timestamp
+    pub stages: Vec<MessageStageRecord>,
+}
+
+impl MessageLifecycle {
+    #[must_use]
+    pub const fn new(message_id: MessageId) -> Self {
+        Self { message_id, signature: None, stages: Vec::new() }
+    }
+
+    /// Apply a lifecycle event, duplicated events are ignored
+    pub fn apply(&mut self, event: MessageLifecycleEvent) {
+        let MessageLifecycleEvent { stage, peer_id, signature, reason, timestamp, .. } = event;
+        if signature.is_some() {
+            self.signature = signature;
+        }
+
+        let record = MessageStageRecord { stage, peer_id, reason, timestamp };
+        if self.stages.iter().any(|r| r.stage == record.stage && r.peer_id == record.peer_id) {
+            return;
+        }
+
+        let idx = self.stages.partition_point(|r| r.timestamp <= record.timestamp);
+        self.stages.insert(idx, record);
+    }
+}
+
+impl From<MessageStage> for proto::MessageStage {
+    fn from(stage: MessageStage) -> Self {
+        match stage {
+            MessageStage::Received => Self::Received,
+            MessageStage::Signed => Self::Signed,
+            MessageStage::Relayed => Self::Relayed,
+            MessageStage::Processed => Self::Processed,
+            MessageStage::Confirmed => Self::Confirmed,
+            MessageStage::Finalized => Self::Finalized,
+            MessageStage::Cosigned => Self::Cosigned,
+            MessageStage::CosignerMissing => Self::CosignerMissing,
+            MessageStage::Rejected => Self::Rejected,
+            MessageStage::Expired => Self::Expired,
+            MessageStage::DeadLettered => Self::DeadLettered,
+            MessageStage::Failed => Self::Failed,
+            MessageStage::SimulationFailed => Self::SimulationFailed,
+        }
+    }
+}
+
+impl From<MessageStageRecord> for proto::MessageStageRecord {
+    fn from(MessageStageRecord { stage, peer_id, reason, timestamp }: MessageStageRecord) -> Self {
+        Self {
+            stage: proto::MessageStage::from(stage).into(),
+            peer_id,
+            reason: reason.unwrap_or_default(),
+            timestamp: Some(SystemTime::from(timestamp).into()),
+        }
+    }
+}
+
+impl From<MessageLifecycle> for proto::MessageLifecycle {
+    fn from(MessageLifecycle { message_id, signature, stages }: MessageLifecycle) -> Self {
+        Self {
+            message_id: message_id.to_string(),
+            signature: signature.unwrap_or_default(),
+            stages: stages.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::{
+        address_lookup_table::AddressLookupTableAccount,
+        hash::Hash,
+        message::{v0, Message, VersionedMessage},
+        pubkey::Pubkey,
+        signature::Keypair,
+        signer::Signer as _,
+        system_instruction,
+        transaction::VersionedTransaction,
+    };
+
+    use super::{RawMessage, SignedMessage, TransactionFee};
+
+    #[test]
+    fn test_signed_message_serde() {
+        let keypair = Keypair::new();
+        let recipient = Pubkey::new_unique();
+        let instructions = [system_instruction::transfer(&keypair.pubkey(), &recipient, 1)];
+        let lookup_table =
+            AddressLookupTableAccount { key: Pubkey::new_unique(), addresses: vec![recipient] };
+        let message_id = RawMessage::new("origin".to_string(), vec![]).id;
+
+        let legacy = VersionedMessage::Legacy(Message::new_with_blockhash(
+            &instructions,
+            Some(&keypair.pubkey()),
+            &Hash::new_unique(),
+        ));
+        let v0 = VersionedMessage::V0(
+            v0::Message::try_compile(
+                &keypair.pubkey(),
+                &instructions,
+                &[lookup_table],
+                Hash::new_unique(),
+            )
+            .unwrap(),
+        );
+        for message in [legacy, v0] {
+            let transaction = VersionedTransaction::try_new(message, &[&keypair]).unwrap();
+            let signed_message = SignedMessage {
+                message_ids: vec![message_id],
+                transaction,
+                fee: TransactionFee::default(),
+                signer: "peer".to_string(),
+            };
+
+            let json = serde_json::to_vec(&signed_message).unwrap();
+            let decoded = serde_json::from_slice::<SignedMessage>(&json).unwrap();
+            assert_eq!(decoded.message_ids, vec![message_id]);
+            assert_eq!(decoded.signer, "peer");
+            assert_eq!(decoded.transaction, signed_message.transaction);
+            assert!(decoded.transaction.verify_with_results().into_iter().all(|valid| valid));
+        }
+    }
+}
@@ -1,6 +1,36 @@
+mod election;
+mod event;
+mod frost;
+mod lifecycle;
+mod page;
 mod peer;
+mod relay;
+mod request;
 
-pub use self::peer::{
-    CompiledInstructionForUtoipa, MessageForUtoipa, MessageHeaderForUtoipa, TransactionDetail,
-    TransactionForUtoipa,
+pub use self::{
+    election::LeaderSyncInfo,
+    event::{
+        ConfirmationStatusChanged, EventFilter, EventQuery, LeaderElected, LeaderRole,
+        MessageReceived, NodeEvent, NodeEventKind, NodeEventType, PeerConnection,
+        TransactionSigned,
+    },
+    frost::{DkgRound1Package, FrostBytes, FrostRequest, FrostResponse, SigningCommitments},
+    lifecycle::{
+        MessageId, MessageLifecycle, MessageLifecycleEvent, MessageStage, MessageStageRecord,
+        RawMessage, SignedMessage, TransactionFee,
+    },
+    page::{ListQuery, Page, SortOrder, DEFAULT_PAGE_LIMIT, MAX_PAGE_LIMIT},
+    peer::{
+        peer_id_to_solana_pubkey, CompiledInstructionView, MessageAddressTableLookupView,
+        MessageHeaderView, MessageVersion, MessageView, SubmitMessageRequest,
+        SubmitMessageResponse, TransactionDetail, TransactionView,
+    },
+    relay::{
+        CommitmentStatus, DeadLetter, RelayMarker, RelayMarkerState, RelayedTransaction,
+        SimulationResult,
+    },
+    request::{
+        CatchupRequest, CatchupResponse, CosignRequest, CosignResponse, FeePayerRequest,
+        FeePayerResponse, PeerRequest, PeerResponse,
+    },
 };
@@ -0,0 +1,118 @@
+// SAFETY: allow: utoipa generated code of generic schemas
+#![allow(clippy::option_if_let_else)]
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::proto::peer as proto;
+
+/// Number of records in a page if the limit is not set
+pub const DEFAULT_PAGE_LIMIT: u32 = 100;
+/// Limits above are lowered to the max
+pub const MAX_PAGE_LIMIT: u32 = 1_000;
+
+/// Order of listed records by the time they are recorded, peers are ordered
+/// by their IDs
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Pagination, filters and ordering of list endpoints
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct ListQuery {
+    /// `nextPageToken` of the previous page, the first page is listed if
+    /// unset. The other parameters must be kept between pages
+    pub page_token: Option<String>,
+    /// max number of records in the page, 100 by default and 1000 at most
+    pub limit: Option<u32>,
+    /// records recorded at or after the time, ignored by peers
+    pub since: Option<DateTime<Utc>>,
+    /// records recorded before the time, ignored by peers
+    pub until: Option<DateTime<Utc>>,
+    /// peer ID of the signer, ignored by peers
+    pub signer: Option<String>,
+    /// peer ID of the relayer, only relayed transactions are filtered
+    pub relayer: Option<String>,
+    #[serde(default)]
+    #[param(inline)]
+    pub order: SortOrder,
+}
+
+impl ListQuery {
+    /// Limit of the page within `1..=MAX_PAGE_LIMIT`
+    #[must_use]
+    pub fn limit(&self) -> usize {
+        let limit = self.limit.unwrap_or(DEFAULT_PAGE_LIMIT).clamp(1, MAX_PAGE_LIMIT);
+        usize::try_from(limit).unwrap_or(usize::MAX)
+    }
+}
+
+/// Page of listed records
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// token of the next page, unset on the last page
+    pub next_page_token: Option<String>,
+}
+
+impl<T> Page<T> {
+    #[must_use]
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            next_page_token: self.next_page_token,
+        }
+    }
+}
+
+impl From<SortOrder> for proto::v1::SortOrder {
+    fn from(order: SortOrder) -> Self {
+        match order {
+            SortOrder::Asc => Self::Asc,
+            SortOrder::Desc => Self::Desc,
+        }
+    }
+}
+
+impl From<proto::v1::SortOrder> for SortOrder {
+    fn from(order: proto::v1::SortOrder) -> Self {
+        match order {
+            proto::v1::SortOrder::Asc => Self::Asc,
+            proto::v1::SortOrder::Desc => Self::Desc,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::extract::Query;
+    use http::Uri;
+
+    use super::{ListQuery, SortOrder, DEFAULT_PAGE_LIMIT};
+
+    fn list_query(uri: &'static str) -> ListQuery {
+        Query::try_from_uri(&Uri::from_static(uri)).unwrap().0
+    }
+
+    #[test]
+    fn test_list_query() {
+        let query = list_query("/?limit=5000&since=2024-01-01T00:00:00Z&relayer=peer&order=desc");
+        assert_eq!(query.limit(), 1_000);
+        assert_eq!(query.since.unwrap().to_rfc3339(), "2024-01-01T00:00:00+00:00");
+        assert_eq!(query.relayer.as_deref(), Some("peer"));
+        assert_eq!(query.order, SortOrder::Desc);
+
+        let query = list_query("/");
+        assert_eq!(query.limit(), DEFAULT_PAGE_LIMIT as usize);
+        assert_eq!(query.order, SortOrder::Asc);
+        assert_eq!(ListQuery { limit: Some(0), ..ListQuery::default() }.limit(), 1);
+    }
+}
@@ -2,16 +2,19 @@ use libp2p::{identity, PeerId};
 use serde::{Deserialize, Serialize};
 use solana_sdk::{
     instruction::CompiledInstruction,
-    message::{Message, MessageHeader},
+    message::{v0, v0::MessageAddressTableLookup, Message, MessageHeader, VersionedMessage},
     pubkey::Pubkey,
-    transaction::Transaction,
+    transaction::VersionedTransaction,
 };
 use solana_transaction_status_client_types::{
     EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, UiMessage, UiTransaction,
 };
 use utoipa::ToSchema;
 
-use crate::proto::peer as proto;
+use crate::{
+    model::{SignedMessage, SimulationResult, TransactionFee},
+    proto::peer as proto,
+};
 
 impl From<CompiledInstruction> for proto::CompiledInstruction {
     fn from(CompiledInstruction { program_id_index, accounts, data }: CompiledInstruction) -> Self {
@@ -35,30 +38,85 @@ impl From<MessageHeader> for proto::MessageHeader {
     }
 }
 
-impl From<Message> for proto::Message {
-    fn from(Message { header, account_keys, recent_blockhash, instructions }: Message) -> Self {
+impl From<MessageAddressTableLookup> for proto::MessageAddressTableLookup {
+    fn from(
+        MessageAddressTableLookup { account_key, writable_indexes, readonly_indexes }: MessageAddressTableLookup,
+    ) -> Self {
+        Self { account_key: account_key.to_string(), writable_indexes, readonly_indexes }
+    }
+}
+
+impl From<VersionedMessage> for proto::Message {
+    fn from(message: VersionedMessage) -> Self {
+        let (version, header, account_keys, recent_blockhash, instructions, address_table_lookups) =
+            match message {
+                VersionedMessage::Legacy(Message {
+                    header,
+                    account_keys,
+                    recent_blockhash,
+                    instructions,
+                }) => (
+                    proto::MessageVersion::Legacy,
+                    header,
+                    account_keys,
+                    recent_blockhash,
+                    instructions,
+                    Vec::new(),
+                ),
+                VersionedMessage::V0(v0::Message {
+                    header,
+                    account_keys,
+                    recent_blockhash,
+                    instructions,
+                    address_table_lookups,
+                }) => (
+                    proto::MessageVersion::V0,
+                    header,
+                    account_keys,
+                    recent_blockhash,
+                    instructions,
+                    address_table_lookups,
+                ),
+            };
+
         Self {
             header: Some(header.into()),
             account_keys: account_keys.into_iter().map(|key| key.to_string()).collect(),
             recent_blockhash: recent_blockhash.to_string(),
             instructions: instructions.into_iter().map(Into::into).collect(),
+            version: version.into(),
+            address_table_lookups: address_table_lookups.into_iter().map(Into::into).collect(),
         }
     }
 }
 
-impl From<Transaction> for proto::Transaction {
-    fn from(Transaction { signatures, message }: Transaction) -> Self {
+impl From<SignedMessage> for proto::Transaction {
+    fn from(
+        SignedMessage {
+            message_ids,
+            transaction: VersionedTransaction { signatures, message },
+            fee,
+            ..
+        }: SignedMessage,
+    ) -> Self {
         Self {
+            message_ids: message_ids.iter().map(ToString::to_string).collect(),
             signatures: signatures.into_iter().map(|signature| signature.to_string()).collect(),
             message: Some(message.into()),
+            fee: Some(fee.into()),
         }
     }
 }
 
-/// Workaround for `OpenAPI` docs
-#[derive(ToSchema)]
+/// Signed transaction, legacy or v0
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
 #[schema(as = Transaction)]
-pub struct TransactionForUtoipa {
+pub struct TransactionView {
+    /// Base58 encoded IDs of the messages signed in the transaction, in
+    /// instruction order.
+    pub message_ids: Vec<String>,
+
     /// A set of signatures of a serialized [`Message`], signed by the first
     /// keys of the `Message`'s [`account_keys`], where the number of signatures
     /// is equal to [`num_required_signatures`] of the `Message`'s
@@ -72,19 +130,49 @@ pub struct TransactionForUtoipa {
 
     /// The message to sign.
     #[schema(inline)]
-    pub message: MessageForUtoipa,
+    pub message: MessageView,
+
+    /// Fee paid by the fee payer.
+    pub fee: TransactionFee,
+}
+
+impl From<SignedMessage> for TransactionView {
+    fn from(
+        SignedMessage {
+            message_ids,
+            transaction: VersionedTransaction { signatures, message },
+            fee,
+            ..
+        }: SignedMessage,
+    ) -> Self {
+        Self {
+            message_ids: message_ids.iter().map(ToString::to_string).collect(),
+            signatures: signatures.into_iter().map(|signature| signature.to_string()).collect(),
+            message: message.into(),
+            fee,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum MessageVersion {
+    Legacy,
+    V0,
 }
 
-/// Workaround for `OpenAPI` docs
-#[derive(ToSchema)]
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
 #[schema(as = Message)]
-pub struct MessageForUtoipa {
+pub struct MessageView {
+    pub version: MessageVersion,
+
     /// The message header, identifying signed and read-only `account_keys`.
     // NOTE: Serialization-related changes must be paired with the direct read at sigverify.
     #[schema(inline)]
-    pub header: MessageHeaderForUtoipa,
+    pub header: MessageHeaderView,
 
-    /// All the account keys used by this transaction.
+    /// All the static account keys used by this transaction.
     pub account_keys: Vec<String>,
 
     /// The id of a recent ledger entry.
@@ -93,13 +181,59 @@ pub struct MessageForUtoipa {
     /// Programs that will be executed in sequence and committed in one atomic
     /// transaction if all succeed.
     #[schema(inline)]
-    pub instructions: Vec<CompiledInstructionForUtoipa>,
+    pub instructions: Vec<CompiledInstructionView>,
+
+    /// List of address table lookups used to load additional accounts for
+    /// this transaction, empty for legacy messages.
+    #[schema(inline)]
+    pub address_table_lookups: Vec<MessageAddressTableLookupView>,
+}
+
+impl From<VersionedMessage> for MessageView {
+    fn from(message: VersionedMessage) -> Self {
+        let version = match message {
+            VersionedMessage::Legacy(_) => MessageVersion::Legacy,
+            VersionedMessage::V0(_) => MessageVersion::V0,
+        };
+        let header = *message.header();
+        let address_table_lookups = message
+            .address_table_lookups()
+            .unwrap_or_default()
+            .iter()
+            .map(|lookup| MessageAddressTableLookupView {
+                account_key: lookup.account_key.to_string(),
+                writable_indexes: lookup.writable_indexes.clone(),
+                readonly_indexes: lookup.readonly_indexes.clone(),
+            })
+            .collect();
+
+        Self {
+            version,
+            header: MessageHeaderView {
+                num_required_signatures: header.num_required_signatures,
+                num_readonly_signed_accounts: header.num_readonly_signed_accounts,
+                num_readonly_unsigned_accounts: header.num_readonly_unsigned_accounts,
+            },
+            account_keys: message.static_account_keys().iter().map(ToString::to_string).collect(),
+            recent_blockhash: message.recent_blockhash().to_string(),
+            instructions: message
+                .instructions()
+                .iter()
+                .map(|instruction| CompiledInstructionView {
+                    program_id_index: instruction.program_id_index,
+                    accounts: instruction.accounts.clone(),
+                    data: instruction.data.clone(),
+                })
+                .collect(),
+            address_table_lookups,
+        }
+    }
 }
 
-/// Workaround for `OpenAPI` docs
-#[derive(ToSchema)]
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
 #[schema(as = MessageHeader)]
-pub struct MessageHeaderForUtoipa {
+pub struct MessageHeaderView {
     /// The number of signatures required for this message to be considered
     /// valid. The signers of those signatures must match the first
     /// `num_required_signatures` of [`Message::account_keys`].
@@ -115,10 +249,10 @@ pub struct MessageHeaderForUtoipa {
     pub num_readonly_unsigned_accounts: u8,
 }
 
-/// Workaround for `OpenAPI` docs
-#[derive(ToSchema)]
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
 #[schema(as = CompiledInstruction)]
-pub struct CompiledInstructionForUtoipa {
+pub struct CompiledInstructionView {
     /// Index into the transaction keys array indicating the program account
     /// that executes this instruction.
     pub program_id_index: u8,
@@ -129,6 +263,18 @@ pub struct CompiledInstructionForUtoipa {
     pub data: Vec<u8>,
 }
 
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(as = MessageAddressTableLookup)]
+pub struct MessageAddressTableLookupView {
+    /// Address lookup table account key
+    pub account_key: String,
+    /// List of indexes used to load writable account addresses
+    pub writable_indexes: Vec<u8>,
+    /// List of indexes used to load readonly account addresses
+    pub readonly_indexes: Vec<u8>,
+}
+
 #[derive(Debug, Deserialize, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct TransactionDetail {
@@ -139,6 +285,36 @@ pub struct TransactionDetail {
     /// base58 encoded string of signer `PeerId`
     signers: Vec<String>,
     log_messages: Vec<String>,
+    /// result of the simulation before the transaction is relayed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    simulation: Option<SimulationResult>,
+}
+
+impl TransactionDetail {
+    #[must_use]
+    pub fn with_simulation(mut self, simulation: Option<SimulationResult>) -> Self {
+        self.simulation = simulation;
+        self
+    }
+}
+
+/// Detail of a transaction which does not land, built from the transaction
+/// itself
+impl From<VersionedTransaction> for TransactionDetail {
+    fn from(VersionedTransaction { signatures, message }: VersionedTransaction) -> Self {
+        let account_keys = message.static_account_keys();
+        let num_required_signatures = usize::from(message.header().num_required_signatures);
+        Self {
+            signatures: signatures.iter().map(ToString::to_string).collect(),
+            account_keys: account_keys.iter().map(ToString::to_string).collect(),
+            signers: account_keys[..num_required_signatures]
+                .iter()
+                .map(|pubkey| solana_public_key_str_to_peer_id(&pubkey.to_string()).to_base58())
+                .collect(),
+            log_messages: Vec::new(),
+            simulation: None,
+        }
+    }
 }
 
 impl From<EncodedConfirmedTransactionWithStatusMeta> for TransactionDetail {
@@ -161,6 +337,7 @@ impl From<EncodedConfirmedTransactionWithStatusMeta> for TransactionDetail {
                             .map(|pkey| solana_public_key_str_to_peer_id(pkey).to_base58())
                             .collect(),
                         log_messages,
+                        simulation: None,
                     }
                 } else {
                     unimplemented!("unsupported `UiMessage` {message:?}")
@@ -173,12 +350,38 @@ impl From<EncodedConfirmedTransactionWithStatusMeta> for TransactionDetail {
 
 impl From<TransactionDetail> for proto::TransactionDetail {
     fn from(
-        TransactionDetail { signatures, account_keys, signers, log_messages }: TransactionDetail,
+        TransactionDetail {
+            signatures,
+            account_keys,
+            signers,
+            log_messages,
+            simulation,
+        }: TransactionDetail,
     ) -> Self {
-        Self { signatures, account_keys, signers, log_messages }
+        Self {
+            signatures,
+            account_keys,
+            signers,
+            log_messages,
+            simulation: simulation.map(Into::into),
+        }
     }
 }
 
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmitMessageRequest {
+    /// base64 encoded message data
+    pub data: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmitMessageResponse {
+    /// base58 encoded string of message ID
+    pub message_id: String,
+}
+
 fn solana_public_key_str_to_peer_id(solana_pkey_str: &str) -> PeerId {
     let solana_pkey = Pubkey::from_str_const(solana_pkey_str);
     let pkey: identity::PublicKey =
@@ -188,3 +391,13 @@ fn solana_public_key_str_to_peer_id(solana_pkey_str: &str) -> PeerId {
 
     pkey.into()
 }
+
+/// Solana public key of the peer, peers share the same ed25519 keypair between
+/// libp2p and Solana
+#[must_use]
+pub fn peer_id_to_solana_pubkey(peer_id: &PeerId) -> Option<Pubkey> {
+    let pkey = identity::PublicKey::try_decode_protobuf(peer_id.as_ref().digest()).ok()?;
+    let pkey = pkey.try_into_ed25519().ok()?;
+
+    Some(Pubkey::new_from_array(pkey.to_bytes()))
+}
@@ -0,0 +1,164 @@
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::proto::peer as proto;
+
+/// Relay state of a transaction gossiped by the relayer, so that the next
+/// relayer takes over the transactions which are not done
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelayMarker {
+    /// base58 encoded string of transaction signature
+    pub signature: String,
+    /// base58 encoded string of relayer `PeerId`
+    pub relayer: String,
+    pub state: RelayMarkerState,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RelayMarkerState {
+    /// The relayer is sending the transaction, it may have been sent
+    Claimed,
+    /// The transaction is relayed, dead-lettered or rejected, it is not sent
+    /// again
+    Done,
+}
+
+/// Transaction sent by the relayer, gossiped when it is sent and whenever its
+/// commitment status changes
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RelayedTransaction {
+    /// base58 encoded string of transaction signature
+    pub signature: String,
+    /// base58 encoded IDs of the messages signed in the transaction
+    pub message_ids: Vec<String>,
+    /// base58 encoded string of signer `PeerId`, empty if the signer is
+    /// unknown
+    pub signer: String,
+    /// base58 encoded string of relayer `PeerId`
+    pub relayer: String,
+    /// slot the transaction is processed in
+    pub slot: Option<u64>,
+    pub submitted_at: DateTime<Utc>,
+    /// when the transaction reaches the `confirmed` commitment
+    pub confirmed_at: Option<DateTime<Utc>>,
+    pub status: CommitmentStatus,
+    /// lamports paid by the fee payer, the signature fee and the priority fee
+    pub fee: u64,
+    /// why the transaction fails to execute
+    pub error: Option<String>,
+}
+
+/// Commitment status of a relayed transaction, in the order it progresses
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum CommitmentStatus {
+    /// The transaction is sent without waiting for confirmation
+    Submitted,
+    Processed,
+    Confirmed,
+    Finalized,
+    /// The transaction is executed with an error
+    Failed,
+    /// The blockhash of the transaction expires before it lands
+    Expired,
+}
+
+impl From<RelayedTransaction> for proto::RelayedTransaction {
+    fn from(
+        RelayedTransaction {
+            signature,
+            message_ids,
+            signer,
+            relayer,
+            slot,
+            submitted_at,
+            confirmed_at,
+            status,
+            fee,
+            error,
+        }: RelayedTransaction,
+    ) -> Self {
+        Self {
+            signature,
+            message_ids,
+            signer,
+            relayer,
+            slot,
+            submitted_at: Some(SystemTime::from(submitted_at).into()),
+            confirmed_at: confirmed_at.map(|confirmed_at| SystemTime::from(confirmed_at).into()),
+            status: proto::CommitmentStatus::from(status).into(),
+            fee,
+            error: error.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<CommitmentStatus> for proto::CommitmentStatus {
+    fn from(status: CommitmentStatus) -> Self {
+        match status {
+            CommitmentStatus::Submitted => Self::Submitted,
+            CommitmentStatus::Processed => Self::Processed,
+            CommitmentStatus::Confirmed => Self::Confirmed,
+            CommitmentStatus::Finalized => Self::Finalized,
+            CommitmentStatus::Failed => Self::Failed,
+            CommitmentStatus::Expired => Self::Expired,
+        }
+    }
+}
+
+/// A transaction which the relayer fails to send, kept until it is replayed
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DeadLetter {
+    /// base58 encoded string of transaction signature
+    pub signature: String,
+    /// base58 encoded IDs of the messages signed in the transaction
+    pub message_ids: Vec<String>,
+    /// attempts to send the transaction
+    pub attempts: u32,
+    /// error of the last attempt
+    pub error: String,
+    pub failed_at: DateTime<Utc>,
+}
+
+impl From<DeadLetter> for proto::DeadLetter {
+    fn from(DeadLetter { signature, message_ids, attempts, error, failed_at }: DeadLetter) -> Self {
+        Self {
+            signature,
+            message_ids,
+            attempts,
+            error,
+            failed_at: Some(SystemTime::from(failed_at).into()),
+        }
+    }
+}
+
+/// Result of simulating a transaction before it is relayed
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationResult {
+    pub logs: Vec<String>,
+    pub units_consumed: Option<u64>,
+    /// why the transaction fails, the transaction is not relayed if set
+    pub error: Option<String>,
+    pub simulated_at: DateTime<Utc>,
+}
+
+impl From<SimulationResult> for proto::SimulationResult {
+    fn from(
+        SimulationResult { logs, units_consumed, error, simulated_at }: SimulationResult,
+    ) -> Self {
+        Self {
+            logs,
+            units_consumed,
+            error: error.unwrap_or_default(),
+            simulated_at: Some(SystemTime::from(simulated_at).into()),
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{message::Message, signature::Signature, transaction::VersionedTransaction};
+
+use crate::model::{
+    FrostRequest, FrostResponse, LeaderSyncInfo, RawMessage, RelayedTransaction, SignedMessage,
+};
+
+/// Request sent to a peer over the request-response protocol
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PeerRequest {
+    Cosign(CosignRequest),
+    Frost(FrostRequest),
+    FeePayer(FeePayerRequest),
+    Catchup(CatchupRequest),
+}
+
+/// Response replied by a peer over the request-response protocol
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PeerResponse {
+    Cosign(CosignResponse),
+    Frost(FrostResponse),
+    FeePayer(FeePayerResponse),
+    Catchup(CatchupResponse),
+}
+
+/// Ask a co-signer to sign the transaction message built by the coordinator
+/// from the raw message
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CosignRequest {
+    pub raw_message: RawMessage,
+    pub message: Message,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CosignResponse {
+    Signed(Signature),
+    Rejected(String),
+}
+
+/// Ask the relayer to pay the fee of the transaction partially signed by the
+/// signer
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeePayerRequest {
+    #[serde(with = "super::lifecycle::bincode_base64")]
+    pub transaction: VersionedTransaction,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FeePayerResponse {
+    Signed(Signature),
+    Rejected(String),
+}
+
+/// Ask an established peer for the state gossiped before this peer joined
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatchupRequest {
+    /// relayed transactions and signed messages recorded since the time
+    pub since: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatchupResponse {
+    pub relayer: Option<LeaderSyncInfo>,
+    pub signer: Option<LeaderSyncInfo>,
+    pub relayed_transactions: Vec<RelayedTransaction>,
+    /// signed messages which are not relayed yet
+    pub signed_messages: Vec<SignedMessage>,
+}
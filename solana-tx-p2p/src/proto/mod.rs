@@ -17,5 +17,9 @@
 
 pub mod peer;
 
+pub mod rpc {
+    tonic::include_proto!("google.rpc");
+}
+
 pub const FILE_DESCRIPTOR_SET: &[u8] =
     include_bytes!(concat!(env!("OUT_DIR"), "/file_descriptor_set.pb"));
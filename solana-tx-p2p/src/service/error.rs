@@ -1,19 +1,29 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, collections::HashMap, path::PathBuf};
 
 use axum::{
-    body,
     response::{IntoResponse, Response},
+    Json,
 };
-use http::{header, StatusCode};
+use http::StatusCode;
+use prost::Message as _;
 use snafu::{Backtrace, Snafu};
-use solana_sdk::signature::{ParseSignatureError, Signature};
+use solana_sdk::{
+    hash::ParseHashError,
+    instruction::InstructionError,
+    pubkey::Pubkey,
+    signature::{ParseSignatureError, Signature},
+    transaction::TransactionError,
+};
 use tokio::sync::{
     mpsc::error::SendError as MpscSendError, oneshot::error::RecvError as OneshotRecvError,
 };
-use tonic::Status;
+use tonic::{Code, Status};
 
 use crate::{
-    error::fmt_backtrace_with_source, service::PeerWorkerInboundEvent, web::ErrorResponse,
+    error::{fmt_backtrace, fmt_backtrace_with_source},
+    proto::rpc,
+    service::{solana::is_endpoint_unavailable, PeerWorkerInboundEvent},
+    web::ErrorResponse,
 };
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -33,40 +43,163 @@ pub enum Error {
     ))]
     SendPeerWorkerInstruction {
         instruction: Cow<'static, str>,
-        source: MpscSendError<PeerWorkerInboundEvent>,
+        #[snafu(source(from(MpscSendError<PeerWorkerInboundEvent>, Box::new)))]
+        source: Box<MpscSendError<PeerWorkerInboundEvent>>,
         backtrace: Backtrace,
     },
 
+    #[snafu(display("Fail to submit message{}", fmt_backtrace_with_source(backtrace, source)))]
+    SubmitMessage { source: OneshotRecvError, backtrace: Backtrace },
+
     #[snafu(display("Fail to list peers{}", fmt_backtrace_with_source(backtrace, source)))]
     ListPeers { source: OneshotRecvError, backtrace: Backtrace },
 
+    #[snafu(display("Fail to get transaction{}", fmt_backtrace_with_source(backtrace, source)))]
+    GetTransaction { source: OneshotRecvError, backtrace: Backtrace },
+
+    #[snafu(display("Fail to list dead letters{}", fmt_backtrace_with_source(backtrace, source)))]
+    ListDeadLetters { source: OneshotRecvError, backtrace: Backtrace },
+
     #[snafu(display(
-        "Fail to list signed messages{}",
+        "Fail to replay dead letter{}",
         fmt_backtrace_with_source(backtrace, source)
     ))]
-    ListSignedMessages { source: OneshotRecvError, backtrace: Backtrace },
+    ReplayDeadLetter { source: OneshotRecvError, backtrace: Backtrace },
 
     #[snafu(display(
-        "Fail to list relayed transactions{}",
+        "Fail to open store `{}`{}",
+        path.display(),
         fmt_backtrace_with_source(backtrace, source)
     ))]
-    ListRelayedTransactions { source: OneshotRecvError, backtrace: Backtrace },
+    OpenStore { path: PathBuf, source: redb::DatabaseError, backtrace: Backtrace },
 
-    #[snafu(display("Fail to get transaction{}", fmt_backtrace_with_source(backtrace, source)))]
-    GetTransaction { source: OneshotRecvError, backtrace: Backtrace },
+    #[snafu(display("Fail to access store{}", fmt_backtrace_with_source(backtrace, source)))]
+    Store { source: redb::Error, backtrace: Backtrace },
+
+    #[snafu(display(
+        "Fail to encode store record{}",
+        fmt_backtrace_with_source(backtrace, source)
+    ))]
+    EncodeStoreRecord { source: serde_json::Error, backtrace: Backtrace },
+
+    #[snafu(display(
+        "Fail to decode store record{}",
+        fmt_backtrace_with_source(backtrace, source)
+    ))]
+    DecodeStoreRecord { source: serde_json::Error, backtrace: Backtrace },
+
+    #[snafu(display("Transaction `{signature}` is not found{}", fmt_backtrace(backtrace)))]
+    TransactionNotFound { signature: Signature, backtrace: Backtrace },
+
+    #[snafu(display("Dead letter `{signature}` is not found{}", fmt_backtrace(backtrace)))]
+    DeadLetterNotFound { signature: Signature, backtrace: Backtrace },
+
+    #[snafu(display("Message lifecycle of `{key}` is not found{}", fmt_backtrace(backtrace)))]
+    MessageLifecycleNotFound { key: String, backtrace: Backtrace },
+
+    #[snafu(display(
+        "Events from sequence {from_sequence} are no longer kept, the oldest kept event is \
+         {first_sequence}{}",
+        fmt_backtrace(backtrace)
+    ))]
+    EventsExpired { from_sequence: u64, first_sequence: u64, backtrace: Backtrace },
+
+    #[snafu(display(
+        "Subscriber falls behind and {skipped} events are dropped{}",
+        fmt_backtrace(backtrace)
+    ))]
+    EventsLagged { skipped: u64, backtrace: Backtrace },
+
+    #[snafu(display("Page token `{token}` is invalid{}", fmt_backtrace(backtrace)))]
+    InvalidPageToken { token: String, backtrace: Backtrace },
+
+    #[snafu(display("Fail to parse message ID{}", fmt_backtrace_with_source(backtrace, source)))]
+    ParseMessageId { source: ParseHashError, backtrace: Backtrace },
+
+    #[snafu(display("Memo must be valid UTF-8{}", fmt_backtrace_with_source(backtrace, source)))]
+    InvalidMemo { source: std::str::Utf8Error, backtrace: Backtrace },
+
+    #[snafu(display(
+        "Fail to deserialize instructions{}",
+        fmt_backtrace_with_source(backtrace, source)
+    ))]
+    DeserializeInstructions { source: bincode::Error, backtrace: Backtrace },
+
+    #[snafu(display(
+        "Instruction of program `{program_id}` uses the signer `{signer}` as a signer or writable \
+         account{}",
+        fmt_backtrace(backtrace)
+    ))]
+    InstructionUsesSigner { program_id: Pubkey, signer: Pubkey, backtrace: Backtrace },
 
     #[snafu(display("Fail to request airdrop{}", fmt_backtrace_with_source(backtrace, source)))]
     RequestAirdrop { source: solana_client::client_error::ClientError, backtrace: Backtrace },
 
     #[snafu(display(
-        "Fail to confirm transaction{}",
+        "Fail to transfer funds from treasury{}",
+        fmt_backtrace_with_source(backtrace, source)
+    ))]
+    TransferFunds { source: solana_client::client_error::ClientError, backtrace: Backtrace },
+
+    #[snafu(display("Fail to get balance{}", fmt_backtrace_with_source(backtrace, source)))]
+    GetBalance { source: solana_client::client_error::ClientError, backtrace: Backtrace },
+
+    #[snafu(display(
+        "Balance {balance} lamports is below the minimum balance {min_balance} lamports{}",
+        fmt_backtrace(backtrace)
+    ))]
+    InsufficientBalance { balance: u64, min_balance: u64, backtrace: Backtrace },
+
+    #[snafu(display(
+        "Funding transaction `{signature}` fails{}",
         fmt_backtrace_with_source(backtrace, source)
     ))]
-    ConfirmSolanaTransaction {
+    FundingTransactionFailed {
+        signature: Signature,
+        source: TransactionError,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display(
+        "Funding transaction `{signature}` is not confirmed in time{}",
+        fmt_backtrace(backtrace)
+    ))]
+    FundingTimeout { signature: Signature, backtrace: Backtrace },
+
+    #[snafu(display(
+        "Fail to get nonce accounts{}",
+        fmt_backtrace_with_source(backtrace, source)
+    ))]
+    GetNonceAccounts { source: solana_client::client_error::ClientError, backtrace: Backtrace },
+
+    #[snafu(display(
+        "Fail to create nonce account{}",
+        fmt_backtrace_with_source(backtrace, source)
+    ))]
+    CreateNonceAccount { source: solana_client::client_error::ClientError, backtrace: Backtrace },
+
+    #[snafu(display(
+        "Fail to get address lookup tables{}",
+        fmt_backtrace_with_source(backtrace, source)
+    ))]
+    GetAddressLookupTables {
         source: solana_client::client_error::ClientError,
         backtrace: Backtrace,
     },
 
+    #[snafu(display("Address lookup table `{address}` is not found{}", fmt_backtrace(backtrace)))]
+    AddressLookupTableNotFound { address: Pubkey, backtrace: Backtrace },
+
+    #[snafu(display(
+        "Fail to deserialize address lookup table `{address}`{}",
+        fmt_backtrace_with_source(backtrace, source)
+    ))]
+    DeserializeAddressLookupTable {
+        address: Pubkey,
+        source: InstructionError,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display(
         "Fail to parse solana signature{}",
         fmt_backtrace_with_source(backtrace, source)
@@ -84,23 +217,198 @@ pub enum Error {
     },
 }
 
-impl IntoResponse for Error {
-    fn into_response(self) -> Response {
-        let body = {
-            let body = ErrorResponse { message: self.to_string() };
-            body::boxed(body::Full::from(
-                serde_json::to_vec(&body).expect("`ErrorResponse` is valid JSON"),
-            ))
+/// Domain of `google.rpc.ErrorInfo` in the details of gRPC statuses
+const ERROR_DOMAIN: &str = "solana-tx-p2p";
+
+/// Class of errors replied to clients, which decides the HTTP status code and
+/// the gRPC status code
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// the request is invalid and should not be retried as it is
+    InvalidArgument,
+    NotFound,
+    /// the Solana RPC endpoints or the workers of this node are down, the
+    /// request may be retried later
+    Unavailable,
+    Internal,
+}
+
+impl ErrorKind {
+    #[must_use]
+    pub const fn status_code(self) -> StatusCode {
+        match self {
+            Self::InvalidArgument => StatusCode::BAD_REQUEST,
+            Self::NotFound => StatusCode::NOT_FOUND,
+            Self::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    #[must_use]
+    pub const fn grpc_code(self) -> Code {
+        match self {
+            Self::InvalidArgument => Code::InvalidArgument,
+            Self::NotFound => Code::NotFound,
+            Self::Unavailable => Code::Unavailable,
+            Self::Internal => Code::Internal,
+        }
+    }
+
+    /// JSON `ErrorResponse` with the error code
+    #[must_use]
+    pub fn response(self, code: &str, message: String) -> Response {
+        let body = ErrorResponse { code: code.to_string(), message };
+        (self.status_code(), Json(body)).into_response()
+    }
+
+    /// gRPC status carrying `google.rpc.ErrorInfo` in its details, the reason
+    /// of which is the error code
+    #[must_use]
+    pub fn grpc_status(self, code: &str, message: String) -> Status {
+        let error_info = rpc::ErrorInfo {
+            reason: code.to_string(),
+            domain: ERROR_DOMAIN.to_string(),
+            metadata: HashMap::new(),
+        };
+        let details = rpc::Status {
+            code: self.grpc_code().into(),
+            message: message.clone(),
+            details: vec![prost_types::Any {
+                type_url: "type.googleapis.com/google.rpc.ErrorInfo".to_string(),
+                value: error_info.encode_to_vec(),
+            }],
         };
+        Status::with_details(self.grpc_code(), message, details.encode_to_vec().into())
+    }
+}
+
+impl Error {
+    #[must_use]
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::InvalidPageToken { .. }
+            | Self::ParseMessageId { .. }
+            | Self::ParseSolanaSignature { .. }
+            | Self::InvalidMemo { .. }
+            | Self::DeserializeInstructions { .. }
+            | Self::InstructionUsesSigner { .. }
+            | Self::EventsExpired { .. } => ErrorKind::InvalidArgument,
+            Self::TransactionNotFound { .. }
+            | Self::DeadLetterNotFound { .. }
+            | Self::MessageLifecycleNotFound { .. }
+            | Self::AddressLookupTableNotFound { .. } => ErrorKind::NotFound,
+            // the peer worker stops or falls behind
+            Self::SendPeerWorkerInstruction { .. }
+            | Self::SubmitMessage { .. }
+            | Self::ListPeers { .. }
+            | Self::GetTransaction { .. }
+            | Self::ListDeadLetters { .. }
+            | Self::ReplayDeadLetter { .. }
+            | Self::EventsLagged { .. } => ErrorKind::Unavailable,
+            Self::RequestAirdrop { source, .. }
+            | Self::TransferFunds { source, .. }
+            | Self::GetBalance { source, .. }
+            | Self::GetNonceAccounts { source, .. }
+            | Self::CreateNonceAccount { source, .. }
+            | Self::GetAddressLookupTables { source, .. }
+            | Self::GetSolanaTransaction { source, .. }
+                if is_endpoint_unavailable(source) =>
+            {
+                ErrorKind::Unavailable
+            }
+            _ => ErrorKind::Internal,
+        }
+    }
+
+    /// Stable machine-readable code of the error in `UPPER_SNAKE_CASE`
+    #[must_use]
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidPageToken { .. } => "INVALID_PAGE_TOKEN",
+            Self::ParseMessageId { .. } => "INVALID_MESSAGE_ID",
+            Self::ParseSolanaSignature { .. } => "INVALID_SIGNATURE",
+            Self::InvalidMemo { .. } => "INVALID_MEMO",
+            Self::DeserializeInstructions { .. } | Self::InstructionUsesSigner { .. } => {
+                "INVALID_INSTRUCTIONS"
+            }
+            Self::EventsExpired { .. } => "EVENTS_EXPIRED",
+            Self::TransactionNotFound { .. } => "TRANSACTION_NOT_FOUND",
+            Self::DeadLetterNotFound { .. } => "DEAD_LETTER_NOT_FOUND",
+            Self::MessageLifecycleNotFound { .. } => "MESSAGE_LIFECYCLE_NOT_FOUND",
+            Self::AddressLookupTableNotFound { .. } => "ADDRESS_LOOKUP_TABLE_NOT_FOUND",
+            Self::EventsLagged { .. } => "EVENTS_LAGGED",
+            _ => match self.kind() {
+                ErrorKind::Unavailable if self.is_peer_worker_error() => "PEER_WORKER_UNAVAILABLE",
+                ErrorKind::Unavailable => "SOLANA_RPC_UNAVAILABLE",
+                _ => "INTERNAL",
+            },
+        }
+    }
 
-        Response::builder()
-            .status(StatusCode::INTERNAL_SERVER_ERROR)
-            .header(header::CONTENT_TYPE, "application/json")
-            .body(body)
-            .expect("It should be a valid `Response`")
+    const fn is_peer_worker_error(&self) -> bool {
+        matches!(
+            self,
+            Self::SendPeerWorkerInstruction { .. }
+                | Self::SubmitMessage { .. }
+                | Self::ListPeers { .. }
+                | Self::GetTransaction { .. }
+                | Self::ListDeadLetters { .. }
+                | Self::ReplayDeadLetter { .. }
+        )
     }
 }
 
+impl IntoResponse for Error {
+    fn into_response(self) -> Response { self.kind().response(self.code(), self.to_string()) }
+}
+
 impl From<Error> for Status {
-    fn from(error: Error) -> Self { Self::internal(error.to_string()) }
+    fn from(error: Error) -> Self { error.kind().grpc_status(error.code(), error.to_string()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use axum::response::IntoResponse;
+    use http::StatusCode;
+    use prost::Message as _;
+    use snafu::{IntoError, ResultExt};
+    use solana_client::client_error::ClientError;
+    use solana_sdk::signature::Signature;
+    use tokio::sync::oneshot;
+    use tonic::{Code, Status};
+
+    use super::{
+        ErrorKind, GetBalanceSnafu, ParseSolanaSignatureSnafu, SubmitMessageSnafu,
+        TransactionNotFoundSnafu,
+    };
+    use crate::proto::rpc;
+
+    #[test]
+    fn test_error_kind() {
+        let err = "invalid".parse::<Signature>().context(ParseSolanaSignatureSnafu).unwrap_err();
+        assert_eq!((err.kind(), err.code()), (ErrorKind::InvalidArgument, "INVALID_SIGNATURE"));
+        assert_eq!(err.into_response().status(), StatusCode::BAD_REQUEST);
+
+        let err = TransactionNotFoundSnafu { signature: Signature::default() }.build();
+        let status = Status::from(err);
+        assert_eq!(status.code(), Code::NotFound);
+        let details = rpc::Status::decode(status.details()).unwrap();
+        let error_info = rpc::ErrorInfo::decode(details.details[0].value.as_slice()).unwrap();
+        assert_eq!(error_info.reason, "TRANSACTION_NOT_FOUND");
+
+        let (sender, receiver) = oneshot::channel::<()>();
+        drop(sender);
+        let err = SubmitMessageSnafu.into_error(receiver.blocking_recv().unwrap_err());
+        assert_eq!((err.kind(), err.code()), (ErrorKind::Unavailable, "PEER_WORKER_UNAVAILABLE"));
+        assert_eq!(err.into_response().status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let source = ClientError::from(io::Error::other("connection refused"));
+        let err = GetBalanceSnafu.into_error(source);
+        assert_eq!(err.code(), "SOLANA_RPC_UNAVAILABLE");
+        let source = ClientError::from(solana_sdk::transaction::TransactionError::AlreadyProcessed);
+        let err = GetBalanceSnafu.into_error(source);
+        assert_eq!((err.kind(), err.code()), (ErrorKind::Internal, "INTERNAL"));
+    }
 }
@@ -0,0 +1,198 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use chrono::Utc;
+use futures_util::{stream, Stream};
+use snafu::ensure;
+use tokio::sync::{broadcast, broadcast::error::RecvError};
+
+use crate::{
+    model::{EventFilter, NodeEvent, NodeEventKind},
+    service::{error, Result},
+    ShutdownSignal,
+};
+
+/// Events kept for subscribers resuming from a sequence number, the oldest
+/// are dropped when exceeded
+const EVENT_HISTORY_CAPACITY: usize = 1_000;
+/// Events buffered for each subscriber, subscribers falling further behind
+/// are dropped
+const EVENT_CHANNEL_CAPACITY: usize = 1_000;
+
+#[derive(Debug)]
+struct EventHistory {
+    next_sequence: u64,
+    events: VecDeque<NodeEvent>,
+}
+
+impl EventHistory {
+    /// Sequence number of the oldest event which is kept
+    fn first_sequence(&self) -> u64 {
+        self.events.front().map_or(self.next_sequence, |event| event.sequence)
+    }
+}
+
+/// Broadcast of node events fed by the peer worker, the election workers, the
+/// signer and the relayer. Publishing never waits for subscribers
+#[derive(Clone)]
+pub struct EventBroadcaster {
+    sender: broadcast::Sender<NodeEvent>,
+    history: Arc<Mutex<EventHistory>>,
+    /// subscriptions end on shutdown, so that streaming servers stop
+    /// gracefully
+    shutdown_signal: ShutdownSignal,
+}
+
+impl EventBroadcaster {
+    #[must_use]
+    pub fn new(shutdown_signal: ShutdownSignal) -> Self {
+        let (sender, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        let history = EventHistory { next_sequence: 1, events: VecDeque::new() };
+        Self { sender, history: Arc::new(Mutex::new(history)), shutdown_signal }
+    }
+
+    /// Number the event and send it to subscribers
+    pub fn publish(&self, kind: NodeEventKind) {
+        let mut history = self.history.lock().unwrap_or_else(PoisonError::into_inner);
+        let event = NodeEvent { sequence: history.next_sequence, timestamp: Utc::now(), kind };
+        history.next_sequence += 1;
+        if history.events.len() >= EVENT_HISTORY_CAPACITY {
+            history.events.pop_front();
+        }
+        history.events.push_back(event.clone());
+
+        // events are sent while the history is locked, so that subscribers
+        // never miss or repeat events between the history and the channel.
+        // Sending fails only if no one subscribes
+        drop(self.sender.send(event));
+        drop(history);
+    }
+
+    /// # Errors
+    ///
+    /// * events from the sequence number of the filter are no longer kept
+    pub fn subscribe(&self, filter: EventFilter) -> Result<EventSubscription> {
+        let history = self.history.lock().unwrap_or_else(PoisonError::into_inner);
+        let replay = match filter.from_sequence {
+            Some(from_sequence) => {
+                let first_sequence = history.first_sequence();
+                ensure!(from_sequence.max(1) >= first_sequence, error::EventsExpiredSnafu {
+                    from_sequence,
+                    first_sequence
+                });
+                history
+                    .events
+                    .iter()
+                    .filter(|event| event.sequence >= from_sequence)
+                    .cloned()
+                    .collect()
+            }
+            None => VecDeque::new(),
+        };
+        let receiver = self.sender.subscribe();
+        drop(history);
+
+        Ok(EventSubscription {
+            filter,
+            replay,
+            receiver,
+            shutdown_signal: self.shutdown_signal.clone(),
+        })
+    }
+}
+
+/// Events replayed from the history followed by new events, which match the
+/// filter
+pub struct EventSubscription {
+    filter: EventFilter,
+    replay: VecDeque<NodeEvent>,
+    receiver: broadcast::Receiver<NodeEvent>,
+    shutdown_signal: ShutdownSignal,
+}
+
+impl EventSubscription {
+    /// The next event matching the filter, `None` on shutdown
+    ///
+    /// # Errors
+    ///
+    /// * the subscriber falls behind and events are dropped, it should
+    ///   subscribe again from the sequence number after the last received event
+    pub async fn next(&mut self) -> Result<Option<NodeEvent>> {
+        while let Some(event) = self.replay.pop_front() {
+            if self.filter.matches(&event) {
+                return Ok(Some(event));
+            }
+        }
+
+        loop {
+            let result = tokio::select! {
+                () = self.shutdown_signal.wait() => return Ok(None),
+                result = self.receiver.recv() => result,
+            };
+            match result {
+                Ok(event) if self.filter.matches(&event) => return Ok(Some(event)),
+                Ok(_) => {}
+                Err(RecvError::Closed) => return Ok(None),
+                Err(RecvError::Lagged(skipped)) => {
+                    return error::EventsLaggedSnafu { skipped }.fail();
+                }
+            }
+        }
+    }
+
+    /// Stream of the events, the stream ends after an error
+    pub fn into_stream(self) -> impl Stream<Item = Result<NodeEvent>> + Send {
+        stream::unfold(Some(self), |subscription| async move {
+            let mut subscription = subscription?;
+            match subscription.next().await {
+                Ok(Some(event)) => Some((Ok(event), Some(subscription))),
+                Ok(None) => None,
+                Err(err) => Some((Err(err), None)),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EventBroadcaster;
+    use crate::{
+        model::{EventFilter, NodeEventKind, NodeEventType, PeerConnection},
+        service::error::Error,
+        SignalHandleBuilder,
+    };
+
+    fn peer_joined(peer_id: &str) -> NodeEventKind {
+        NodeEventKind::PeerJoined(PeerConnection { peer_id: peer_id.to_string() })
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_from_sequence() {
+        let shutdown_signal_handler = SignalHandleBuilder::new(None).start();
+        let events = EventBroadcaster::new(shutdown_signal_handler.shutdown_signal());
+        events.publish(peer_joined("a"));
+        events.publish(NodeEventKind::PeerLeft(PeerConnection { peer_id: "a".to_string() }));
+
+        let filter = EventFilter { types: vec![NodeEventType::PeerJoined], from_sequence: Some(1) };
+        let mut subscription = events.subscribe(filter).unwrap();
+        events.publish(peer_joined("b"));
+
+        let event = subscription.next().await.unwrap().unwrap();
+        assert_eq!((event.sequence, event.kind), (1, peer_joined("a")));
+        let event = subscription.next().await.unwrap().unwrap();
+        assert_eq!((event.sequence, event.kind), (3, peer_joined("b")));
+
+        for _ in 0..super::EVENT_HISTORY_CAPACITY {
+            events.publish(peer_joined("c"));
+        }
+        let filter = EventFilter { types: Vec::new(), from_sequence: Some(3) };
+        assert!(matches!(
+            events.subscribe(filter),
+            Err(Error::EventsExpired { from_sequence: 3, first_sequence: 4, .. })
+        ));
+
+        shutdown_signal_handler.stop();
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use futures_util::future;
+use libp2p::PeerId;
+use solana_sdk::{message::Message, pubkey::Pubkey, signature::Signature};
+
+use crate::{
+    model::{FrostRequest, FrostResponse, RawMessage},
+    service::frost::KeyPackage,
+};
+
+/// Deliver requests of the coordinator to participants, including the
+/// coordinator itself
+#[async_trait]
+pub trait FrostTransport: Sync {
+    /// Send request to participant, `None` if the participant does not respond
+    async fn request(&self, peer_id: PeerId, request: FrostRequest) -> Option<FrostResponse>;
+}
+
+/// Run distributed key generation among `participants`, return the group
+/// public key
+///
+/// # Errors
+///
+/// * any participant does not respond or rejects the request
+/// * participants do not agree on the group public key
+pub async fn run_dkg<T>(
+    transport: &T,
+    mut participants: Vec<PeerId>,
+    threshold: u16,
+) -> Result<Pubkey, String>
+where
+    T: FrostTransport,
+{
+    participants.sort_unstable();
+    participants.dedup();
+    let session_id = rand::random();
+    let participant_ids = participants.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+    // round 1
+    let responses = request_all(transport, &participants, |_| FrostRequest::DkgRound1 {
+        session_id,
+        participants: participant_ids.clone(),
+        threshold,
+    })
+    .await?;
+    let packages = responses
+        .into_iter()
+        .map(|(peer_id, response)| match response {
+            FrostResponse::DkgRound1(package) => Ok((peer_id.to_string(), package)),
+            _ => Err(unexpected_response(&peer_id)),
+        })
+        .collect::<Result<BTreeMap<_, _>, _>>()?;
+
+    // round 2, shares are encrypted for the recipients
+    let responses = request_all(transport, &participants, |_| FrostRequest::DkgRound2 {
+        session_id,
+        packages: packages.clone(),
+    })
+    .await?;
+    let mut shares = BTreeMap::<String, BTreeMap<_, _>>::new();
+    for (peer_id, response) in responses {
+        let FrostResponse::DkgRound2(encrypted_shares) = response else {
+            return Err(unexpected_response(&peer_id));
+        };
+        for (recipient, share) in encrypted_shares {
+            shares.entry(recipient).or_default().insert(peer_id.to_string(), share);
+        }
+    }
+
+    let responses = request_all(transport, &participants, |peer_id| FrostRequest::DkgFinalize {
+        session_id,
+        shares: shares.remove(&peer_id.to_string()).unwrap_or_default(),
+    })
+    .await?;
+    let mut group_public_key = None;
+    for (peer_id, response) in responses {
+        let FrostResponse::DkgFinalized(key) = response else {
+            return Err(unexpected_response(&peer_id));
+        };
+        if *group_public_key.get_or_insert(key) != key {
+            return Err("participants do not agree on the group public key".to_string());
+        }
+    }
+
+    group_public_key.ok_or_else(|| "no participant".to_string())
+}
+
+/// Sign the transaction message with `threshold` participants out of
+/// `candidates`
+///
+/// # Errors
+///
+/// * less than `threshold` candidates commit to the signing session
+/// * any signer does not respond, rejects the request or replies invalid
+///   signature share
+pub async fn sign<T>(
+    transport: &T,
+    key_package: &KeyPackage,
+    candidates: Vec<PeerId>,
+    raw_message: &RawMessage,
+    message: &Message,
+) -> Result<Signature, String>
+where
+    T: FrostTransport,
+{
+    let session_id = rand::random();
+    let group_public_key = key_package.group_public_key;
+
+    // round 1, collect commitments from available candidates
+    let candidates = candidates
+        .into_iter()
+        .filter(|peer_id| key_package.index_of(&peer_id.to_string()).is_some())
+        .collect::<Vec<_>>();
+    let responses = future::join_all(candidates.iter().map(|peer_id| {
+        transport.request(*peer_id, FrostRequest::Commit { session_id, group_public_key })
+    }))
+    .await;
+    let mut signers = Vec::new();
+    let mut commitments = BTreeMap::new();
+    for (peer_id, response) in candidates.into_iter().zip(responses) {
+        match response {
+            Some(FrostResponse::Commitments(commitment)) => {
+                signers.push(peer_id);
+                commitments.insert(peer_id.to_string(), commitment);
+            }
+            Some(FrostResponse::Rejected(reason)) => {
+                tracing::warn!("Participant {peer_id} does not commit: {reason}");
+            }
+            _ => tracing::warn!("Participant {peer_id} does not commit"),
+        }
+        if signers.len() == usize::from(key_package.threshold) {
+            break;
+        }
+    }
+    if signers.len() < usize::from(key_package.threshold) {
+        return Err(format!(
+            "{} of {} participants commit to the signing session",
+            signers.len(),
+            key_package.threshold
+        ));
+    }
+
+    // round 2
+    let responses = request_all(transport, &signers, |_| FrostRequest::Sign {
+        session_id,
+        raw_message: raw_message.clone(),
+        message: message.clone(),
+        commitments: commitments.clone(),
+    })
+    .await?;
+    let shares = responses
+        .into_iter()
+        .map(|(peer_id, response)| match response {
+            FrostResponse::SignatureShare(share) => Ok((peer_id.to_string(), share)),
+            _ => Err(unexpected_response(&peer_id)),
+        })
+        .collect::<Result<BTreeMap<_, _>, _>>()?;
+
+    let signature = key_package.aggregate(&commitments, &shares, &message.serialize())?;
+    Ok(Signature::from(signature))
+}
+
+/// Send requests to all participants, fail if any participant does not
+/// respond or rejects the request
+async fn request_all<T, F>(
+    transport: &T,
+    participants: &[PeerId],
+    mut request: F,
+) -> Result<Vec<(PeerId, FrostResponse)>, String>
+where
+    T: FrostTransport,
+    F: FnMut(&PeerId) -> FrostRequest,
+{
+    let requests = participants.iter().map(|peer_id| transport.request(*peer_id, request(peer_id)));
+    let responses = future::join_all(requests).await;
+
+    participants
+        .iter()
+        .zip(responses)
+        .map(|(peer_id, response)| match response {
+            None => Err(format!("participant {peer_id} does not respond")),
+            Some(FrostResponse::Rejected(reason)) => {
+                Err(format!("participant {peer_id} rejects the request: {reason}"))
+            }
+            Some(response) => Ok((*peer_id, response)),
+        })
+        .collect()
+}
+
+fn unexpected_response(peer_id: &PeerId) -> String {
+    format!("unexpected response from participant {peer_id}")
+}
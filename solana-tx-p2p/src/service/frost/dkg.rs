@@ -0,0 +1,204 @@
+use std::collections::BTreeMap;
+
+use curve25519_dalek::{edwards::EdwardsPoint, scalar::Scalar, traits::Identity};
+use solana_sdk::signature::Signature;
+
+use crate::{
+    model::{DkgRound1Package, FrostBytes},
+    service::frost::{
+        base_mul, decode_point, decode_scalar, encode_point, hash, hash_to_scalar, identifier,
+        random_scalar,
+    },
+};
+
+/// Secret state of a participant kept between rounds of key generation
+pub struct DkgSecret {
+    index: u16,
+    coefficients: Vec<Scalar>,
+    encryption_secret: Scalar,
+}
+
+/// Public state of a participant verified in round 2
+pub struct DkgPublic {
+    commitments: Vec<EdwardsPoint>,
+    encryption_key: EdwardsPoint,
+}
+
+/// Result of key generation of a participant
+pub struct DkgOutput {
+    pub signing_share: Scalar,
+    pub group_public_key: EdwardsPoint,
+    /// verifying shares of all participants ordered by index
+    pub verifying_shares: Vec<EdwardsPoint>,
+}
+
+/// Round 1: commit to a random polynomial of degree `threshold - 1` and prove
+/// the knowledge of its constant term
+pub fn round1(index: u16, threshold: u16) -> (DkgSecret, DkgRound1Package) {
+    let coefficients = (0..threshold).map(|_| random_scalar()).collect::<Vec<_>>();
+    let commitments = coefficients.iter().map(base_mul).collect::<Vec<_>>();
+
+    let nonce = random_scalar();
+    let proof_commitment = base_mul(&nonce);
+    let challenge = proof_challenge(index, &commitments[0], &proof_commitment);
+    let proof_response = nonce + coefficients[0] * challenge;
+
+    let encryption_secret = random_scalar();
+    let package = DkgRound1Package {
+        commitments: commitments.iter().map(encode_point).collect(),
+        proof_commitment: encode_point(&proof_commitment),
+        proof_response: proof_response.to_bytes(),
+        encryption_key: encode_point(&base_mul(&encryption_secret)),
+        // signed by the participant
+        signature: Signature::default(),
+    };
+
+    (DkgSecret { index, coefficients, encryption_secret }, package)
+}
+
+/// Verify the round 1 package of participant `index`
+pub fn verify_round1(
+    index: u16,
+    threshold: u16,
+    package: &DkgRound1Package,
+) -> Result<DkgPublic, String> {
+    if package.commitments.len() != usize::from(threshold) {
+        return Err(format!("participant {index} commits to a polynomial of wrong degree"));
+    }
+
+    let commitments =
+        package.commitments.iter().map(decode_point).collect::<Result<Vec<_>, _>>()?;
+    let proof_commitment = decode_point(&package.proof_commitment)?;
+    let proof_response = decode_scalar(&package.proof_response)?;
+    let challenge = proof_challenge(index, &commitments[0], &proof_commitment);
+    if base_mul(&proof_response) != proof_commitment + commitments[0] * challenge {
+        return Err(format!("invalid proof of knowledge of participant {index}"));
+    }
+
+    Ok(DkgPublic { commitments, encryption_key: decode_point(&package.encryption_key)? })
+}
+
+/// Round 2: evaluate the secret polynomial for other participants, shares are
+/// encrypted for the recipients
+pub fn round2(
+    secret: &DkgSecret,
+    session_id: u64,
+    publics: &BTreeMap<u16, DkgPublic>,
+) -> BTreeMap<u16, FrostBytes> {
+    publics
+        .iter()
+        .filter(|(index, _)| **index != secret.index)
+        .map(|(index, public)| {
+            let share = evaluate(&secret.coefficients, identifier(*index));
+            let key = share_key(
+                &(public.encryption_key * secret.encryption_secret),
+                session_id,
+                secret.index,
+                *index,
+            );
+            (*index, xor(&share.to_bytes(), &key))
+        })
+        .collect()
+}
+
+/// Decrypt and verify shares from other participants, then derive the signing
+/// share and the group public key
+pub fn finalize(
+    secret: &DkgSecret,
+    session_id: u64,
+    publics: &BTreeMap<u16, DkgPublic>,
+    shares: &BTreeMap<u16, FrostBytes>,
+) -> Result<DkgOutput, String> {
+    let x = identifier(secret.index);
+    let mut signing_share = evaluate(&secret.coefficients, x);
+    for (index, public) in publics.iter().filter(|(index, _)| **index != secret.index) {
+        let encrypted =
+            shares.get(index).ok_or_else(|| format!("missing share of participant {index}"))?;
+        let key = share_key(
+            &(public.encryption_key * secret.encryption_secret),
+            session_id,
+            *index,
+            secret.index,
+        );
+        let share = decode_scalar(&xor(encrypted, &key))?;
+        if base_mul(&share) != evaluate_commitments(&public.commitments, x) {
+            return Err(format!("invalid share of participant {index}"));
+        }
+        signing_share += share;
+    }
+
+    let group_public_key = publics.values().map(|public| public.commitments[0]).sum();
+    let verifying_shares = publics
+        .keys()
+        .map(|index| {
+            publics
+                .values()
+                .map(|public| evaluate_commitments(&public.commitments, identifier(*index)))
+                .sum()
+        })
+        .collect();
+
+    Ok(DkgOutput { signing_share, group_public_key, verifying_shares })
+}
+
+/// Message of the round 1 package signed by the identity key of `participant`
+/// in the session
+pub fn round1_signing_message(
+    session_id: u64,
+    participant: &str,
+    package: &DkgRound1Package,
+) -> [u8; 64] {
+    let session_id = session_id.to_le_bytes();
+    let mut inputs = vec![session_id.as_slice(), participant.as_bytes()];
+    inputs.extend(package.commitments.iter().map(<[u8; 32]>::as_slice));
+    inputs.extend([
+        package.proof_commitment.as_slice(),
+        package.proof_response.as_slice(),
+        package.encryption_key.as_slice(),
+    ]);
+
+    hash(b"dkg-auth", &inputs)
+}
+
+fn proof_challenge(
+    index: u16,
+    commitment: &EdwardsPoint,
+    proof_commitment: &EdwardsPoint,
+) -> Scalar {
+    hash_to_scalar(b"dkg", &[
+        identifier(index).as_bytes(),
+        commitment.compress().as_bytes(),
+        proof_commitment.compress().as_bytes(),
+    ])
+}
+
+/// One-time key of the share sent from `from` to `to`, derived from the
+/// Diffie-Hellman shared secret of their encryption keys
+fn share_key(shared_secret: &EdwardsPoint, session_id: u64, from: u16, to: u16) -> FrostBytes {
+    let digest = hash(b"enc", &[
+        shared_secret.compress().as_bytes(),
+        &session_id.to_le_bytes(),
+        &from.to_le_bytes(),
+        &to.to_le_bytes(),
+    ]);
+
+    let mut key = [0; 32];
+    key.copy_from_slice(&digest[..32]);
+    key
+}
+
+fn xor(lhs: &FrostBytes, rhs: &FrostBytes) -> FrostBytes {
+    let mut output = [0; 32];
+    for (output, (lhs, rhs)) in output.iter_mut().zip(lhs.iter().zip(rhs)) {
+        *output = lhs ^ rhs;
+    }
+    output
+}
+
+fn evaluate(coefficients: &[Scalar], x: Scalar) -> Scalar {
+    coefficients.iter().rev().fold(Scalar::ZERO, |acc, coefficient| acc * x + coefficient)
+}
+
+fn evaluate_commitments(commitments: &[EdwardsPoint], x: Scalar) -> EdwardsPoint {
+    commitments.iter().rev().fold(EdwardsPoint::identity(), |acc, commitment| acc * x + commitment)
+}
@@ -0,0 +1,228 @@
+mod coordinator;
+mod dkg;
+mod participant;
+mod signing;
+
+use std::{path::PathBuf, time::Duration};
+
+use curve25519_dalek::{
+    constants::ED25519_BASEPOINT_POINT,
+    edwards::{CompressedEdwardsY, EdwardsPoint},
+    scalar::Scalar,
+};
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha512};
+
+pub use self::{
+    coordinator::{run_dkg, sign, FrostTransport},
+    participant::{FrostParticipant, KeyPackage},
+};
+use crate::model::FrostBytes;
+
+const CONTEXT_STRING: &[u8] = b"FROST-ED25519-SHA512-v1";
+
+/// FROST(Ed25519, SHA-512) threshold signing, see [RFC 9591](https://www.rfc-editor.org/rfc/rfc9591).
+///
+/// The group public key is an ordinary Ed25519 public key and the aggregated
+/// signature is an ordinary Ed25519 signature, so that the transaction signed
+/// by the group can be relayed as-is
+#[derive(Clone, Debug)]
+pub struct FrostConfig {
+    /// number of participants required to sign
+    pub threshold: u16,
+    /// how long the coordinator waits for a participant in each round
+    pub timeout: Duration,
+    /// directory where key shares are persisted
+    pub key_share_dir: PathBuf,
+    /// replace the existing key share by a new distributed key generation,
+    /// the previous key share is archived in `key_share_dir`
+    pub allow_rekey: bool,
+}
+
+/// `SHA-512(contextString || domain || inputs)`
+fn hash(domain: &[u8], inputs: &[&[u8]]) -> [u8; 64] {
+    let mut hasher = Sha512::new();
+    hasher.update(CONTEXT_STRING);
+    hasher.update(domain);
+    for input in inputs {
+        hasher.update(input);
+    }
+
+    hasher.finalize().into()
+}
+
+fn hash_to_scalar(domain: &[u8], inputs: &[&[u8]]) -> Scalar {
+    Scalar::from_bytes_mod_order_wide(&hash(domain, inputs))
+}
+
+/// Ed25519 challenge `SHA-512(R || A || M)`
+fn challenge(
+    group_commitment: &EdwardsPoint,
+    group_public_key: &EdwardsPoint,
+    message: &[u8],
+) -> Scalar {
+    let mut hasher = Sha512::new();
+    hasher.update(group_commitment.compress().as_bytes());
+    hasher.update(group_public_key.compress().as_bytes());
+    hasher.update(message);
+
+    Scalar::from_bytes_mod_order_wide(&hasher.finalize().into())
+}
+
+/// Participant identifier, `index` starts from 1
+fn identifier(index: u16) -> Scalar { Scalar::from(u64::from(index)) }
+
+fn random_scalar() -> Scalar { Scalar::random(&mut OsRng) }
+
+/// Lagrange coefficient of participant `index` for the set of `signers`
+fn lagrange_coefficient(index: u16, signers: &[u16]) -> Scalar {
+    let x_i = identifier(index);
+    let (numerator, denominator) = signers
+        .iter()
+        .filter(|j| **j != index)
+        .map(|j| identifier(*j))
+        .fold((Scalar::ONE, Scalar::ONE), |(numerator, denominator), x_j| {
+            (numerator * x_j, denominator * (x_j - x_i))
+        });
+
+    numerator * denominator.invert()
+}
+
+fn encode_point(point: &EdwardsPoint) -> FrostBytes { point.compress().to_bytes() }
+
+fn decode_point(bytes: &FrostBytes) -> Result<EdwardsPoint, String> {
+    let point = CompressedEdwardsY(*bytes).decompress().ok_or("invalid point")?;
+    if point.is_small_order() || !point.is_torsion_free() {
+        return Err("point is not in the prime order subgroup".to_string());
+    }
+
+    Ok(point)
+}
+
+fn decode_scalar(bytes: &FrostBytes) -> Result<Scalar, String> {
+    Option::from(Scalar::from_canonical_bytes(*bytes)).ok_or_else(|| "invalid scalar".to_string())
+}
+
+fn base_mul(scalar: &Scalar) -> EdwardsPoint { ED25519_BASEPOINT_POINT * scalar }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{BTreeMap, HashMap},
+        sync::{Arc, Mutex},
+    };
+
+    use async_trait::async_trait;
+    use libp2p::PeerId;
+    use solana_sdk::{hash::Hash, message::Message, transaction::Transaction};
+
+    use super::{run_dkg, sign, FrostParticipant, FrostTransport};
+    use crate::{
+        model::{FrostRequest, FrostResponse, RawMessage},
+        service::{
+            solana::MemoInstructionBuilder, InstructionBuilder, InstructionSigner, PeerWorker,
+        },
+    };
+
+    struct InMemoryTransport {
+        coordinator: PeerId,
+        participants: HashMap<PeerId, Mutex<FrostParticipant>>,
+    }
+
+    #[async_trait]
+    impl FrostTransport for InMemoryTransport {
+        async fn request(&self, peer_id: PeerId, request: FrostRequest) -> Option<FrostResponse> {
+            let participant = self.participants.get(&peer_id)?;
+            Some(participant.lock().unwrap().handle(self.coordinator, request))
+        }
+    }
+
+    fn in_memory_transport(participants: usize) -> (Vec<PeerId>, InMemoryTransport) {
+        let participants = (0..participants)
+            .map(|_| {
+                let (key, solana_key) = PeerWorker::generate_keypair();
+                let peer_id = key.public().to_peer_id();
+                let participant = FrostParticipant::new(
+                    peer_id,
+                    solana_key,
+                    Arc::new(MemoInstructionBuilder),
+                    None,
+                    false,
+                );
+                (peer_id, Mutex::new(participant))
+            })
+            .collect::<HashMap<_, _>>();
+        let mut peer_ids = participants.keys().copied().collect::<Vec<_>>();
+        peer_ids.sort_unstable();
+
+        (peer_ids.clone(), InMemoryTransport { coordinator: peer_ids[0], participants })
+    }
+
+    #[tokio::test]
+    async fn test_frost_dkg_and_sign() {
+        let (peer_ids, mut transport) = in_memory_transport(3);
+        let coordinator = peer_ids[0];
+
+        let group_public_key = run_dkg(&transport, peer_ids.clone(), 2).await.unwrap();
+        for participant in transport.participants.values() {
+            let key_package = participant.lock().unwrap().key_package().cloned().unwrap();
+            assert_eq!(key_package.group_public_key, group_public_key);
+        }
+        // the key share is never replaced unless re-keying is allowed
+        assert!(run_dkg(&transport, peer_ids.clone(), 2).await.is_err());
+
+        // a participant leaves, the others can still sign
+        transport.participants.remove(&peer_ids[1]);
+        let key_package =
+            transport.participants[&coordinator].lock().unwrap().key_package().cloned().unwrap();
+
+        let raw_message = RawMessage::new(coordinator.to_string(), b"hello".to_vec());
+        let signer = InstructionSigner { peer_id: coordinator, pubkey: group_public_key };
+        let instructions = MemoInstructionBuilder.build(&raw_message, &signer).unwrap();
+        let message = Message::new_with_blockhash(
+            &instructions,
+            Some(&group_public_key),
+            &Hash::new_unique(),
+        );
+
+        let signature =
+            sign(&transport, &key_package, peer_ids.clone(), &raw_message, &message).await.unwrap();
+        let transaction = Transaction { signatures: vec![signature], message };
+        assert!(transaction.verify().is_ok());
+
+        // not enough participants
+        transport.participants.remove(&peer_ids[2]);
+        let message = transaction.message;
+        assert!(sign(&transport, &key_package, peer_ids, &raw_message, &message).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_frost_dkg_rejects_replaced_package() {
+        let (peer_ids, transport) = in_memory_transport(2);
+        let participants = peer_ids.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+        let mut packages = BTreeMap::new();
+        for peer_id in &peer_ids {
+            let request = FrostRequest::DkgRound1 {
+                session_id: 1,
+                participants: participants.clone(),
+                threshold: 2,
+            };
+            let Some(FrostResponse::DkgRound1(package)) =
+                transport.request(*peer_id, request).await
+            else {
+                panic!("round 1 is rejected");
+            };
+            packages.insert(peer_id.to_string(), package);
+        }
+
+        // the coordinator replaces the encryption key of the other participant
+        let encryption_key = packages[&participants[0]].encryption_key;
+        packages.get_mut(&participants[1]).unwrap().encryption_key = encryption_key;
+        let request = FrostRequest::DkgRound2 { session_id: 1, packages };
+        assert!(matches!(
+            transport.request(peer_ids[0], request).await,
+            Some(FrostResponse::Rejected(_))
+        ));
+    }
+}
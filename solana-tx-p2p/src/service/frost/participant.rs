@@ -0,0 +1,478 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, OpenOptions},
+    io::{self, Write as _},
+    os::unix::fs::OpenOptionsExt as _,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use chrono::Utc;
+use curve25519_dalek::{edwards::EdwardsPoint, scalar::Scalar};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use solana_sdk::{
+    message::Message,
+    pubkey::Pubkey,
+    signature::{Keypair as SolanaKeypair, Signer as _},
+};
+
+use crate::{
+    model::{
+        peer_id_to_solana_pubkey, DkgRound1Package, FrostBytes, FrostRequest, FrostResponse,
+        RawMessage, SigningCommitments,
+    },
+    service::{
+        frost::{
+            decode_point, decode_scalar,
+            dkg::{self, DkgPublic, DkgSecret},
+            encode_point,
+            signing::{self, SigningNonces},
+        },
+        solana::leading_compute_budget_instructions,
+        InstructionBuilder, InstructionSigner,
+    },
+};
+
+/// Signing sessions kept by a participant, nonces of stale sessions are
+/// dropped when exceeded
+const MAX_SIGNING_SESSIONS: usize = 64;
+
+/// Key share of a participant generated by distributed key generation
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyPackage {
+    /// base58 encoded string of `PeerId` of participants, sorted
+    pub participants: Vec<String>,
+    pub threshold: u16,
+    /// index of this participant, starts from 1
+    pub index: u16,
+    signing_share: FrostBytes,
+    pub group_public_key: Pubkey,
+    /// verifying shares of participants ordered by index
+    verifying_shares: Vec<FrostBytes>,
+}
+
+impl KeyPackage {
+    /// Index of the participant, starts from 1
+    #[must_use]
+    pub fn index_of(&self, peer_id: &str) -> Option<u16> {
+        let position = self.participants.iter().position(|participant| participant == peer_id)?;
+        u16::try_from(position + 1).ok()
+    }
+
+    /// Aggregate signature shares keyed by `PeerId` into an Ed25519 signature
+    pub(super) fn aggregate(
+        &self,
+        commitments: &BTreeMap<String, SigningCommitments>,
+        shares: &BTreeMap<String, FrostBytes>,
+        message: &[u8],
+    ) -> Result<[u8; 64], String> {
+        let commitments = signing::decode_commitments(&self.index_commitments(commitments)?)?;
+        let shares = shares
+            .iter()
+            .map(|(peer_id, share)| {
+                let index = self
+                    .index_of(peer_id)
+                    .ok_or_else(|| format!("peer `{peer_id}` is not a participant"))?;
+                Ok((index, decode_scalar(share)?))
+            })
+            .collect::<Result<_, String>>()?;
+
+        signing::aggregate(
+            &self.group_public_key()?,
+            &self.verifying_shares()?,
+            &commitments,
+            message,
+            &shares,
+        )
+    }
+
+    fn signing_share(&self) -> Result<Scalar, String> { decode_scalar(&self.signing_share) }
+
+    fn group_public_key(&self) -> Result<EdwardsPoint, String> {
+        decode_point(&self.group_public_key.to_bytes())
+    }
+
+    fn verifying_shares(&self) -> Result<BTreeMap<u16, EdwardsPoint>, String> {
+        (1..)
+            .zip(&self.verifying_shares)
+            .map(|(index, verifying_share)| Ok((index, decode_point(verifying_share)?)))
+            .collect()
+    }
+
+    /// Decode commitments keyed by `PeerId` into commitments keyed by index
+    fn index_commitments(
+        &self,
+        commitments: &BTreeMap<String, SigningCommitments>,
+    ) -> Result<BTreeMap<u16, SigningCommitments>, String> {
+        commitments
+            .iter()
+            .map(|(peer_id, commitments)| {
+                let index = self
+                    .index_of(peer_id)
+                    .ok_or_else(|| format!("peer `{peer_id}` is not a participant"))?;
+                Ok((index, *commitments))
+            })
+            .collect()
+    }
+}
+
+struct DkgSession {
+    participants: Vec<String>,
+    index: u16,
+    threshold: u16,
+    secret: DkgSecret,
+    publics: Option<BTreeMap<u16, DkgPublic>>,
+}
+
+/// FROST participant which handles requests from the coordinator
+pub struct FrostParticipant {
+    peer_id: PeerId,
+    /// identity key of this peer, which signs round 1 packages
+    keypair: Arc<SolanaKeypair>,
+    instruction_builder: Arc<dyn InstructionBuilder>,
+    /// key share is not persisted if not set
+    key_share_dir: Option<PathBuf>,
+    /// distributed key generation is refused if this participant has a key
+    /// share and re-keying is not allowed
+    allow_rekey: bool,
+
+    key_package: Option<KeyPackage>,
+    dkg_sessions: HashMap<u64, DkgSession>,
+    signing_sessions: HashMap<u64, SigningNonces>,
+}
+
+impl FrostParticipant {
+    /// Create participant, the key share persisted in `key_share_dir` is
+    /// loaded if exists
+    #[must_use]
+    pub fn new(
+        peer_id: PeerId,
+        keypair: Arc<SolanaKeypair>,
+        instruction_builder: Arc<dyn InstructionBuilder>,
+        key_share_dir: Option<PathBuf>,
+        allow_rekey: bool,
+    ) -> Self {
+        let key_package = key_share_dir.as_deref().and_then(|dir| {
+            let path = key_share_path(dir, &peer_id);
+            if !path.exists() {
+                return None;
+            }
+
+            match fs::read(&path).map_err(|err| err.to_string()).and_then(|data| {
+                serde_json::from_slice::<KeyPackage>(&data).map_err(|err| err.to_string())
+            }) {
+                Ok(key_package) => {
+                    tracing::info!(
+                        "Load FROST key share of group `{}`",
+                        key_package.group_public_key
+                    );
+                    Some(key_package)
+                }
+                Err(err) => {
+                    tracing::error!("Fail to load FROST key share `{}`: {err}", path.display());
+                    None
+                }
+            }
+        });
+
+        Self {
+            peer_id,
+            keypair,
+            instruction_builder,
+            key_share_dir,
+            allow_rekey,
+            key_package,
+            dkg_sessions: HashMap::new(),
+            signing_sessions: HashMap::new(),
+        }
+    }
+
+    #[must_use]
+    pub const fn key_package(&self) -> Option<&KeyPackage> { self.key_package.as_ref() }
+
+    /// Replacing the key share loses the group key and the funds at its
+    /// address, it is refused unless the operator allows re-keying
+    ///
+    /// # Errors
+    ///
+    /// * this participant has a key share and re-keying is not allowed
+    pub fn ensure_rekey_allowed(&self) -> Result<(), String> {
+        match &self.key_package {
+            Some(key_package) if !self.allow_rekey => Err(format!(
+                "this peer has key share of group `{}` and re-keying is not allowed",
+                key_package.group_public_key
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Handle request from the coordinator, the reason is replied if the
+    /// request is rejected
+    pub fn handle(&mut self, coordinator: PeerId, request: FrostRequest) -> FrostResponse {
+        let result = match request {
+            FrostRequest::DkgRound1 { session_id, participants, threshold } => {
+                self.dkg_round1(session_id, participants, threshold)
+            }
+            FrostRequest::DkgRound2 { session_id, packages } => {
+                self.dkg_round2(session_id, &packages)
+            }
+            FrostRequest::DkgFinalize { session_id, shares } => {
+                self.dkg_finalize(session_id, &shares)
+            }
+            FrostRequest::Commit { session_id, group_public_key } => {
+                self.commit(session_id, &group_public_key)
+            }
+            FrostRequest::Sign { session_id, raw_message, message, commitments } => {
+                self.sign(coordinator, session_id, &raw_message, &message, &commitments)
+            }
+        };
+
+        result.unwrap_or_else(|reason| {
+            tracing::warn!("Reject FROST request from {coordinator}: {reason}");
+            FrostResponse::Rejected(reason)
+        })
+    }
+
+    fn dkg_round1(
+        &mut self,
+        session_id: u64,
+        participants: Vec<String>,
+        threshold: u16,
+    ) -> Result<FrostResponse, String> {
+        let index = participants
+            .iter()
+            .position(|participant| *participant == self.peer_id.to_string())
+            .and_then(|position| u16::try_from(position + 1).ok())
+            .ok_or("this peer is not a participant")?;
+        self.ensure_rekey_allowed()?;
+        if threshold < 2 || usize::from(threshold) > participants.len() {
+            return Err(format!(
+                "invalid threshold {threshold} of {} participants",
+                participants.len()
+            ));
+        }
+
+        let (secret, mut package) = dkg::round1(index, threshold);
+        package.signature = self.keypair.sign_message(&dkg::round1_signing_message(
+            session_id,
+            &self.peer_id.to_string(),
+            &package,
+        ));
+        self.dkg_sessions.insert(session_id, DkgSession {
+            participants,
+            index,
+            threshold,
+            secret,
+            publics: None,
+        });
+
+        Ok(FrostResponse::DkgRound1(package))
+    }
+
+    fn dkg_round2(
+        &mut self,
+        session_id: u64,
+        packages: &BTreeMap<String, DkgRound1Package>,
+    ) -> Result<FrostResponse, String> {
+        let session = self.dkg_sessions.get_mut(&session_id).ok_or("unknown DKG session")?;
+
+        let mut publics = BTreeMap::new();
+        for (index, participant) in (1..).zip(&session.participants) {
+            let package = packages
+                .get(participant)
+                .ok_or_else(|| format!("missing round 1 package of `{participant}`"))?;
+            // packages are relayed by the coordinator, which must not replace them
+            let pubkey = participant
+                .parse::<PeerId>()
+                .ok()
+                .as_ref()
+                .and_then(peer_id_to_solana_pubkey)
+                .ok_or_else(|| format!("participant `{participant}` is not an ed25519 peer"))?;
+            let message = dkg::round1_signing_message(session_id, participant, package);
+            if !package.signature.verify(pubkey.as_ref(), &message) {
+                return Err(format!("invalid signature of round 1 package of `{participant}`"));
+            }
+            publics.insert(index, dkg::verify_round1(index, session.threshold, package)?);
+        }
+
+        let shares = dkg::round2(&session.secret, session_id, &publics)
+            .into_iter()
+            .map(|(index, share)| (session.participants[usize::from(index) - 1].clone(), share))
+            .collect();
+        session.publics = Some(publics);
+
+        Ok(FrostResponse::DkgRound2(shares))
+    }
+
+    fn dkg_finalize(
+        &mut self,
+        session_id: u64,
+        shares: &BTreeMap<String, FrostBytes>,
+    ) -> Result<FrostResponse, String> {
+        let DkgSession { participants, index, threshold, secret, publics } =
+            self.dkg_sessions.remove(&session_id).ok_or("unknown DKG session")?;
+        let publics = publics.ok_or("DKG round 2 is not completed")?;
+        self.ensure_rekey_allowed()?;
+
+        let shares = (1..)
+            .zip(&participants)
+            .filter_map(|(index, participant)| Some((index, *shares.get(participant)?)))
+            .collect();
+        let output = dkg::finalize(&secret, session_id, &publics, &shares)?;
+
+        let key_package = KeyPackage {
+            participants,
+            threshold,
+            index,
+            signing_share: output.signing_share.to_bytes(),
+            group_public_key: Pubkey::new_from_array(encode_point(&output.group_public_key)),
+            verifying_shares: output.verifying_shares.iter().map(encode_point).collect(),
+        };
+        if let Some(dir) = &self.key_share_dir {
+            persist_key_package(dir, &self.peer_id, &key_package, self.key_package.as_ref())?;
+        }
+
+        tracing::info!("Generate FROST key share of group `{}`", key_package.group_public_key);
+        let group_public_key = key_package.group_public_key;
+        self.key_package = Some(key_package);
+
+        Ok(FrostResponse::DkgFinalized(group_public_key))
+    }
+
+    fn commit(
+        &mut self,
+        session_id: u64,
+        group_public_key: &Pubkey,
+    ) -> Result<FrostResponse, String> {
+        let key_package = self.key_package.as_ref().ok_or("this peer does not have key share")?;
+        if key_package.group_public_key != *group_public_key {
+            return Err(format!("this peer does not have key share of `{group_public_key}`"));
+        }
+
+        if self.signing_sessions.len() >= MAX_SIGNING_SESSIONS {
+            self.signing_sessions.clear();
+        }
+        let nonces = signing::commit(&key_package.signing_share()?);
+        let commitments = nonces.commitments();
+        self.signing_sessions.insert(session_id, nonces);
+
+        Ok(FrostResponse::Commitments(commitments))
+    }
+
+    fn sign(
+        &mut self,
+        coordinator: PeerId,
+        session_id: u64,
+        raw_message: &RawMessage,
+        message: &Message,
+        commitments: &BTreeMap<String, SigningCommitments>,
+    ) -> Result<FrostResponse, String> {
+        // nonces must never be reused
+        let nonces = self.signing_sessions.remove(&session_id).ok_or("unknown signing session")?;
+        let key_package = self.key_package.as_ref().ok_or("this peer does not have key share")?;
+
+        // only sign the transaction built from the raw message
+        if !raw_message.verify_id() {
+            return Err(format!("message ID `{}` does not match its content", raw_message.id));
+        }
+        let signer =
+            InstructionSigner { peer_id: coordinator, pubkey: key_package.group_public_key };
+        let mut instructions =
+            self.instruction_builder.build(raw_message, &signer).map_err(|err| err.to_string())?;
+        // the coordinator sets the compute budget
+        instructions.splice(
+            0..0,
+            leading_compute_budget_instructions(&message.account_keys, &message.instructions),
+        );
+        let expected = Message::new_with_blockhash(
+            &instructions,
+            Some(&key_package.group_public_key),
+            &message.recent_blockhash,
+        );
+        if expected != *message {
+            return Err("transaction does not match the message".to_string());
+        }
+
+        let share = signing::sign(
+            key_package.index,
+            &key_package.signing_share()?,
+            &nonces,
+            &key_package.group_public_key()?,
+            &key_package.index_commitments(commitments)?,
+            &message.serialize(),
+        )?;
+
+        Ok(FrostResponse::SignatureShare(share.to_bytes()))
+    }
+}
+
+fn key_share_path(dir: &Path, peer_id: &PeerId) -> PathBuf {
+    dir.join(format!("frost-key-share-{peer_id}.json"))
+}
+
+/// The previous key share is kept as an archive, which is never overwritten
+fn persist_key_package(
+    dir: &Path,
+    peer_id: &PeerId,
+    key_package: &KeyPackage,
+    previous: Option<&KeyPackage>,
+) -> Result<(), String> {
+    let path = key_share_path(dir, peer_id);
+    let data = serde_json::to_vec_pretty(key_package).map_err(|err| err.to_string())?;
+    let persist = || -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        if path.exists() {
+            let archive_path = dir.join(format!(
+                "frost-key-share-{peer_id}-{}.json",
+                previous.map_or_else(
+                    || Utc::now().timestamp_millis().to_string(),
+                    |previous| previous.group_public_key.to_string()
+                )
+            ));
+            // fails if the archive exists
+            fs::hard_link(&path, &archive_path)?;
+            tracing::warn!("Archive previous FROST key share `{}`", archive_path.display());
+        }
+        write_secret_file(&path, &data)
+    };
+
+    persist().map_err(|err| format!("fail to persist key share `{}`: {err}", path.display()))
+}
+
+/// Write the file readable only by the owner. It is written to a temporary
+/// file then renamed, so that a crash never leaves a truncated file
+fn write_secret_file(path: &Path, data: &[u8]) -> io::Result<()> {
+    let temp_path = path.with_extension("tmp");
+    if temp_path.exists() {
+        fs::remove_file(&temp_path)?;
+    }
+
+    let mut file = OpenOptions::new().write(true).create_new(true).mode(0o600).open(&temp_path)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    fs::rename(&temp_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, os::unix::fs::PermissionsExt as _};
+
+    use super::write_secret_file;
+
+    #[test]
+    fn test_write_secret_file() {
+        let dir = std::env::temp_dir().join(format!("frost-test-{}", rand::random::<u64>()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("key-share.json");
+
+        write_secret_file(&path, b"old").unwrap();
+        write_secret_file(&path, b"new").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert!(!path.with_extension("tmp").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
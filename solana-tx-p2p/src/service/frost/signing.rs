@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+
+use curve25519_dalek::{edwards::EdwardsPoint, scalar::Scalar};
+use rand::RngCore as _;
+
+use crate::{
+    model::SigningCommitments,
+    service::frost::{
+        base_mul, challenge, decode_point, encode_point, hash, hash_to_scalar, identifier,
+        lagrange_coefficient,
+    },
+};
+
+/// Single-use nonces of a signing session
+pub struct SigningNonces {
+    hiding: Scalar,
+    binding: Scalar,
+    commitments: SigningCommitments,
+}
+
+/// Decoded commitments of signers keyed by index
+pub type Commitments = BTreeMap<u16, (EdwardsPoint, EdwardsPoint)>;
+
+/// Round 1: generate nonces and their commitments
+pub fn commit(signing_share: &Scalar) -> SigningNonces {
+    let hiding = generate_nonce(signing_share);
+    let binding = generate_nonce(signing_share);
+    let commitments = SigningCommitments {
+        hiding: encode_point(&base_mul(&hiding)),
+        binding: encode_point(&base_mul(&binding)),
+    };
+
+    SigningNonces { hiding, binding, commitments }
+}
+
+impl SigningNonces {
+    pub const fn commitments(&self) -> SigningCommitments { self.commitments }
+}
+
+pub fn decode_commitments(
+    commitments: &BTreeMap<u16, SigningCommitments>,
+) -> Result<Commitments, String> {
+    commitments
+        .iter()
+        .map(|(index, SigningCommitments { hiding, binding })| {
+            Ok((*index, (decode_point(hiding)?, decode_point(binding)?)))
+        })
+        .collect()
+}
+
+/// Round 2: compute the signature share of participant `index`
+pub fn sign(
+    index: u16,
+    signing_share: &Scalar,
+    nonces: &SigningNonces,
+    group_public_key: &EdwardsPoint,
+    commitments: &BTreeMap<u16, SigningCommitments>,
+    message: &[u8],
+) -> Result<Scalar, String> {
+    if commitments.get(&index) != Some(&nonces.commitments) {
+        return Err("commitments of this participant do not match".to_string());
+    }
+
+    let commitments = decode_commitments(commitments)?;
+    let binding_factors = binding_factors(group_public_key, &commitments, message);
+    let group_commitment = group_commitment(&commitments, &binding_factors);
+    let challenge = challenge(&group_commitment, group_public_key, message);
+    let signers = commitments.keys().copied().collect::<Vec<_>>();
+
+    Ok(nonces.hiding
+        + nonces.binding * binding_factors[&index]
+        + lagrange_coefficient(index, &signers) * signing_share * challenge)
+}
+
+/// Verify signature shares and aggregate them into an Ed25519 signature
+pub fn aggregate(
+    group_public_key: &EdwardsPoint,
+    verifying_shares: &BTreeMap<u16, EdwardsPoint>,
+    commitments: &Commitments,
+    message: &[u8],
+    shares: &BTreeMap<u16, Scalar>,
+) -> Result<[u8; 64], String> {
+    let binding_factors = binding_factors(group_public_key, commitments, message);
+    let group_commitment = group_commitment(commitments, &binding_factors);
+    let challenge = challenge(&group_commitment, group_public_key, message);
+    let signers = commitments.keys().copied().collect::<Vec<_>>();
+
+    let mut z = Scalar::ZERO;
+    for (index, (hiding, binding)) in commitments {
+        let share =
+            shares.get(index).ok_or_else(|| format!("missing signature share of {index}"))?;
+        let verifying_share =
+            verifying_shares.get(index).ok_or_else(|| format!("unknown participant {index}"))?;
+        let expected = hiding
+            + binding * binding_factors[index]
+            + verifying_share * (lagrange_coefficient(*index, &signers) * challenge);
+        if base_mul(share) != expected {
+            return Err(format!("invalid signature share of participant {index}"));
+        }
+        z += share;
+    }
+
+    let mut signature = [0; 64];
+    signature[..32].copy_from_slice(group_commitment.compress().as_bytes());
+    signature[32..].copy_from_slice(z.as_bytes());
+    Ok(signature)
+}
+
+fn generate_nonce(secret: &Scalar) -> Scalar {
+    let mut random_bytes = [0; 32];
+    rand::rngs::OsRng.fill_bytes(&mut random_bytes);
+
+    hash_to_scalar(b"nonce", &[&random_bytes, secret.as_bytes()])
+}
+
+fn binding_factors(
+    group_public_key: &EdwardsPoint,
+    commitments: &Commitments,
+    message: &[u8],
+) -> BTreeMap<u16, Scalar> {
+    let encoded_commitments = commitments
+        .iter()
+        .flat_map(|(index, (hiding, binding))| {
+            [identifier(*index).to_bytes(), encode_point(hiding), encode_point(binding)]
+        })
+        .flatten()
+        .collect::<Vec<_>>();
+    let message_hash = hash(b"msg", &[message]);
+    let commitments_hash = hash(b"com", &[&encoded_commitments]);
+
+    commitments
+        .keys()
+        .map(|index| {
+            let binding_factor = hash_to_scalar(b"rho", &[
+                group_public_key.compress().as_bytes(),
+                &message_hash,
+                &commitments_hash,
+                identifier(*index).as_bytes(),
+            ]);
+            (*index, binding_factor)
+        })
+        .collect()
+}
+
+fn group_commitment(
+    commitments: &Commitments,
+    binding_factors: &BTreeMap<u16, Scalar>,
+) -> EdwardsPoint {
+    commitments
+        .iter()
+        .map(|(index, (hiding, binding))| hiding + binding * binding_factors[index])
+        .sum()
+}
@@ -1,34 +1,67 @@
 pub mod error;
+mod event;
+mod frost;
 mod peer;
 mod round_robin_election;
+mod seen_cache;
 mod solana;
+mod store;
 
 use std::time::Duration;
 
 use axum::async_trait;
 use rand::random;
-use solana_sdk::transaction::Transaction;
 use tokio::{sync::mpsc, time};
 
 pub use self::{
     error::Result,
+    event::{EventBroadcaster, EventSubscription},
+    frost::FrostConfig,
     peer::{DefaultPeerService, PeerWorker, PeerWorkerInboundEvent, PeerWorkerInstruction},
     round_robin_election::{RRElectionWorker, RRElectionWorkerType},
     solana::{
-        create_solana_client, RelayerInboundEvent, SignerInboundEvent, SolanaRelayer, SolanaSigner,
+        create_solana_client, fetch_address_lookup_tables, BatchConfig, BlockhashCache,
+        ComputeBudgetConfig, ComputeUnitPrice, ConfirmationTracker, DurableNonce,
+        DurableNonceConfig, FundingConfig, FundingStrategy, HelloWorldInstructionBuilder,
+        InstructionBuilder, InstructionSigner, MemoInstructionBuilder, MultisigConfig,
+        RawInstructionBuilder, RelayRetryConfig, RelayerFeePayerConfig, RelayerInboundEvent,
+        RpcPool, RpcPoolConfig, SignerInboundEvent, SigningPolicyConfig, SolanaRelayer,
+        SolanaSigner, WalletFunding, MAX_COMPUTE_UNIT_LIMIT, MEMO_PROGRAM_ID,
     },
+    store::{MemoryStore, RedbStore, Store, StorePruner, StoreWrite, StoreWriter},
 };
 use crate::{model, ShutdownSignal};
 
 #[async_trait]
 pub trait PeerService {
-    async fn discovery_peers(&self) -> Result<Vec<String>>;
+    async fn submit_message(&self, data: Vec<u8>) -> Result<model::MessageId>;
 
-    async fn list_signed_messages(&self) -> Result<Vec<Transaction>>;
+    async fn discovery_peers(&self, query: &model::ListQuery) -> Result<model::Page<String>>;
 
-    async fn list_relayed_transactions(&self) -> Result<Vec<String>>;
+    async fn list_signed_messages(
+        &self,
+        query: &model::ListQuery,
+    ) -> Result<model::Page<model::SignedMessage>>;
+
+    async fn list_relayed_transactions(
+        &self,
+        query: &model::ListQuery,
+    ) -> Result<model::Page<model::RelayedTransaction>>;
 
     async fn get_transaction(&self, signature: &str) -> Result<model::TransactionDetail>;
+
+    async fn get_message_lifecycle(&self, message_id: &str) -> Result<model::MessageLifecycle>;
+
+    async fn get_message_lifecycle_by_signature(
+        &self,
+        signature: &str,
+    ) -> Result<model::MessageLifecycle>;
+
+    async fn list_dead_letters(&self) -> Result<Vec<model::DeadLetter>>;
+
+    async fn replay_dead_letter(&self, signature: &str) -> Result<model::DeadLetter>;
+
+    async fn subscribe_events(&self, filter: model::EventFilter) -> Result<EventSubscription>;
 }
 
 /// Trigger message
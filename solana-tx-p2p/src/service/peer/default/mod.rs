@@ -1,29 +1,58 @@
+use std::{collections::BTreeSet, sync::Arc};
+
 use async_trait::async_trait;
-use snafu::ResultExt;
-use solana_sdk::transaction::Transaction;
+use snafu::{OptionExt, ResultExt};
+use solana_sdk::signature::Signature;
 use tokio::sync::{mpsc, oneshot};
 
 use crate::{
     model,
-    service::{error, error::Result, PeerService, PeerWorkerInboundEvent, PeerWorkerInstruction},
+    service::{
+        error,
+        error::Result,
+        store::{collect_records, scan, RecordQuery},
+        EventBroadcaster, EventSubscription, PeerService, PeerWorkerInboundEvent,
+        PeerWorkerInstruction, Store,
+    },
 };
 
 #[derive(Clone)]
 pub struct DefaultPeerService {
     peer_worker_inbound_sender: mpsc::Sender<PeerWorkerInboundEvent>,
+    /// records written by the peer worker are read from the store directly
+    store: Arc<dyn Store>,
+    events: EventBroadcaster,
 }
 
 impl DefaultPeerService {
     #[inline]
     #[must_use]
-    pub const fn new(peer_worker_inbound_sender: mpsc::Sender<PeerWorkerInboundEvent>) -> Self {
-        Self { peer_worker_inbound_sender }
+    pub fn new(
+        peer_worker_inbound_sender: mpsc::Sender<PeerWorkerInboundEvent>,
+        store: Arc<dyn Store>,
+        events: EventBroadcaster,
+    ) -> Self {
+        Self { peer_worker_inbound_sender, store, events }
     }
 }
 
 #[async_trait]
 impl PeerService for DefaultPeerService {
-    async fn discovery_peers(&self) -> Result<Vec<String>> {
+    async fn submit_message(&self, data: Vec<u8>) -> Result<model::MessageId> {
+        let (sender, receiver) = oneshot::channel();
+
+        let instruction = PeerWorkerInstruction::SubmitMessage((data, sender));
+        self.peer_worker_inbound_sender
+            .send(PeerWorkerInboundEvent::Instruction(instruction))
+            .await
+            .context(error::SendPeerWorkerInstructionSnafu { instruction: "SubmitMessage" })?;
+
+        let message_id = receiver.await.context(error::SubmitMessageSnafu)?;
+
+        Ok(message_id)
+    }
+
+    async fn discovery_peers(&self, query: &model::ListQuery) -> Result<model::Page<String>> {
         let (sender, receiver) = oneshot::channel();
 
         let instruction = PeerWorkerInstruction::ListPeers(sender);
@@ -34,48 +63,99 @@ impl PeerService for DefaultPeerService {
 
         let peers = receiver.await.context(error::ListPeersSnafu)?;
 
-        Ok(peers)
+        // peers are not recorded, so they are paged by their IDs only
+        let peers = peers.into_iter().map(|peer| (0, peer)).collect::<BTreeSet<_>>();
+        let query = RecordQuery {
+            since: None,
+            until: None,
+            signer: None,
+            relayer: None,
+            ..RecordQuery::from_list_query(query)?
+        };
+        let peers = collect_records(
+            scan(&peers, &query).cloned().map(Ok),
+            query.limit,
+            |peer| Ok(Some(peer.to_string())),
+            |_| true,
+        )?;
+
+        Ok(peers.into())
+    }
+
+    async fn list_signed_messages(
+        &self,
+        query: &model::ListQuery,
+    ) -> Result<model::Page<model::SignedMessage>> {
+        Ok(self.store.list_signed_messages(&RecordQuery::from_list_query(query)?)?.into())
+    }
+
+    async fn list_relayed_transactions(
+        &self,
+        query: &model::ListQuery,
+    ) -> Result<model::Page<model::RelayedTransaction>> {
+        Ok(self.store.list_relayed_transactions(&RecordQuery::from_list_query(query)?)?.into())
     }
 
-    async fn list_signed_messages(&self) -> Result<Vec<Transaction>> {
+    async fn get_transaction(&self, signature: &str) -> Result<model::TransactionDetail> {
         let (sender, receiver) = oneshot::channel();
 
-        let instruction = PeerWorkerInstruction::ListSignedMessages(sender);
+        let instruction = PeerWorkerInstruction::GetTransaction((signature.to_string(), sender));
         self.peer_worker_inbound_sender
             .send(PeerWorkerInboundEvent::Instruction(instruction))
             .await
-            .context(error::SendPeerWorkerInstructionSnafu { instruction: "ListSignedMessages" })?;
+            .context(error::SendPeerWorkerInstructionSnafu { instruction: "GetTransaction" })?;
 
-        let signed_messages = receiver.await.context(error::ListSignedMessagesSnafu)?;
+        receiver.await.context(error::GetTransactionSnafu)?
+    }
+
+    async fn get_message_lifecycle(&self, message_id: &str) -> Result<model::MessageLifecycle> {
+        self.store
+            .get_lifecycle(&message_id.parse().context(error::ParseMessageIdSnafu)?)?
+            .context(error::MessageLifecycleNotFoundSnafu { key: message_id })
+    }
 
-        Ok(signed_messages)
+    async fn get_message_lifecycle_by_signature(
+        &self,
+        signature: &str,
+    ) -> Result<model::MessageLifecycle> {
+        let signature =
+            signature.parse::<Signature>().context(error::ParseSolanaSignatureSnafu)?.to_string();
+        self.store
+            .get_lifecycle_by_signature(&signature)?
+            .context(error::MessageLifecycleNotFoundSnafu { key: signature })
     }
 
-    async fn list_relayed_transactions(&self) -> Result<Vec<String>> {
+    async fn list_dead_letters(&self) -> Result<Vec<model::DeadLetter>> {
         let (sender, receiver) = oneshot::channel();
 
-        let instruction = PeerWorkerInstruction::ListRelayedTransactions(sender);
+        let instruction = PeerWorkerInstruction::ListDeadLetters(sender);
         self.peer_worker_inbound_sender
             .send(PeerWorkerInboundEvent::Instruction(instruction))
             .await
-            .context(error::SendPeerWorkerInstructionSnafu {
-                instruction: "ListRelayedTransactions",
-            })?;
+            .context(error::SendPeerWorkerInstructionSnafu { instruction: "ListDeadLetters" })?;
 
-        let relayed_transactions = receiver.await.context(error::ListRelayedTransactionsSnafu)?;
+        let dead_letters = receiver.await.context(error::ListDeadLettersSnafu)?;
 
-        Ok(relayed_transactions)
+        Ok(dead_letters)
     }
 
-    async fn get_transaction(&self, signature: &str) -> Result<model::TransactionDetail> {
+    async fn replay_dead_letter(&self, signature: &str) -> Result<model::DeadLetter> {
         let (sender, receiver) = oneshot::channel();
 
-        let instruction = PeerWorkerInstruction::GetTransaction((signature.to_string(), sender));
+        let signature = signature.parse::<Signature>().context(error::ParseSolanaSignatureSnafu)?;
+        let instruction = PeerWorkerInstruction::ReplayDeadLetter((signature, sender));
         self.peer_worker_inbound_sender
             .send(PeerWorkerInboundEvent::Instruction(instruction))
             .await
-            .context(error::SendPeerWorkerInstructionSnafu { instruction: "GetTransaction" })?;
+            .context(error::SendPeerWorkerInstructionSnafu { instruction: "ReplayDeadLetter" })?;
+
+        receiver
+            .await
+            .context(error::ReplayDeadLetterSnafu)?
+            .context(error::DeadLetterNotFoundSnafu { signature })
+    }
 
-        receiver.await.context(error::ListRelayedTransactionsSnafu)?
+    async fn subscribe_events(&self, filter: model::EventFilter) -> Result<EventSubscription> {
+        self.events.subscribe(filter)
     }
 }
@@ -1,5 +1,11 @@
-use std::{collections::HashSet, str::FromStr, sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
 
+use chrono::{DateTime, Utc};
 use lazy_static::lazy_static;
 use libp2p::{
     floodsub::{Floodsub, FloodsubEvent, FloodsubMessage, Topic},
@@ -7,27 +13,43 @@ use libp2p::{
     identity,
     mdns::{tokio::Behaviour as Mdns, Config as MdnsConfig, Event as MdnsEvent},
     noise::Config as NoiseConfig,
+    request_response::{
+        self, json::Behaviour as JsonRequestResponse, Event as RequestResponseEvent,
+        Message as RequestResponseMessage, OutboundRequestId, ProtocolSupport, ResponseChannel,
+    },
     swarm::{NetworkBehaviour, Swarm, SwarmEvent},
     tcp::Config as TcpConfig,
     yamux::Config as YamuxConfig,
-    PeerId, SwarmBuilder,
+    PeerId, StreamProtocol, SwarmBuilder,
+};
+use snafu::{OptionExt, ResultExt};
+use solana_client::{
+    nonblocking::rpc_client::RpcClient, rpc_config::RpcTransactionConfig, rpc_request::RpcRequest,
 };
-use snafu::ResultExt;
-use solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::RpcTransactionConfig};
 use solana_sdk::{
     commitment_config::CommitmentConfig,
     signature::{Keypair as SolanaKeypair, Signature},
-    transaction::Transaction,
+    transaction::VersionedTransaction,
+};
+use solana_transaction_status_client_types::{
+    EncodedConfirmedTransactionWithStatusMeta, UiTransactionEncoding,
+};
+use tokio::sync::{
+    mpsc::{self, error::TrySendError},
+    oneshot, RwLock,
 };
-use solana_transaction_status_client_types::UiTransactionEncoding;
-use tokio::sync::{mpsc, oneshot, RwLock};
 
 use crate::{
-    model,
+    metrics,
+    model::{
+        self, CatchupRequest, CatchupResponse, DeadLetter, LeaderSyncInfo, MessageId,
+        MessageLifecycleEvent, MessageReceived, MessageStage, NodeEventKind, PeerConnection,
+        PeerRequest, PeerResponse, RawMessage, RelayMarker, RelayedTransaction, SignedMessage,
+        SimulationResult,
+    },
     service::{
-        error,
-        round_robin_election::{ElectionWorkerInboundEvent, LeaderSyncInfo},
-        RelayerInboundEvent, Result, SignerInboundEvent,
+        error, round_robin_election::ElectionWorkerInboundEvent, store::RecordQuery,
+        EventBroadcaster, RelayerInboundEvent, Result, SignerInboundEvent, Store, StoreWrite,
     },
     ShutdownSignal,
 };
@@ -39,6 +61,8 @@ lazy_static! {
     static ref SIGNER_INFO_TOPIC: Topic = Topic::new("signer-info");
     static ref TRANSACTION_TOPIC: Topic = Topic::new("transaction");
     static ref RELAYED_TRANSACTION_TOPIC: Topic = Topic::new("relayed-transaction");
+    static ref LIFECYCLE_TOPIC: Topic = Topic::new("lifecycle");
+    static ref RELAY_MARKER_TOPIC: Topic = Topic::new("relay-marker");
     static ref TOPICS: Vec<Topic> = vec![
         MESSAGE_TOPIC.clone(),
         HEARTBEAT_TOPIC.clone(),
@@ -46,9 +70,19 @@ lazy_static! {
         SIGNER_INFO_TOPIC.clone(),
         TRANSACTION_TOPIC.clone(),
         RELAYED_TRANSACTION_TOPIC.clone(),
+        LIFECYCLE_TOPIC.clone(),
+        RELAY_MARKER_TOPIC.clone(),
     ];
 }
 
+const REQUEST_RESPONSE_PROTOCOL: StreamProtocol = StreamProtocol::new("/solana-tx-p2p/request/1");
+/// Simulation results kept by the relayer of this peer, the oldest are dropped
+/// when exceeded
+const MAX_SIMULATIONS: usize = 1_000;
+/// Relayed transactions and signed messages replied to a catch-up request, the
+/// most recent are replied when exceeded
+const MAX_CATCHUP_RECORDS: usize = 1_000;
+
 enum Action {
     Input(Option<String>),
     InboundEvent(Option<PeerWorkerInboundEvent>),
@@ -62,17 +96,28 @@ pub enum PeerWorkerInboundEvent {
     HeartbeatTrigger,
     RelayerSyncInfo(LeaderSyncInfo),
     SignerSyncInfo(LeaderSyncInfo),
-    Transaction(Transaction),
-    RelayedTransaction(String),
+    Transaction(SignedMessage),
+    /// The relayer sends the transaction or its commitment status changes
+    RelayedTransaction(RelayedTransaction),
+    /// The relayer simulates the transaction before relaying it
+    Simulation((VersionedTransaction, SimulationResult)),
+    /// The relayer gossips the relay state of a transaction
+    RelayMarker(RelayMarker),
+    Lifecycle(MessageLifecycleEvent),
     Instruction(PeerWorkerInstruction),
+    /// Send request to a peer, `None` is replied if the request fails
+    Request((PeerId, PeerRequest, oneshot::Sender<Option<PeerResponse>>)),
+    /// Reply to a request from a peer
+    Response((ResponseChannel<PeerResponse>, PeerResponse)),
 }
 
 #[derive(Debug)]
 pub enum PeerWorkerInstruction {
+    SubmitMessage((Vec<u8>, oneshot::Sender<MessageId>)),
     ListPeers(oneshot::Sender<Vec<String>>),
-    ListSignedMessages(oneshot::Sender<Vec<Transaction>>),
-    ListRelayedTransactions(oneshot::Sender<Vec<String>>),
     GetTransaction((String, oneshot::Sender<Result<model::TransactionDetail>>)),
+    ListDeadLetters(oneshot::Sender<Vec<DeadLetter>>),
+    ReplayDeadLetter((Signature, oneshot::Sender<Option<DeadLetter>>)),
 }
 
 #[derive(NetworkBehaviour)]
@@ -80,12 +125,14 @@ pub enum PeerWorkerInstruction {
 struct PeerBehaviour {
     floodsub: Floodsub,
     mdns: Mdns,
+    request_response: JsonRequestResponse<PeerRequest, PeerResponse>,
 }
 
 #[derive(Debug)]
 enum PeerBehaviourEvent {
     Floobsub(FloodsubEvent),
     Mdns(MdnsEvent),
+    RequestResponse(RequestResponseEvent<PeerRequest, PeerResponse>),
 }
 
 impl From<FloodsubEvent> for PeerBehaviourEvent {
@@ -96,6 +143,15 @@ impl From<MdnsEvent> for PeerBehaviourEvent {
     fn from(event: MdnsEvent) -> Self { Self::Mdns(event) }
 }
 
+impl From<RequestResponseEvent<PeerRequest, PeerResponse>> for PeerBehaviourEvent {
+    fn from(event: RequestResponseEvent<PeerRequest, PeerResponse>) -> Self {
+        Self::RequestResponse(event)
+    }
+}
+
+// SAFETY: allow: `peer_worker_inbound_*` are named after the channel they
+// belong to
+#[allow(clippy::struct_field_names)]
 pub struct PeerWorker {
     key: identity::Keypair,
     peer_id: PeerId,
@@ -109,14 +165,31 @@ pub struct PeerWorker {
     relayer_election_worker_inbound_sender: mpsc::Sender<ElectionWorkerInboundEvent>,
     signer_election_worker_inbound_sender: mpsc::Sender<ElectionWorkerInboundEvent>,
 
+    /// the swarm loop never waits for the relayer and the signer, which wait
+    /// for the peer worker in turn, events are dropped if they fall behind
     solana_relayer_inbound_sender: mpsc::Sender<RelayerInboundEvent>,
     solana_signer_inbound_sender: mpsc::Sender<SignerInboundEvent>,
 
     peer_worker_inbound_sender: mpsc::Sender<PeerWorkerInboundEvent>,
     peer_worker_inbound_receiver: mpsc::Receiver<PeerWorkerInboundEvent>,
 
-    signed_messages: Arc<RwLock<Vec<Transaction>>>,
-    relayed_transactions: Arc<RwLock<Vec<String>>>,
+    /// signed messages, relayed transactions and message lifecycles, written
+    /// by the store writer so that the swarm loop never waits for the store
+    store: Arc<dyn Store>,
+    store_write_sender: mpsc::Sender<StoreWrite>,
+    events: EventBroadcaster,
+    simulations: Arc<RwLock<VecDeque<(VersionedTransaction, SimulationResult)>>>,
+
+    /// requests sent to peers which are waiting for responses
+    pending_requests: HashMap<OutboundRequestId, oneshot::Sender<Option<PeerResponse>>>,
+
+    /// latest leader sync info elected by this peer or gossiped by peers,
+    /// replied to peers catching up
+    relayer_sync_info: Option<LeaderSyncInfo>,
+    signer_sync_info: Option<LeaderSyncInfo>,
+    catchup_window: Duration,
+    /// this peer catches up from the first peer connected after it starts
+    catchup: Catchup,
 
     solana_client: Arc<RpcClient>,
 }
@@ -135,6 +208,10 @@ impl PeerWorker {
         signer_election_worker_inbound_sender: mpsc::Sender<ElectionWorkerInboundEvent>,
         solana_relayer_inbound_sender: mpsc::Sender<RelayerInboundEvent>,
         solana_signer_inbound_sender: mpsc::Sender<SignerInboundEvent>,
+        store: Arc<dyn Store>,
+        store_write_sender: mpsc::Sender<StoreWrite>,
+        events: EventBroadcaster,
+        catchup_window: Duration,
         solana_client: Arc<RpcClient>,
     ) -> Self {
         let peer_id = key.public().into();
@@ -155,8 +232,15 @@ impl PeerWorker {
             solana_signer_inbound_sender,
             peer_worker_inbound_receiver,
             peer_worker_inbound_sender,
-            signed_messages: Arc::new(RwLock::new(Vec::new())),
-            relayed_transactions: Arc::new(RwLock::new(Vec::new())),
+            store,
+            store_write_sender,
+            events,
+            simulations: Arc::new(RwLock::new(VecDeque::new())),
+            pending_requests: HashMap::new(),
+            relayer_sync_info: None,
+            signer_sync_info: None,
+            catchup_window,
+            catchup: Catchup::Pending,
             solana_client,
         }
     }
@@ -192,8 +276,13 @@ impl PeerWorker {
     /// - send peer heartbeat to the p2p network
     /// - send relayer sync info to the p2p network
     /// - send signer sync info to the p2p network
+    /// - send message lifecycle events to the p2p network
+    /// - catch up leaders, relayed transactions and pending signed messages
+    ///   from an established peer when this peer joins
     /// - get transaction from solana
-    /// - record peers, signed messages, relayed transactions in the memory
+    /// - record peers in the memory
+    /// - record signed messages, relayed transactions, message lifecycles in
+    ///   the store
     ///
     /// # Errors
     ///
@@ -233,11 +322,15 @@ impl PeerWorker {
                             cmd if cmd.starts_with("ls p") => handle_list_peers(&swarm),
                             cmd if cmd.starts_with("ls sm") => tracing::info!(
                                 "Signed Messages: {:?}",
-                                *self.signed_messages.read().await
+                                self.store
+                                    .list_signed_messages(&RecordQuery::default())
+                                    .map(|records| records.records)
                             ),
                             cmd if cmd.starts_with("ls tx") => tracing::info!(
                                 "Relayed Transactions: {:?}",
-                                *self.relayed_transactions.read().await
+                                self.store
+                                    .list_relayed_transactions(&RecordQuery::default())
+                                    .map(|records| records.records)
                             ),
                             cmd if cmd.starts_with("get tx") => {
                                 let signature =
@@ -245,6 +338,17 @@ impl PeerWorker {
                                 let tx = get_transaction(&self.solana_client, signature).await;
                                 tracing::info!("Get transaction `{signature} result: {tx:?}");
                             }
+                            cmd if cmd.starts_with("get lc") => {
+                                let key = cmd.strip_prefix("get lc").expect("must match").trim();
+                                let lifecycle = match key.parse::<MessageId>() {
+                                    Ok(message_id) => self.store.get_lifecycle(&message_id),
+                                    Err(_) => self.store.get_lifecycle_by_signature(key),
+                                };
+                                tracing::info!("Get lifecycle `{key}` result: {lifecycle:?}");
+                            }
+                            cmd if cmd.starts_with("dkg") => {
+                                let _ = self.send_to_signer(SignerInboundEvent::StartDkg, "dkg");
+                            }
                             _ => tracing::error!("unknown command from stdin"),
                         }
                     } else {
@@ -258,19 +362,8 @@ impl PeerWorker {
                     }
                     Some(PeerWorkerInboundEvent::MessageTrigger) => {
                         tracing::warn!("Message trigger");
-                        // send message to p2p network
-                        let message = format!("Message from {}", self.peer_id.clone());
-                        swarm
-                            .behaviour_mut()
-                            .floodsub
-                            .publish(MESSAGE_TOPIC.clone(), message.clone());
-
-                        if let Err(err) = self
-                            .solana_signer_inbound_sender
-                            .send(SignerInboundEvent::RawMessage(message.into()))
-                            .await
-                        {
-                            tracing::error!("Fail to send message to solana signer: {err}");
+                        let data = format!("Message from {}", self.peer_id).into_bytes();
+                        if self.publish_message(&mut swarm, data).is_err() {
                             break;
                         }
                     }
@@ -278,6 +371,7 @@ impl PeerWorker {
                         self.heartbeat_trigger(&mut swarm, HEARTBEAT_TOPIC.clone()).await;
                     }
                     Some(PeerWorkerInboundEvent::RelayerSyncInfo(leader_sync_info)) => {
+                        self.relayer_sync_info = Some(leader_sync_info.clone());
                         // send relayer info to p2p network
                         swarm.behaviour_mut().floodsub.publish(
                             RELAYER_INFO_TOPIC.clone(),
@@ -286,6 +380,7 @@ impl PeerWorker {
                         );
                     }
                     Some(PeerWorkerInboundEvent::SignerSyncInfo(leader_sync_info)) => {
+                        self.signer_sync_info = Some(leader_sync_info.clone());
                         // send signer info to p2p network
                         swarm.behaviour_mut().floodsub.publish(
                             SIGNER_INFO_TOPIC.clone(),
@@ -293,30 +388,70 @@ impl PeerWorker {
                                 .expect("LeaderSyncInfo is valid json"),
                         );
                     }
-                    Some(PeerWorkerInboundEvent::Transaction(transaction)) => {
+                    Some(PeerWorkerInboundEvent::Transaction(signed_message)) => {
                         // send transaction to p2p network
-                        tracing::debug!("Transaction: {transaction:?}");
+                        tracing::debug!("Signed message: {signed_message:?}");
                         swarm.behaviour_mut().floodsub.publish(
                             TRANSACTION_TOPIC.clone(),
-                            serde_json::to_vec(&transaction).expect("Transaction is valid json"),
+                            serde_json::to_vec(&signed_message)
+                                .expect("SignedMessage is valid json"),
                         );
 
-                        if let Err(err) = self
-                            .solana_relayer_inbound_sender
-                            .send(RelayerInboundEvent::Transaction(transaction.clone()))
-                            .await
+                        self.record_signed_message(&signed_message);
+                        if self
+                            .send_to_relayer(RelayerInboundEvent::Transaction(signed_message))
+                            .is_err()
                         {
-                            tracing::error!("Fail to send transaction to solana relayer: {err}");
                             break;
                         }
+                    }
+                    Some(PeerWorkerInboundEvent::RelayedTransaction(relayed_transaction)) => {
+                        // send relayed transaction to p2p network
+                        swarm.behaviour_mut().floodsub.publish(
+                            RELAYED_TRANSACTION_TOPIC.clone(),
+                            serde_json::to_vec(&relayed_transaction)
+                                .expect("RelayedTransaction is valid json"),
+                        );
 
-                        self.signed_messages.write().await.push(transaction);
+                        self.record_relayed_transaction(&relayed_transaction);
                     }
-                    Some(PeerWorkerInboundEvent::RelayedTransaction(transaction)) => {
-                        self.relayed_transactions.write().await.push(transaction);
+                    Some(PeerWorkerInboundEvent::Simulation(simulation)) => {
+                        let mut simulations = self.simulations.write().await;
+                        if simulations.len() >= MAX_SIMULATIONS {
+                            simulations.pop_front();
+                        }
+                        simulations.push_back(simulation);
+                    }
+                    Some(PeerWorkerInboundEvent::RelayMarker(marker)) => {
+                        swarm.behaviour_mut().floodsub.publish(
+                            RELAY_MARKER_TOPIC.clone(),
+                            serde_json::to_vec(&marker).expect("RelayMarker is valid json"),
+                        );
+                    }
+                    Some(PeerWorkerInboundEvent::Lifecycle(lifecycle_event)) => {
+                        if self.publish_lifecycle_event(&mut swarm, lifecycle_event).is_err() {
+                            break;
+                        }
                     }
                     Some(PeerWorkerInboundEvent::Instruction(instruction)) => {
-                        self.handle_instruction(instruction).await;
+                        if self.handle_instruction(&mut swarm, instruction).await.is_err() {
+                            break;
+                        }
+                    }
+                    Some(PeerWorkerInboundEvent::Request((peer_id, request, sender))) => {
+                        let request_id =
+                            swarm.behaviour_mut().request_response.send_request(&peer_id, request);
+                        self.pending_requests.insert(request_id, sender);
+                    }
+                    Some(PeerWorkerInboundEvent::Response((channel, response))) => {
+                        if swarm
+                            .behaviour_mut()
+                            .request_response
+                            .send_response(channel, response)
+                            .is_err()
+                        {
+                            tracing::warn!("Fail to send response, the connection is closed");
+                        }
                     }
                 },
                 Action::Swarm(swarm_event) => match swarm_event {
@@ -327,6 +462,11 @@ impl PeerWorker {
                             break;
                         }
                     }
+                    SwarmEvent::Behaviour(PeerBehaviourEvent::RequestResponse(event)) => {
+                        if self.handle_request_response(&mut swarm, event).await.is_err() {
+                            break;
+                        }
+                    }
                     SwarmEvent::Behaviour(PeerBehaviourEvent::Mdns(mdns_event)) => match mdns_event
                     {
                         MdnsEvent::Discovered(discovered_list) => {
@@ -350,14 +490,29 @@ impl PeerWorker {
                             }
                         }
                     },
-                    ref event @ SwarmEvent::ConnectionEstablished { peer_id, .. } => {
+                    ref event @ SwarmEvent::ConnectionEstablished {
+                        peer_id,
+                        num_established,
+                        ..
+                    } => {
                         tracing::info!("New Peer connection established {event:?}");
                         {
                             self.peers.write().await.push(peer_id.to_string());
                         }
+                        if num_established.get() == 1 {
+                            self.events.publish(NodeEventKind::PeerJoined(PeerConnection {
+                                peer_id: peer_id.to_string(),
+                            }));
+                        }
+                        self.request_catchup(&mut swarm, peer_id);
                     }
-                    ref event @ SwarmEvent::ConnectionClosed { peer_id, .. } => {
+                    ref event @ SwarmEvent::ConnectionClosed { peer_id, num_established, .. } => {
                         tracing::info!("Peer connection closed {event:?}");
+                        if num_established == 0 {
+                            self.events.publish(NodeEventKind::PeerLeft(PeerConnection {
+                                peer_id: peer_id.to_string(),
+                            }));
+                        }
                         {
                             let mut peers = self.peers.write().await;
                             if let Ok(idx) = peers.binary_search(&peer_id.to_string()) {
@@ -387,30 +542,129 @@ impl PeerWorker {
         self.send_leader_heartbeat(&self.peer_id).await;
     }
 
+    fn publish_message(
+        &self,
+        swarm: &mut Swarm<PeerBehaviour>,
+        data: Vec<u8>,
+    ) -> Result<MessageId, ()> {
+        // send message to p2p network
+        let message = RawMessage::new(self.peer_id.to_string(), data);
+        let message_id = message.id;
+        self.publish_message_received(&message);
+        swarm.behaviour_mut().floodsub.publish(
+            MESSAGE_TOPIC.clone(),
+            serde_json::to_vec(&message).expect("RawMessage is valid json"),
+        );
+
+        let lifecycle_event = MessageLifecycleEvent::new(
+            message_id,
+            MessageStage::Received,
+            self.peer_id.to_string(),
+            None,
+        );
+        self.publish_lifecycle_event(swarm, lifecycle_event)?;
+
+        self.send_to_signer(SignerInboundEvent::RawMessage(message), "message")?;
+
+        Ok(message_id)
+    }
+
+    fn publish_lifecycle_event(
+        &self,
+        swarm: &mut Swarm<PeerBehaviour>,
+        event: MessageLifecycleEvent,
+    ) -> Result<(), ()> {
+        tracing::debug!("Message `{}` is {} by {}", event.message_id, event.stage, event.peer_id);
+        swarm.behaviour_mut().floodsub.publish(
+            LIFECYCLE_TOPIC.clone(),
+            serde_json::to_vec(&event).expect("MessageLifecycleEvent is valid json"),
+        );
+
+        // the signer of this peer is notified of messages expired on the relayer
+        // of this peer
+        let notified = self.notify_signer(&event);
+        self.record_lifecycle_event(event);
+        notified
+    }
+
+    fn publish_message_received(&self, message: &RawMessage) {
+        self.events.publish(NodeEventKind::MessageReceived(MessageReceived {
+            message_id: message.id.to_string(),
+            origin: message.origin.clone(),
+        }));
+    }
+
+    fn record_signed_message(&self, signed_message: &SignedMessage) {
+        let write = StoreWrite::SignedMessage(signed_message.clone(), Utc::now());
+        let _ = try_send(&self.store_write_sender, write, "store", "signed message");
+    }
+
+    fn record_relayed_transaction(&self, relayed_transaction: &RelayedTransaction) {
+        let write = StoreWrite::RelayedTransaction(relayed_transaction.clone(), Utc::now());
+        let _ = try_send(&self.store_write_sender, write, "store", "relayed transaction");
+    }
+
+    fn record_lifecycle_event(&self, event: MessageLifecycleEvent) {
+        let write = StoreWrite::LifecycleEvent(event);
+        let _ = try_send(&self.store_write_sender, write, "store", "message lifecycle event");
+    }
+
+    /// Notify the signer of stages which change whether a message needs signing
+    fn notify_signer(&self, event: &MessageLifecycleEvent) -> Result<(), ()> {
+        let signer_event = match event.stage {
+            // signed messages must not be signed again by the next signer
+            MessageStage::Signed => SignerInboundEvent::MessageSigned(event.message_id),
+            // expired messages are signed again by the signer
+            MessageStage::Expired => SignerInboundEvent::MessageExpired(event.message_id),
+            // rejected messages must not be signed by the next signer either
+            MessageStage::Rejected => SignerInboundEvent::MessageRejected(event.message_id),
+            _ => return Ok(()),
+        };
+
+        self.send_to_signer(signer_event, "lifecycle event")
+    }
+
+    fn send_to_signer(&self, event: SignerInboundEvent, name: &str) -> Result<(), ()> {
+        try_send(&self.solana_signer_inbound_sender, event, "signer", name)
+    }
+
+    fn send_to_relayer(&self, event: RelayerInboundEvent) -> Result<(), ()> {
+        try_send(&self.solana_relayer_inbound_sender, event, "relayer", "event")
+    }
+
+    /// Heartbeats are dropped if the election worker falls behind, a queued
+    /// heartbeat already keeps the leader alive
     async fn send_leader_heartbeat(&self, source: &PeerId) {
         // send heartbeat to relayer election worker if source is relayer
         if source.to_string() == *self.relayer.read().await {
-            let _ = self.relayer_heartbeat_sender.send(()).await;
+            let _ = self.relayer_heartbeat_sender.try_send(());
         }
 
         // send heartbeat to signer election worker if source is signer
         if source.to_string() == *self.signer.read().await {
-            let _ = self.signer_heartbeat_sender.send(()).await;
+            let _ = self.signer_heartbeat_sender.try_send(());
         }
     }
 
-    async fn handle_message(&self, msg: &FloodsubMessage) -> Result<(), ()> {
+    async fn handle_message(&mut self, msg: &FloodsubMessage) -> Result<(), ()> {
         if msg.topics.contains(&MESSAGE_TOPIC) {
-            let message = String::from_utf8_lossy(&msg.data);
-            tracing::warn!("Receive message from {:?}: {message}", msg.source);
-            if let Err(err) = self
-                .solana_signer_inbound_sender
-                .send(SignerInboundEvent::RawMessage(msg.data.clone()))
-                .await
-            {
-                tracing::error!("Fail to send message to solana signer: {err}");
-                return Err(());
+            let Ok(message) = serde_json::from_slice::<RawMessage>(&msg.data) else {
+                tracing::error!("Invalid raw message");
+                return Ok(());
+            };
+            if !message.verify_id() {
+                tracing::error!("Message ID `{}` does not match its content", message.id);
+                return Ok(());
             }
+
+            tracing::warn!(
+                "Receive message `{}` from {:?}: {}",
+                message.id,
+                msg.source,
+                String::from_utf8_lossy(&message.data)
+            );
+            self.publish_message_received(&message);
+            self.send_to_signer(SignerInboundEvent::RawMessage(message), "message")?;
         } else if msg.topics.contains(&HEARTBEAT_TOPIC) {
             // send heartbeat to election worker
             self.send_leader_heartbeat(&msg.source).await;
@@ -420,6 +674,7 @@ impl PeerWorker {
                 return Ok(());
             };
 
+            self.relayer_sync_info = Some(leader_sync_info.clone());
             if let Err(err) = self
                 .relayer_election_worker_inbound_sender
                 .send(ElectionWorkerInboundEvent::LeaderSyncInfo(leader_sync_info))
@@ -434,6 +689,7 @@ impl PeerWorker {
                 return Ok(());
             };
 
+            self.signer_sync_info = Some(leader_sync_info.clone());
             if let Err(err) = self
                 .signer_election_worker_inbound_sender
                 .send(ElectionWorkerInboundEvent::LeaderSyncInfo(leader_sync_info))
@@ -442,37 +698,321 @@ impl PeerWorker {
                 tracing::error!("Fail to send signer leader info to election worker: {err}");
                 return Err(());
             }
+        } else if msg.topics.contains(&TRANSACTION_TOPIC) {
+            let Ok(signed_message) = serde_json::from_slice::<SignedMessage>(&msg.data) else {
+                tracing::error!("Invalid signed message");
+                return Ok(());
+            };
+
+            // the relayer holds transactions signed by other peers, so that it
+            // takes them over if the relayer fails
+            self.record_signed_message(&signed_message);
+            self.send_to_relayer(RelayerInboundEvent::Transaction(signed_message))?;
+        } else if msg.topics.contains(&RELAY_MARKER_TOPIC) {
+            let Ok(marker) = serde_json::from_slice::<RelayMarker>(&msg.data) else {
+                tracing::error!("Invalid relay marker");
+                return Ok(());
+            };
+
+            self.send_to_relayer(RelayerInboundEvent::Marker(marker))?;
         } else if msg.topics.contains(&RELAYED_TRANSACTION_TOPIC) {
-            let transaction_signature = String::from_utf8_lossy(&msg.data);
+            let Ok(relayed_transaction) = serde_json::from_slice::<RelayedTransaction>(&msg.data)
+            else {
+                tracing::error!("Invalid relayed transaction");
+                return Ok(());
+            };
 
-            self.relayed_transactions.write().await.push(transaction_signature.to_string());
+            self.record_relayed_transaction(&relayed_transaction);
+        } else if msg.topics.contains(&LIFECYCLE_TOPIC) {
+            let Ok(lifecycle_event) = serde_json::from_slice::<MessageLifecycleEvent>(&msg.data)
+            else {
+                tracing::error!("Invalid message lifecycle event");
+                return Ok(());
+            };
+
+            // any peer can gossip lifecycle events, only the leader in charge
+            // of the stage may change the pending messages of the signer
+            let signer = self.signer.read().await.clone();
+            let relayer = self.relayer.read().await.clone();
+            if is_lifecycle_authority(&msg.source, &lifecycle_event, &signer, &relayer) {
+                self.notify_signer(&lifecycle_event)?;
+            } else {
+                tracing::debug!(
+                    "Ignore stage {} of message `{}` declared by {} from {}",
+                    lifecycle_event.stage,
+                    lifecycle_event.message_id,
+                    lifecycle_event.peer_id,
+                    msg.source
+                );
+            }
+
+            self.record_lifecycle_event(lifecycle_event);
         }
 
         Ok(())
     }
 
-    async fn handle_instruction(&self, instruction: PeerWorkerInstruction) {
+    async fn handle_request_response(
+        &mut self,
+        swarm: &mut Swarm<PeerBehaviour>,
+        event: RequestResponseEvent<PeerRequest, PeerResponse>,
+    ) -> Result<(), ()> {
+        match event {
+            RequestResponseEvent::Message {
+                peer,
+                message: RequestResponseMessage::Request { request, channel, .. },
+            } => match request {
+                PeerRequest::Cosign(request) => {
+                    self.send_to_signer(
+                        SignerInboundEvent::CosignRequest((peer, request, channel)),
+                        "co-sign request",
+                    )?;
+                }
+                PeerRequest::Frost(request) => {
+                    self.send_to_signer(
+                        SignerInboundEvent::FrostRequest((peer, request, channel)),
+                        "FROST request",
+                    )?;
+                }
+                PeerRequest::FeePayer(request) => {
+                    self.send_to_relayer(RelayerInboundEvent::FeePayerRequest((
+                        peer, request, channel,
+                    )))?;
+                }
+                PeerRequest::Catchup(request) => {
+                    let response = PeerResponse::Catchup(self.catchup_response(&request));
+                    if swarm
+                        .behaviour_mut()
+                        .request_response
+                        .send_response(channel, response)
+                        .is_err()
+                    {
+                        tracing::warn!("Fail to send catch-up response, the connection is closed");
+                    }
+                }
+            },
+            RequestResponseEvent::Message {
+                peer,
+                message: RequestResponseMessage::Response { request_id, response },
+            } => {
+                if self.catchup == Catchup::Requested(request_id) {
+                    self.catchup = Catchup::Done;
+                    match response {
+                        PeerResponse::Catchup(response) => {
+                            self.merge_catchup(peer, response).await?;
+                        }
+                        response => tracing::warn!("Unexpected catch-up response: {response:?}"),
+                    }
+                } else if let Some(sender) = self.pending_requests.remove(&request_id) {
+                    drop(sender.send(Some(response)));
+                }
+            }
+            RequestResponseEvent::OutboundFailure { peer, request_id, error } => {
+                tracing::warn!("Request to {peer} failed: {error}");
+                if self.catchup == Catchup::Requested(request_id) {
+                    // catch up from another connected peer
+                    self.catchup = Catchup::Pending;
+                    let other = swarm.connected_peers().find(|other| **other != peer).copied();
+                    if let Some(other) = other {
+                        self.request_catchup(swarm, other);
+                    }
+                } else if let Some(sender) = self.pending_requests.remove(&request_id) {
+                    drop(sender.send(None));
+                }
+            }
+            RequestResponseEvent::InboundFailure { peer, error, .. } => {
+                tracing::warn!("Request from {peer} failed: {error}");
+            }
+            RequestResponseEvent::ResponseSent { .. } => {}
+        }
+
+        Ok(())
+    }
+
+    fn request_catchup(&mut self, swarm: &mut Swarm<PeerBehaviour>, peer_id: PeerId) {
+        if self.catchup != Catchup::Pending {
+            return;
+        }
+
+        let since = chrono::Duration::from_std(self.catchup_window)
+            .ok()
+            .and_then(|window| Utc::now().checked_sub_signed(window))
+            .unwrap_or(DateTime::<Utc>::MIN_UTC);
+        tracing::info!("Catch up state since {since} from {peer_id}");
+        let request_id = swarm
+            .behaviour_mut()
+            .request_response
+            .send_request(&peer_id, PeerRequest::Catchup(CatchupRequest { since }));
+        self.catchup = Catchup::Requested(request_id);
+    }
+
+    /// Leaders known by this peer, relayed transactions and signed messages
+    /// which are not relayed yet since the requested time
+    fn catchup_response(&self, request: &CatchupRequest) -> CatchupResponse {
+        let query = RecordQuery::since(request.since);
+        let relayed_transactions = self.store.list_relayed_transactions(&query).map_or_else(
+            |err| {
+                tracing::error!("Fail to list relayed transactions for catch-up: {err}");
+                Vec::new()
+            },
+            |records| records.records,
+        );
+        let signed_messages = self.store.list_signed_messages(&query).map_or_else(
+            |err| {
+                tracing::error!("Fail to list signed messages for catch-up: {err}");
+                Vec::new()
+            },
+            |records| records.records,
+        );
+
+        let (relayed_transactions, signed_messages) =
+            catchup_records(relayed_transactions, signed_messages);
+
+        CatchupResponse {
+            relayer: self.relayer_sync_info.clone(),
+            signer: self.signer_sync_info.clone(),
+            relayed_transactions,
+            signed_messages,
+        }
+    }
+
+    /// Merge the state replied by an established peer, leader sync info is
+    /// ignored if gossip is received before the response
+    async fn merge_catchup(&mut self, peer: PeerId, response: CatchupResponse) -> Result<(), ()> {
+        tracing::info!(
+            "Catch up {} relayed transactions and {} signed messages from {peer}",
+            response.relayed_transactions.len(),
+            response.signed_messages.len()
+        );
+
+        if let Some(leader_sync_info) = response.relayer {
+            if self.relayer_sync_info.is_none() {
+                self.relayer_sync_info = Some(leader_sync_info.clone());
+                if let Err(err) = self
+                    .relayer_election_worker_inbound_sender
+                    .send(ElectionWorkerInboundEvent::LeaderSyncInfo(leader_sync_info))
+                    .await
+                {
+                    tracing::error!("Fail to send relayer leader info to election worker: {err}");
+                    return Err(());
+                }
+            }
+        }
+        if let Some(leader_sync_info) = response.signer {
+            if self.signer_sync_info.is_none() {
+                self.signer_sync_info = Some(leader_sync_info.clone());
+                if let Err(err) = self
+                    .signer_election_worker_inbound_sender
+                    .send(ElectionWorkerInboundEvent::LeaderSyncInfo(leader_sync_info))
+                    .await
+                {
+                    tracing::error!("Fail to send signer leader info to election worker: {err}");
+                    return Err(());
+                }
+            }
+        }
+
+        for relayed_transaction in &response.relayed_transactions {
+            self.record_relayed_transaction(relayed_transaction);
+        }
+
+        for signed_message in response.signed_messages {
+            // signed messages must not be signed again by this peer
+            for message_id in &signed_message.message_ids {
+                self.send_to_signer(
+                    SignerInboundEvent::MessageSigned(*message_id),
+                    "signed message",
+                )?;
+            }
+
+            // the relayer holds them as if they were gossiped
+            self.record_signed_message(&signed_message);
+            self.send_to_relayer(RelayerInboundEvent::Transaction(signed_message))?;
+        }
+
+        Ok(())
+    }
+
+    async fn handle_instruction(
+        &self,
+        swarm: &mut Swarm<PeerBehaviour>,
+        instruction: PeerWorkerInstruction,
+    ) -> Result<(), ()> {
         match instruction {
+            PeerWorkerInstruction::SubmitMessage((data, sender)) => {
+                let message_id = self.publish_message(swarm, data)?;
+                let _ = sender.send(message_id);
+            }
             PeerWorkerInstruction::ListPeers(sender) => {
                 let peers = self.peers.read().await.clone();
                 drop(sender.send(peers));
             }
-            PeerWorkerInstruction::ListSignedMessages(sender) => {
-                let signed_messages = self.signed_messages.read().await.clone();
-                drop(sender.send(signed_messages));
-            }
-            PeerWorkerInstruction::ListRelayedTransactions(sender) => {
-                let relayed_transactions = self.relayed_transactions.read().await.clone();
-                drop(sender.send(relayed_transactions));
-            }
             PeerWorkerInstruction::GetTransaction((signature, sender)) => {
-                let result = get_transaction(&self.solana_client, &signature).await;
+                let simulation = self
+                    .simulations
+                    .read()
+                    .await
+                    .iter()
+                    .rfind(|(transaction, _)| transaction.signatures[0].to_string() == signature)
+                    .cloned();
+                let result =
+                    match (get_transaction(&self.solana_client, &signature).await, simulation) {
+                        (Ok(detail), simulation) => {
+                            Ok(detail.with_simulation(simulation.map(|(_, simulation)| simulation)))
+                        }
+                        // transactions failing the simulation are not relayed
+                        (Err(_), Some((transaction, simulation))) => {
+                            Ok(model::TransactionDetail::from(transaction)
+                                .with_simulation(Some(simulation)))
+                        }
+                        (Err(err), None) => Err(err),
+                    };
                 drop(sender.send(result));
             }
+            // dead letters are kept by the relayer
+            PeerWorkerInstruction::ListDeadLetters(sender) => {
+                self.send_to_relayer(RelayerInboundEvent::ListDeadLetters(sender))?;
+            }
+            PeerWorkerInstruction::ReplayDeadLetter((signature, sender)) => {
+                self.send_to_relayer(RelayerInboundEvent::ReplayDeadLetter((signature, sender)))?;
+            }
         }
+
+        Ok(())
     }
 }
 
+/// Signed messages which are not relayed yet, the most recent records are kept
+/// if they exceed the limit
+fn catchup_records(
+    mut relayed_transactions: Vec<RelayedTransaction>,
+    signed_messages: Vec<SignedMessage>,
+) -> (Vec<RelayedTransaction>, Vec<SignedMessage>) {
+    let relayed = relayed_transactions
+        .iter()
+        .map(|relayed_transaction| relayed_transaction.signature.as_str())
+        .collect::<HashSet<_>>();
+    let mut signed_messages = signed_messages
+        .into_iter()
+        .filter(|signed_message| {
+            !relayed.contains(signed_message.transaction.signatures[0].to_string().as_str())
+        })
+        .collect::<Vec<_>>();
+    signed_messages.drain(..signed_messages.len().saturating_sub(MAX_CATCHUP_RECORDS));
+    relayed_transactions.drain(..relayed_transactions.len().saturating_sub(MAX_CATCHUP_RECORDS));
+
+    (relayed_transactions, signed_messages)
+}
+
+/// State of catching up from an established peer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Catchup {
+    /// request is sent to the next connected peer
+    Pending,
+    Requested(OutboundRequestId),
+    Done,
+}
+
 fn handle_list_peers(swarm: &Swarm<PeerBehaviour>) {
     tracing::info!("Discovered Peers:");
     let nodes = swarm.behaviour().mdns.discovered_nodes();
@@ -497,10 +1037,15 @@ async fn get_transaction(
         commitment: Some(CommitmentConfig::confirmed()),
         max_supported_transaction_version: Some(0),
     };
+    // unknown transactions are replied as `null`
     let tx = solana_client
-        .get_transaction_with_config(&signature, config)
+        .send::<Option<EncodedConfirmedTransactionWithStatusMeta>>(
+            RpcRequest::GetTransaction,
+            serde_json::json!([signature.to_string(), config]),
+        )
         .await
-        .context(error::GetSolanaTransactionSnafu { signature })?;
+        .context(error::GetSolanaTransactionSnafu { signature })?
+        .context(error::TransactionNotFoundSnafu { signature })?;
 
     Ok(model::TransactionDetail::from(tx))
 }
@@ -521,6 +1066,10 @@ fn start_swarm(
             peer_id,
         )
         .expect("must create mdns"),
+        request_response: JsonRequestResponse::new(
+            [(REQUEST_RESPONSE_PROTOCOL, ProtocolSupport::Full)],
+            request_response::Config::default(),
+        ),
     };
 
     for topic in topics {
@@ -541,3 +1090,152 @@ fn start_swarm(
 
     Ok(swarm)
 }
+
+/// Send the event without waiting for the receiver, the event is dropped if
+/// the receiver falls behind. Return error if the receiver stops
+fn try_send<T>(
+    sender: &mpsc::Sender<T>,
+    event: T,
+    receiver: &'static str,
+    name: &str,
+) -> Result<(), ()> {
+    match sender.try_send(event) {
+        Ok(()) => Ok(()),
+        Err(TrySendError::Full(_)) => {
+            tracing::warn!("The {receiver} falls behind, drop {name}");
+            metrics::PEER_WORKER_DROPPED_EVENTS.with_label_values(&[receiver]).inc();
+            Ok(())
+        }
+        Err(TrySendError::Closed(_)) => {
+            tracing::error!("Fail to send {name} to the {receiver}: channel is closed");
+            Err(())
+        }
+    }
+}
+
+/// Whether the gossiped lifecycle event is published by the leader in charge
+/// of its stage, the signer signs or rejects messages and the relayer expires
+/// them. The peer declared by the event must be the source of the gossip
+fn is_lifecycle_authority(
+    source: &PeerId,
+    event: &MessageLifecycleEvent,
+    signer: &str,
+    relayer: &str,
+) -> bool {
+    if event.peer_id != source.to_string() {
+        return false;
+    }
+
+    match event.stage {
+        MessageStage::Signed | MessageStage::Rejected => event.peer_id == signer,
+        MessageStage::Expired => event.peer_id == relayer,
+        // the other stages do not change whether a message needs signing
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use libp2p::PeerId;
+    use solana_sdk::{
+        hash::Hash, instruction::Instruction, message::Message, signature::Keypair,
+        signer::Signer as _, transaction::Transaction,
+    };
+    use tokio::sync::mpsc;
+
+    use super::{catchup_records, is_lifecycle_authority, try_send, MAX_CATCHUP_RECORDS};
+    use crate::{
+        model::{
+            CommitmentStatus, MessageId, MessageLifecycleEvent, MessageStage, RelayedTransaction,
+            SignedMessage, TransactionFee,
+        },
+        service::MEMO_PROGRAM_ID,
+    };
+
+    fn signed_message() -> SignedMessage {
+        let signer = Keypair::new();
+        let instruction = Instruction::new_with_bytes(MEMO_PROGRAM_ID, b"hello", vec![]);
+        let message = Message::new(&[instruction], Some(&signer.pubkey()));
+        let transaction = Transaction::new(&[&signer], message, Hash::new_unique());
+        SignedMessage {
+            message_ids: vec![],
+            transaction: transaction.into(),
+            fee: TransactionFee::default(),
+            signer: String::new(),
+        }
+    }
+
+    fn relayed_transaction(signature: String) -> RelayedTransaction {
+        RelayedTransaction {
+            signature,
+            message_ids: vec![],
+            signer: String::new(),
+            relayer: String::new(),
+            slot: None,
+            submitted_at: Utc::now(),
+            confirmed_at: None,
+            status: CommitmentStatus::Submitted,
+            fee: 0,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_catchup_records() {
+        let relayed = signed_message();
+        let pending = signed_message();
+        let relayed_transactions =
+            vec![relayed_transaction(relayed.transaction.signatures[0].to_string())];
+
+        let (relayed_transactions, signed_messages) =
+            catchup_records(relayed_transactions, vec![relayed, pending.clone()]);
+        assert_eq!(relayed_transactions.len(), 1);
+        assert_eq!(
+            signed_messages.iter().map(|sm| sm.transaction.signatures[0]).collect::<Vec<_>>(),
+            [pending.transaction.signatures[0]]
+        );
+
+        // the most recent records are kept
+        let relayed_transactions =
+            (0..=MAX_CATCHUP_RECORDS).map(|idx| relayed_transaction(idx.to_string())).collect();
+        let (relayed_transactions, _) = catchup_records(relayed_transactions, vec![]);
+        assert_eq!(relayed_transactions.len(), MAX_CATCHUP_RECORDS);
+        assert_eq!(relayed_transactions[0].signature, "1");
+    }
+
+    #[test]
+    fn test_try_send() {
+        let (sender, mut receiver) = mpsc::channel(1);
+        assert!(try_send(&sender, 1, "signer", "event").is_ok());
+        // dropped without waiting if the receiver falls behind
+        assert!(try_send(&sender, 2, "signer", "event").is_ok());
+        assert_eq!(receiver.try_recv().ok(), Some(1));
+        assert!(receiver.try_recv().is_err());
+
+        drop(receiver);
+        assert!(try_send(&sender, 3, "signer", "event").is_err());
+    }
+
+    #[test]
+    fn test_lifecycle_authority() {
+        let signer = PeerId::random();
+        let relayer = PeerId::random();
+        let other = PeerId::random();
+        let message_id = MessageId::new("peer", &Utc::now(), b"hello");
+        let check = |source: &PeerId, peer_id: &PeerId, stage| {
+            let event = MessageLifecycleEvent::new(message_id, stage, peer_id.to_string(), None);
+            is_lifecycle_authority(source, &event, &signer.to_string(), &relayer.to_string())
+        };
+
+        assert!(check(&signer, &signer, MessageStage::Signed));
+        assert!(check(&relayer, &relayer, MessageStage::Expired));
+        // only the leader in charge of the stage
+        assert!(!check(&other, &other, MessageStage::Signed));
+        assert!(!check(&signer, &signer, MessageStage::Expired));
+        assert!(check(&signer, &signer, MessageStage::Rejected));
+        assert!(!check(&relayer, &relayer, MessageStage::Rejected));
+        // the declared peer must be the source
+        assert!(!check(&other, &signer, MessageStage::Signed));
+    }
+}
@@ -1,7 +1,6 @@
 use std::{fmt::Display, sync::Arc, time::Duration};
 
-use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use chrono::Utc;
 use tokio::{
     sync::{mpsc, RwLock},
     time,
@@ -9,7 +8,8 @@ use tokio::{
 };
 
 use crate::{
-    service::{peer::PeerWorkerInboundEvent, Result},
+    model::{LeaderElected, LeaderRole, LeaderSyncInfo, NodeEventKind},
+    service::{peer::PeerWorkerInboundEvent, EventBroadcaster, Result},
     ShutdownSignal,
 };
 
@@ -18,12 +18,6 @@ pub enum ElectionWorkerInboundEvent {
     LeaderSyncInfo(LeaderSyncInfo),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct LeaderSyncInfo {
-    leader: String,
-    next_round_time: DateTime<Utc>,
-}
-
 enum Action {
     Stop,
     LeaderHeartbeat(std::result::Result<Option<()>, Elapsed>),
@@ -57,6 +51,7 @@ pub struct RRElectionWorker {
     leader_heartbeat_receiver: mpsc::Receiver<()>,
 
     peer_worker_inbound_sender: mpsc::Sender<PeerWorkerInboundEvent>,
+    events: EventBroadcaster,
 }
 
 impl RRElectionWorker {
@@ -71,6 +66,7 @@ impl RRElectionWorker {
         leader_heartbeat_receiver: mpsc::Receiver<()>,
         peers: Arc<RwLock<Vec<String>>>,
         peer_worker_inbound_sender: mpsc::Sender<PeerWorkerInboundEvent>,
+        events: EventBroadcaster,
     ) -> Self {
         Self {
             r#type,
@@ -81,9 +77,25 @@ impl RRElectionWorker {
             inbound_receiver,
             leader_heartbeat_receiver,
             peer_worker_inbound_sender,
+            events,
         }
     }
 
+    const fn role(&self) -> LeaderRole {
+        match self.r#type {
+            RRElectionWorkerType::Relayer => LeaderRole::Relayer,
+            RRElectionWorkerType::Signer => LeaderRole::Signer,
+        }
+    }
+
+    fn publish_leader_elected(&self, leader_info: &LeaderSyncInfo) {
+        self.events.publish(NodeEventKind::LeaderElected(LeaderElected {
+            role: self.role(),
+            leader: leader_info.leader.clone(),
+            next_round_time: leader_info.next_round_time,
+        }));
+    }
+
     pub async fn start(mut self, mut shutdown_signal: ShutdownSignal) -> Result<()> {
         let mut next_round_time = Instant::now() + self.round_interval;
 
@@ -124,7 +136,12 @@ impl RRElectionWorker {
                     Some(ElectionWorkerInboundEvent::LeaderSyncInfo(leader_info)) => {
                         tracing::info!("Receive {} leader sync info: {leader_info:?}", self.r#type);
                         {
-                            *self.current_leader.write().await = leader_info.leader;
+                            let mut current_leader = self.current_leader.write().await;
+                            if *current_leader != leader_info.leader {
+                                self.publish_leader_elected(&leader_info);
+                            }
+                            *current_leader = leader_info.leader;
+                            drop(current_leader);
                             next_round_time = Instant::now()
                                 + (leader_info.next_round_time - Utc::now())
                                     .to_std()
@@ -175,6 +192,7 @@ impl RRElectionWorker {
 
         let leader_info =
             LeaderSyncInfo { leader: current_leader.clone(), next_round_time: next_round_datetime };
+        self.publish_leader_elected(&leader_info);
         let event = if self.r#type == RRElectionWorkerType::Relayer {
             PeerWorkerInboundEvent::RelayerSyncInfo(leader_info)
         } else {
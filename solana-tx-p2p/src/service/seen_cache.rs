@@ -0,0 +1,108 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+    time::Duration,
+};
+
+use tokio::time::Instant;
+
+/// Bounded set of recently seen keys, keys are evicted after `ttl` or when
+/// `capacity` is reached
+#[derive(Debug)]
+pub struct SeenCache<K> {
+    capacity: usize,
+    ttl: Duration,
+    entries: HashMap<K, Instant>,
+    order: VecDeque<(K, Instant)>,
+}
+
+impl<K> SeenCache<K>
+where
+    K: Clone + Eq + Hash,
+{
+    #[must_use]
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self { capacity, ttl, entries: HashMap::new(), order: VecDeque::new() }
+    }
+
+    /// Insert a key, return `false` if the key has been seen
+    pub fn insert(&mut self, key: K) -> bool {
+        let now = Instant::now();
+        self.evict(now);
+
+        if self.entries.contains_key(&key) {
+            return false;
+        }
+
+        if self.entries.len() >= self.capacity {
+            while let Some((oldest, seen_at)) = self.order.pop_front() {
+                if self.entries.get(&oldest) == Some(&seen_at) {
+                    self.entries.remove(&oldest);
+                    break;
+                }
+            }
+        }
+
+        self.entries.insert(key.clone(), now);
+        self.order.push_back((key, now));
+        true
+    }
+
+    #[must_use]
+    pub fn contains(&self, key: &K) -> bool {
+        self.entries.get(key).is_some_and(|seen_at| seen_at.elapsed() < self.ttl)
+    }
+
+    /// Forget a key, so that it can be inserted again
+    pub fn remove(&mut self, key: &K) { self.entries.remove(key); }
+
+    fn evict(&mut self, now: Instant) {
+        while let Some((key, seen_at)) = self.order.front() {
+            if now.duration_since(*seen_at) < self.ttl {
+                break;
+            }
+
+            // the key may be removed and inserted again
+            if self.entries.get(key) == Some(seen_at) {
+                self.entries.remove(key);
+            }
+            self.order.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::SeenCache;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_seen_cache() {
+        let mut cache = SeenCache::new(2, Duration::from_secs(10));
+
+        assert!(cache.insert(1));
+        assert!(!cache.insert(1));
+        assert!(cache.insert(2));
+
+        // evict the oldest key when capacity is reached
+        assert!(cache.insert(3));
+        assert!(!cache.contains(&1));
+        assert!(cache.contains(&2));
+
+        // evict keys after ttl
+        tokio::time::advance(Duration::from_secs(11)).await;
+        assert!(!cache.contains(&3));
+        assert!(cache.insert(3));
+
+        // removed keys can be inserted again, and are not evicted by their
+        // previous insertion
+        cache.remove(&3);
+        assert!(!cache.contains(&3));
+        tokio::time::advance(Duration::from_secs(5)).await;
+        assert!(cache.insert(3));
+        tokio::time::advance(Duration::from_secs(6)).await;
+        assert!(cache.insert(4));
+        assert!(cache.contains(&3));
+    }
+}
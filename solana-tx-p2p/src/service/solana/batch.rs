@@ -0,0 +1,142 @@
+use std::time::Duration;
+
+use solana_sdk::{instruction::Instruction, message::VersionedMessage, signature::Signature};
+
+/// Pack pending messages into one transaction as multiple instructions
+#[derive(Clone, Copy, Debug)]
+pub struct BatchConfig {
+    /// max messages packed into one transaction
+    pub max_messages: usize,
+    /// how long the signer waits for more messages before signing a batch
+    /// which is not full
+    pub max_delay: Duration,
+}
+
+/// Messages packed into one transaction with their instructions
+pub struct Batch<T> {
+    pub messages: Vec<(T, Vec<Instruction>)>,
+}
+
+impl<T> Batch<T> {
+    /// Instructions of all messages in order
+    pub fn instructions(&self) -> Vec<Instruction> {
+        self.messages.iter().flat_map(|(_, instructions)| instructions.iter().cloned()).collect()
+    }
+}
+
+/// Pack messages into batches of at most `max_messages` in order, a message is
+/// added to the current batch while the transaction `fits`. Return the batches
+/// and the messages which do not fit into a transaction alone
+pub fn pack<T>(
+    messages: Vec<(T, Vec<Instruction>)>,
+    max_messages: usize,
+    fits: impl Fn(&[Instruction]) -> bool,
+) -> (Vec<Batch<T>>, Vec<T>) {
+    let mut batches = Vec::new();
+    let mut oversized = Vec::new();
+    let mut current = Batch { messages: Vec::new() };
+    let mut current_instructions = Vec::new();
+
+    for (message, instructions) in messages {
+        if !current.messages.is_empty() && current.messages.len() < max_messages {
+            let mut candidate = current_instructions.clone();
+            candidate.extend(instructions.iter().cloned());
+            if fits(&candidate) {
+                current_instructions = candidate;
+                current.messages.push((message, instructions));
+                continue;
+            }
+        }
+
+        if !fits(&instructions) {
+            oversized.push(message);
+            continue;
+        }
+        if !current.messages.is_empty() {
+            batches.push(std::mem::replace(&mut current, Batch { messages: Vec::new() }));
+        }
+        current_instructions.clone_from(&instructions);
+        current.messages.push((message, instructions));
+    }
+    if !current.messages.is_empty() {
+        batches.push(current);
+    }
+
+    (batches, oversized)
+}
+
+/// Size in bytes of the transaction of the message signed by all required
+/// signers
+#[must_use]
+pub fn transaction_size(message: &VersionedMessage) -> usize {
+    let signatures = usize::from(message.header().num_required_signatures);
+    let signatures_size = short_vec_len_size(signatures) + signatures * size_of::<Signature>();
+    signatures_size + message.serialize().len()
+}
+
+/// Size of the compact-u16 length prefix
+const fn short_vec_len_size(len: usize) -> usize {
+    match len {
+        0..=0x7f => 1,
+        0x80..=0x3fff => 2,
+        _ => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::{
+        hash::Hash,
+        instruction::Instruction,
+        message::{Message, VersionedMessage},
+        packet::PACKET_DATA_SIZE,
+        signature::Keypair,
+        signer::Signer as _,
+        transaction::VersionedTransaction,
+    };
+
+    use super::{pack, transaction_size};
+    use crate::service::solana::MEMO_PROGRAM_ID;
+
+    #[test]
+    fn test_pack() {
+        let keypair = Keypair::new();
+        let fee_payer = keypair.pubkey();
+        let fits = |instructions: &[Instruction]| {
+            let message = VersionedMessage::Legacy(Message::new_with_blockhash(
+                instructions,
+                Some(&fee_payer),
+                &Hash::default(),
+            ));
+            transaction_size(&message) <= PACKET_DATA_SIZE
+        };
+        let memo = |size: usize| {
+            vec![Instruction::new_with_bytes(MEMO_PROGRAM_ID, &vec![b'a'; size], vec![])]
+        };
+
+        let message = VersionedMessage::Legacy(Message::new_with_blockhash(
+            &memo(100),
+            Some(&fee_payer),
+            &Hash::default(),
+        ));
+        let transaction = VersionedTransaction::try_new(message.clone(), &[&keypair]).unwrap();
+        assert_eq!(transaction_size(&message), bincode::serialize(&transaction).unwrap().len());
+
+        // messages are split by count and by transaction size
+        let messages = vec![
+            (0, memo(10)),
+            (1, memo(10)),
+            (2, memo(10)),
+            (3, memo(800)),
+            (4, memo(2_000)),
+            (5, memo(400)),
+        ];
+        let (batches, oversized) = pack(messages, 2, fits);
+        let batches = batches
+            .iter()
+            .map(|batch| batch.messages.iter().map(|(id, _)| *id).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(batches, vec![vec![0, 1], vec![2, 3], vec![5]]);
+        assert_eq!(oversized, vec![4]);
+    }
+}
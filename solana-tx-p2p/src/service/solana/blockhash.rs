@@ -0,0 +1,116 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig, hash::Hash, transaction::VersionedTransaction,
+};
+use tokio::{sync::RwLock, time};
+
+use crate::{
+    service::{solana::nonce::advance_nonce_accounts, Result},
+    ShutdownSignal,
+};
+
+#[derive(Debug, Default)]
+struct BlockhashState {
+    latest: Option<(Hash, u64)>,
+    block_height: u64,
+    /// last valid block heights of blockhashes which are not expired
+    last_valid_block_heights: HashMap<Hash, u64>,
+}
+
+/// Latest blockhash polled in background, shared by the signer and the relayer
+#[derive(Clone)]
+pub struct BlockhashCache {
+    client: Arc<RpcClient>,
+    refresh_interval: Duration,
+    state: Arc<RwLock<BlockhashState>>,
+}
+
+impl BlockhashCache {
+    #[must_use]
+    pub fn new(client: Arc<RpcClient>, refresh_interval: Duration) -> Self {
+        Self { client, refresh_interval, state: Arc::default() }
+    }
+
+    /// Poll the latest blockhash and the block height until shutdown
+    ///
+    /// # Errors
+    ///
+    /// It never returns error, failures of polling are logged
+    pub async fn start(self, mut shutdown_signal: ShutdownSignal) -> Result<()> {
+        let mut refresh_timer = time::interval(self.refresh_interval);
+        loop {
+            tokio::select! {
+                () = shutdown_signal.wait() => break,
+                _ = refresh_timer.tick() => self.refresh().await,
+            }
+        }
+
+        tracing::warn!("Blockhash cache stopped.");
+
+        Ok(())
+    }
+
+    /// The latest blockhash if it is not expired
+    pub async fn latest(&self) -> Option<Hash> {
+        let state = self.state.read().await;
+        let (blockhash, last_valid_block_height) = state.latest?;
+        (state.block_height <= last_valid_block_height).then_some(blockhash)
+    }
+
+    /// Whether the blockhash of the transaction has expired, transactions
+    /// signed against durable nonces never expire. Blockhashes which are not
+    /// cached are checked with the cluster
+    pub async fn is_expired(&self, transaction: &VersionedTransaction) -> bool {
+        let message = &transaction.message;
+        if advance_nonce_accounts(message.static_account_keys(), message.instructions()).is_some() {
+            return false;
+        }
+
+        let blockhash = *message.recent_blockhash();
+        {
+            let state = self.state.read().await;
+            if let Some(last_valid_block_height) = state.last_valid_block_heights.get(&blockhash) {
+                return state.block_height > *last_valid_block_height;
+            }
+        }
+
+        match self.client.is_blockhash_valid(&blockhash, CommitmentConfig::processed()).await {
+            Ok(valid) => !valid,
+            Err(err) => {
+                tracing::warn!("Fail to check blockhash `{blockhash}`: {err}");
+                false
+            }
+        }
+    }
+
+    async fn refresh(&self) {
+        let latest = match self
+            .client
+            .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())
+            .await
+        {
+            Ok(latest) => latest,
+            Err(err) => {
+                tracing::error!("Fail to get latest blockhash: {err}");
+                return;
+            }
+        };
+        let block_height = match self.client.get_block_height().await {
+            Ok(block_height) => block_height,
+            Err(err) => {
+                tracing::error!("Fail to get block height: {err}");
+                return;
+            }
+        };
+
+        let mut state = self.state.write().await;
+        state.latest = Some(latest);
+        state.block_height = block_height;
+        state.last_valid_block_heights.insert(latest.0, latest.1);
+        state
+            .last_valid_block_heights
+            .retain(|_, last_valid_block_height| *last_valid_block_height >= block_height);
+    }
+}
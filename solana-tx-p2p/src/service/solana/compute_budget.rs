@@ -0,0 +1,244 @@
+use std::sync::Arc;
+
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    borsh1::try_from_slice_unchecked,
+    compute_budget::{self, ComputeBudgetInstruction},
+    fee::FeeStructure,
+    instruction::{CompiledInstruction, Instruction},
+    message::VersionedMessage,
+    pubkey::Pubkey,
+};
+
+use crate::{model::TransactionFee, service::solana::nonce::advance_nonce_accounts};
+
+/// Compute units of each instruction if the transaction does not set the
+/// compute unit limit
+const DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT: u32 = 200_000;
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+const MICRO_LAMPORTS_PER_LAMPORT: u128 = 1_000_000;
+/// percentile of recent prioritization fees used as the dynamic compute unit
+/// price
+const DYNAMIC_PRICE_PERCENTILE: usize = 75;
+/// max accounts accepted by `getRecentPrioritizationFees`
+const MAX_PRIORITIZATION_FEE_ACCOUNTS: usize = 128;
+
+#[derive(Clone, Copy, Debug)]
+pub enum ComputeUnitPrice {
+    /// fixed price in micro-lamports
+    Fixed(u64),
+    /// price estimated from recent prioritization fees of the writable
+    /// accounts, capped at `max` micro-lamports
+    Dynamic { max: u64 },
+}
+
+/// Compute budget instructions prepended to signed transactions, instructions
+/// which are not set are not added
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ComputeBudgetConfig {
+    pub unit_limit: Option<u32>,
+    pub unit_price: Option<ComputeUnitPrice>,
+}
+
+pub struct ComputeBudget {
+    config: ComputeBudgetConfig,
+    client: Arc<RpcClient>,
+}
+
+impl ComputeBudget {
+    #[must_use]
+    pub const fn new(config: ComputeBudgetConfig, client: Arc<RpcClient>) -> Self {
+        Self { config, client }
+    }
+
+    /// Compute budget instructions of a transaction with `instructions` paid by
+    /// `fee_payer`
+    pub async fn instructions(
+        &self,
+        instructions: &[Instruction],
+        fee_payer: &Pubkey,
+    ) -> Vec<Instruction> {
+        let unit_price = match self.config.unit_price {
+            Some(ComputeUnitPrice::Fixed(price)) => Some(price),
+            Some(ComputeUnitPrice::Dynamic { max }) => {
+                Some(self.estimate_unit_price(instructions, fee_payer).await.min(max))
+            }
+            None => None,
+        };
+
+        self.config
+            .unit_limit
+            .map(ComputeBudgetInstruction::set_compute_unit_limit)
+            .into_iter()
+            .chain(unit_price.map(ComputeBudgetInstruction::set_compute_unit_price))
+            .collect()
+    }
+
+    /// Compute budget instructions of the same size as [`Self::instructions`]
+    /// without estimating the unit price, to measure the transaction size
+    #[must_use]
+    pub fn placeholder_instructions(&self) -> Vec<Instruction> {
+        self.config
+            .unit_limit
+            .map(ComputeBudgetInstruction::set_compute_unit_limit)
+            .into_iter()
+            .chain(
+                self.config.unit_price.map(|_| ComputeBudgetInstruction::set_compute_unit_price(0)),
+            )
+            .collect()
+    }
+
+    /// The percentile of recent prioritization fees paid to write the accounts
+    /// of the instructions, failures are logged and no price is paid
+    async fn estimate_unit_price(&self, instructions: &[Instruction], fee_payer: &Pubkey) -> u64 {
+        let mut accounts = vec![*fee_payer];
+        for account in instructions.iter().flat_map(|instruction| &instruction.accounts) {
+            if account.is_writable && !accounts.contains(&account.pubkey) {
+                accounts.push(account.pubkey);
+            }
+        }
+        accounts.truncate(MAX_PRIORITIZATION_FEE_ACCOUNTS);
+
+        let mut fees = match self.client.get_recent_prioritization_fees(&accounts).await {
+            Ok(fees) => fees.into_iter().map(|fee| fee.prioritization_fee).collect::<Vec<_>>(),
+            Err(err) => {
+                tracing::warn!("Fail to get recent prioritization fees: {err}");
+                return 0;
+            }
+        };
+        if fees.is_empty() {
+            return 0;
+        }
+        fees.sort_unstable();
+        fees[(fees.len() - 1) * DYNAMIC_PRICE_PERCENTILE / 100]
+    }
+}
+
+/// Compute budget instructions at the start of the message, after the advance
+/// nonce instruction if any. Co-signers accept these instructions besides the
+/// instructions built from the raw message
+#[must_use]
+pub fn leading_compute_budget_instructions(
+    account_keys: &[Pubkey],
+    instructions: &[CompiledInstruction],
+) -> Vec<Instruction> {
+    let skip = usize::from(advance_nonce_accounts(account_keys, instructions).is_some());
+    instructions
+        .iter()
+        .skip(skip)
+        .map_while(|instruction| {
+            let program_id = account_keys.get(usize::from(instruction.program_id_index))?;
+            (compute_budget::check_id(program_id) && instruction.accounts.is_empty())
+                .then(|| Instruction::new_with_bytes(*program_id, &instruction.data, vec![]))
+        })
+        .collect()
+}
+
+/// Lamports paid for priority by a transaction with `instructions`
+#[must_use]
+pub fn priority_fee(instructions: &[Instruction]) -> u64 {
+    let (unit_limit, unit_price, count) = parse_compute_budget(
+        instructions.iter().map(|instruction| (&instruction.program_id, &instruction.data[..])),
+    );
+    calculate_priority_fee(unit_limit, unit_price, count)
+}
+
+/// Fee paid by the fee payer of the message
+#[must_use]
+pub fn transaction_fee(message: &VersionedMessage) -> TransactionFee {
+    let account_keys = message.static_account_keys();
+    let (compute_unit_limit, compute_unit_price, count) =
+        parse_compute_budget(message.instructions().iter().filter_map(|instruction| {
+            Some((
+                account_keys.get(usize::from(instruction.program_id_index))?,
+                &instruction.data[..],
+            ))
+        }));
+
+    TransactionFee {
+        signature_fee: u64::from(message.header().num_required_signatures)
+            .saturating_mul(FeeStructure::default().lamports_per_signature),
+        compute_unit_limit,
+        compute_unit_price,
+        priority_fee: calculate_priority_fee(compute_unit_limit, compute_unit_price, count),
+    }
+}
+
+/// The compute unit limit, the compute unit price and the number of other
+/// instructions
+fn parse_compute_budget<'a>(
+    instructions: impl Iterator<Item = (&'a Pubkey, &'a [u8])>,
+) -> (Option<u32>, u64, u32) {
+    let mut unit_limit = None;
+    let mut unit_price = 0;
+    let mut count = 0_u32;
+    for (program_id, data) in instructions {
+        if !compute_budget::check_id(program_id) {
+            count = count.saturating_add(1);
+            continue;
+        }
+        match try_from_slice_unchecked::<ComputeBudgetInstruction>(data) {
+            Ok(ComputeBudgetInstruction::SetComputeUnitLimit(limit)) => unit_limit = Some(limit),
+            Ok(ComputeBudgetInstruction::SetComputeUnitPrice(price)) => unit_price = price,
+            _ => {}
+        }
+    }
+
+    (unit_limit, unit_price, count)
+}
+
+fn calculate_priority_fee(unit_limit: Option<u32>, unit_price: u64, count: u32) -> u64 {
+    let unit_limit = unit_limit
+        .unwrap_or_else(|| DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT.saturating_mul(count))
+        .min(MAX_COMPUTE_UNIT_LIMIT);
+    let micro_lamports = u128::from(unit_limit) * u128::from(unit_price);
+    u64::try_from(micro_lamports.div_ceil(MICRO_LAMPORTS_PER_LAMPORT)).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::{
+        compute_budget::ComputeBudgetInstruction,
+        hash::Hash,
+        message::{Message, VersionedMessage},
+        pubkey::Pubkey,
+        system_instruction,
+    };
+
+    use super::{leading_compute_budget_instructions, priority_fee, transaction_fee};
+    use crate::model::TransactionFee;
+
+    #[test]
+    fn test_transaction_fee() {
+        let fee_payer = Pubkey::new_unique();
+        let transfer = system_instruction::transfer(&fee_payer, &Pubkey::new_unique(), 1);
+
+        // default compute unit limit of each instruction
+        let instructions = [
+            ComputeBudgetInstruction::set_compute_unit_price(10_001),
+            transfer.clone(),
+            transfer.clone(),
+        ];
+        assert_eq!(priority_fee(&instructions), 4_001);
+
+        let instructions = [
+            ComputeBudgetInstruction::set_compute_unit_limit(100_000),
+            ComputeBudgetInstruction::set_compute_unit_price(10_000),
+            transfer,
+        ];
+        assert_eq!(priority_fee(&instructions), 1_000);
+
+        let message =
+            Message::new_with_blockhash(&instructions, Some(&fee_payer), &Hash::new_unique());
+        assert_eq!(
+            leading_compute_budget_instructions(&message.account_keys, &message.instructions),
+            instructions[..2]
+        );
+        assert_eq!(transaction_fee(&VersionedMessage::Legacy(message)), TransactionFee {
+            signature_fee: 5_000,
+            compute_unit_limit: Some(100_000),
+            compute_unit_price: 10_000,
+            priority_fee: 1_000,
+        });
+    }
+}
@@ -0,0 +1,307 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use libp2p::PeerId;
+use solana_client::{
+    nonblocking::rpc_client::RpcClient, rpc_request::MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS,
+};
+use solana_sdk::{signature::Signature, transaction::VersionedTransaction};
+use solana_transaction_status_client_types::TransactionConfirmationStatus;
+use tokio::{sync::mpsc, time};
+
+use crate::{
+    model::{
+        CommitmentStatus, ConfirmationStatusChanged, MessageId, MessageStage, NodeEventKind,
+        RelayedTransaction,
+    },
+    service::{
+        solana::{relayer::send_lifecycle_events, BlockhashCache},
+        EventBroadcaster, PeerWorkerInboundEvent, Result,
+    },
+    ShutdownSignal,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Transactions which neither land nor expire in time are no longer tracked,
+/// transactions signed against durable nonces never expire
+const TRACKING_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Stages reported in order as the confirmation status of a transaction
+/// progresses
+static CONFIRMATION_STAGES: [MessageStage; 3] =
+    [MessageStage::Processed, MessageStage::Confirmed, MessageStage::Finalized];
+
+pub enum ConfirmationTrackerInboundEvent {
+    /// Track the confirmation status of a sent transaction signed from the
+    /// messages, the record of the relayed transaction is updated as the
+    /// status progresses
+    Track((Vec<MessageId>, VersionedTransaction, RelayedTransaction)),
+}
+
+enum Action {
+    Stop,
+    Inbound(Option<ConfirmationTrackerInboundEvent>),
+    Poll,
+}
+
+struct TrackedTransaction {
+    message_ids: Vec<MessageId>,
+    transaction: VersionedTransaction,
+    relayed_transaction: RelayedTransaction,
+    /// number of `CONFIRMATION_STAGES` reported
+    reached: usize,
+    sent_at: Instant,
+}
+
+/// Poll the statuses of sent transactions in batches and report their
+/// confirmation stages as lifecycle events, so that the relayer does not wait
+/// for confirmations
+pub struct ConfirmationTracker {
+    peer_id: PeerId,
+
+    peer_worker_inbound_sender: mpsc::Sender<PeerWorkerInboundEvent>,
+    events: EventBroadcaster,
+
+    client: Arc<RpcClient>,
+    blockhash_cache: BlockhashCache,
+
+    inbound_receiver: mpsc::Receiver<ConfirmationTrackerInboundEvent>,
+
+    tracked: HashMap<Signature, TrackedTransaction>,
+}
+
+impl ConfirmationTracker {
+    #[must_use]
+    pub fn new(
+        peer_id: PeerId,
+        peer_worker_inbound_sender: mpsc::Sender<PeerWorkerInboundEvent>,
+        events: EventBroadcaster,
+        client: Arc<RpcClient>,
+        blockhash_cache: BlockhashCache,
+        inbound_receiver: mpsc::Receiver<ConfirmationTrackerInboundEvent>,
+    ) -> Self {
+        Self {
+            peer_id,
+            peer_worker_inbound_sender,
+            events,
+            client,
+            blockhash_cache,
+            inbound_receiver,
+            tracked: HashMap::new(),
+        }
+    }
+
+    /// # Errors
+    ///
+    /// It never returns error, failures of polling are logged
+    pub async fn start(mut self, mut shutdown_signal: ShutdownSignal) -> Result<()> {
+        let mut poll_timer = time::interval(POLL_INTERVAL);
+        loop {
+            let action = tokio::select! {
+                () = shutdown_signal.wait() => Action::Stop,
+                result = self.inbound_receiver.recv() => Action::Inbound(result),
+                _ = poll_timer.tick() => Action::Poll,
+            };
+
+            match action {
+                Action::Stop => break,
+                Action::Inbound(None) => {
+                    tracing::warn!("`confirmation_tracker_inbound_receiver` is closed");
+                    break;
+                }
+                Action::Inbound(Some(ConfirmationTrackerInboundEvent::Track((
+                    message_ids,
+                    transaction,
+                    relayed_transaction,
+                )))) => {
+                    self.tracked.insert(transaction.signatures[0], TrackedTransaction {
+                        message_ids,
+                        transaction,
+                        relayed_transaction,
+                        reached: 0,
+                        sent_at: Instant::now(),
+                    });
+                }
+                Action::Poll => {
+                    if self.poll().await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+
+        tracing::warn!("Confirmation tracker stopped.");
+
+        Ok(())
+    }
+
+    /// Poll the statuses of tracked transactions, transactions which are
+    /// finalized, fail, expire or time out are no longer tracked
+    async fn poll(&mut self) -> Result<(), ()> {
+        let signatures = self.tracked.keys().copied().collect::<Vec<_>>();
+        for signatures in signatures.chunks(MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS) {
+            let statuses = match self.client.get_signature_statuses(signatures).await {
+                Ok(response) => response.value,
+                Err(err) => {
+                    tracing::warn!("Fail to get signature statuses: {err}");
+                    return Ok(());
+                }
+            };
+
+            for (signature, status) in signatures.iter().zip(statuses) {
+                let Some(mut tracked) = self.tracked.remove(signature) else {
+                    continue;
+                };
+
+                let Some(status) = status else {
+                    if self.blockhash_cache.is_expired(&tracked.transaction).await {
+                        tracing::warn!(
+                            "Transaction `{signature}` expires before landing, request signing \
+                             again"
+                        );
+                        send_lifecycle_events(
+                            &self.peer_worker_inbound_sender,
+                            &self.peer_id,
+                            &tracked.message_ids,
+                            MessageStage::Expired,
+                            signature,
+                            None,
+                        )
+                        .await?;
+
+                        tracked.relayed_transaction.status = CommitmentStatus::Expired;
+                        self.report(tracked.relayed_transaction).await?;
+                    } else if tracked.sent_at.elapsed() >= TRACKING_TIMEOUT {
+                        tracing::warn!(
+                            "Transaction `{signature}` does not land in {TRACKING_TIMEOUT:?}"
+                        );
+                    } else {
+                        self.tracked.insert(*signature, tracked);
+                    }
+                    continue;
+                };
+
+                let stages = reached_stages(tracked.reached, &status.confirmation_status());
+                for stage in stages {
+                    send_lifecycle_events(
+                        &self.peer_worker_inbound_sender,
+                        &self.peer_id,
+                        &tracked.message_ids,
+                        *stage,
+                        signature,
+                        None,
+                    )
+                    .await?;
+                }
+                tracked.reached += stages.len();
+
+                let mut relayed_transaction = tracked.relayed_transaction.clone();
+                if !stages.is_empty() {
+                    let confirmation_status = status.confirmation_status();
+                    relayed_transaction.slot = Some(status.slot);
+                    relayed_transaction.status = commitment_status(&confirmation_status);
+                    if confirmation_status != TransactionConfirmationStatus::Processed {
+                        relayed_transaction.confirmed_at.get_or_insert_with(Utc::now);
+                    }
+                }
+
+                if let Some(err) = status.err {
+                    tracing::error!("Transaction `{signature}` fails: {err}");
+                    send_lifecycle_events(
+                        &self.peer_worker_inbound_sender,
+                        &self.peer_id,
+                        &tracked.message_ids,
+                        MessageStage::Failed,
+                        signature,
+                        Some(&err.to_string()),
+                    )
+                    .await?;
+
+                    relayed_transaction.slot = Some(status.slot);
+                    relayed_transaction.status = CommitmentStatus::Failed;
+                    relayed_transaction.error = Some(err.to_string());
+                    self.report(relayed_transaction).await?;
+                    continue;
+                }
+
+                if relayed_transaction != tracked.relayed_transaction {
+                    tracked.relayed_transaction = relayed_transaction;
+                    self.report(tracked.relayed_transaction.clone()).await?;
+                }
+                if tracked.reached < CONFIRMATION_STAGES.len() {
+                    self.tracked.insert(*signature, tracked);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Send the updated record of the relayed transaction to peer worker
+    async fn report(&self, relayed_transaction: RelayedTransaction) -> Result<(), ()> {
+        self.events.publish(NodeEventKind::ConfirmationStatusChanged(ConfirmationStatusChanged {
+            signature: relayed_transaction.signature.clone(),
+            slot: relayed_transaction.slot,
+            status: relayed_transaction.status,
+            error: relayed_transaction.error.clone(),
+        }));
+        if let Err(err) = self
+            .peer_worker_inbound_sender
+            .send(PeerWorkerInboundEvent::RelayedTransaction(relayed_transaction))
+            .await
+        {
+            tracing::error!("Fail to send relayed transaction to peer worker: {err}");
+            return Err(());
+        }
+
+        Ok(())
+    }
+}
+
+const fn commitment_status(status: &TransactionConfirmationStatus) -> CommitmentStatus {
+    match status {
+        TransactionConfirmationStatus::Processed => CommitmentStatus::Processed,
+        TransactionConfirmationStatus::Confirmed => CommitmentStatus::Confirmed,
+        TransactionConfirmationStatus::Finalized => CommitmentStatus::Finalized,
+    }
+}
+
+/// Stages which are reached by the confirmation status but not reported yet,
+/// the stages skipped between polls are included
+fn reached_stages(
+    reported: usize,
+    status: &TransactionConfirmationStatus,
+) -> &'static [MessageStage] {
+    let reached = match status {
+        TransactionConfirmationStatus::Processed => 1,
+        TransactionConfirmationStatus::Confirmed => 2,
+        TransactionConfirmationStatus::Finalized => 3,
+    };
+    CONFIRMATION_STAGES.get(reported..reached).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_transaction_status_client_types::TransactionConfirmationStatus;
+
+    use super::reached_stages;
+    use crate::model::MessageStage;
+
+    #[test]
+    fn test_reached_stages() {
+        assert_eq!(reached_stages(0, &TransactionConfirmationStatus::Processed), [
+            MessageStage::Processed
+        ]);
+        assert_eq!(reached_stages(1, &TransactionConfirmationStatus::Finalized), [
+            MessageStage::Confirmed,
+            MessageStage::Finalized
+        ]);
+        assert!(reached_stages(2, &TransactionConfirmationStatus::Confirmed).is_empty());
+        // statuses rolled back are not reported again
+        assert!(reached_stages(2, &TransactionConfirmationStatus::Processed).is_empty());
+    }
+}
@@ -0,0 +1,173 @@
+use std::time::Duration;
+
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    transaction::VersionedTransaction,
+};
+
+use crate::service::solana::{compute_budget::transaction_fee, MEMO_PROGRAM_ID};
+
+/// The relayer pays the fee of transactions signed by the signer, so that only
+/// relayers need funding
+#[derive(Clone, Copy, Debug)]
+pub struct RelayerFeePayerConfig {
+    /// how long the signer waits for the fee payer signature of the relayer
+    pub timeout: Duration,
+    /// max lamports paid by the relayer for each transaction
+    pub max_fee: u64,
+}
+
+/// Append a memo instruction requiring the signer to sign if the instructions
+/// do not, so that the relayer never pays for transactions without the
+/// signature of the signer
+pub fn require_signer(instructions: &[Instruction], signer: &Pubkey) -> Vec<Instruction> {
+    let mut instructions = instructions.to_vec();
+    let required = instructions.iter().any(|instruction| {
+        instruction.accounts.iter().any(|account| account.pubkey == *signer && account.is_signer)
+    });
+    if !required {
+        instructions.push(Instruction::new_with_bytes(MEMO_PROGRAM_ID, b"signer", vec![
+            AccountMeta::new_readonly(*signer, true),
+        ]));
+    }
+    instructions
+}
+
+/// Verify that the transaction partially signed by the signer only uses the
+/// relayer to pay the fee, return the reason of rejection otherwise
+pub fn verify_fee_payer_request(
+    transaction: &VersionedTransaction,
+    fee_payer: &Pubkey,
+    signer: &Pubkey,
+    max_fee: u64,
+) -> Result<(), String> {
+    let message = &transaction.message;
+    let account_keys = message.static_account_keys();
+    let num_signers = usize::from(message.header().num_required_signatures);
+    let Some((payer, signers)) = account_keys.get(..num_signers).and_then(<[Pubkey]>::split_first)
+    else {
+        return Err("transaction does not have any signer".to_string());
+    };
+    if payer != fee_payer {
+        return Err("fee payer is not the relayer".to_string());
+    }
+    let Some(position) = signers.iter().position(|key| key == signer) else {
+        return Err("transaction does not require the signer to sign".to_string());
+    };
+    if transaction.signatures.len() != num_signers {
+        return Err("transaction does not have the signatures of all signers".to_string());
+    }
+    if !transaction.signatures[position + 1].verify(signer.as_ref(), &message.serialize()) {
+        return Err("invalid signature of the signer".to_string());
+    }
+
+    // the fee payer must not be debited or authorize anything
+    if message.instructions().iter().any(|instruction| instruction.accounts.contains(&0)) {
+        return Err("instructions use the fee payer".to_string());
+    }
+
+    let fee = transaction_fee(message);
+    let total = fee.signature_fee.saturating_add(fee.priority_fee);
+    if total > max_fee {
+        return Err(format!("fee {total} exceeds {max_fee} lamports"));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::slice;
+
+    use solana_sdk::{
+        compute_budget::ComputeBudgetInstruction,
+        hash::Hash,
+        instruction::Instruction,
+        message::{Message, VersionedMessage},
+        signature::{Keypair, Signature},
+        signer::Signer as _,
+        system_instruction,
+        transaction::VersionedTransaction,
+    };
+
+    use super::{require_signer, verify_fee_payer_request};
+    use crate::service::solana::MEMO_PROGRAM_ID;
+
+    fn partially_sign(
+        instructions: &[Instruction],
+        relayer: &Keypair,
+        signer: &Keypair,
+    ) -> VersionedTransaction {
+        let instructions = require_signer(instructions, &signer.pubkey());
+        let message = VersionedMessage::Legacy(Message::new_with_blockhash(
+            &instructions,
+            Some(&relayer.pubkey()),
+            &Hash::new_unique(),
+        ));
+        let num_signers = usize::from(message.header().num_required_signatures);
+        let mut transaction =
+            VersionedTransaction { signatures: vec![Signature::default(); num_signers], message };
+        transaction.signatures[1] = signer.sign_message(&transaction.message.serialize());
+        transaction
+    }
+
+    #[test]
+    fn test_verify_fee_payer_request() {
+        let relayer = Keypair::new();
+        let signer = Keypair::new();
+        let memo = Instruction::new_with_bytes(MEMO_PROGRAM_ID, b"hello", vec![]);
+
+        // the signer is required to sign by the appended memo instruction
+        let transaction = partially_sign(slice::from_ref(&memo), &relayer, &signer);
+        assert_eq!(transaction.message.instructions().len(), 2);
+        assert_eq!(
+            verify_fee_payer_request(&transaction, &relayer.pubkey(), &signer.pubkey(), 10_000),
+            Ok(())
+        );
+
+        // paid by another peer
+        assert!(verify_fee_payer_request(
+            &transaction,
+            &Keypair::new().pubkey(),
+            &signer.pubkey(),
+            10_000
+        )
+        .is_err());
+
+        // signature of the signer is missing
+        let mut unsigned = transaction;
+        unsigned.signatures[1] = Signature::default();
+        assert!(verify_fee_payer_request(&unsigned, &relayer.pubkey(), &signer.pubkey(), 10_000)
+            .is_err());
+
+        // instructions transferring lamports from the fee payer
+        let transfer = system_instruction::transfer(&relayer.pubkey(), &signer.pubkey(), 1);
+        let transaction = partially_sign(&[transfer], &relayer, &signer);
+        assert!(verify_fee_payer_request(
+            &transaction,
+            &relayer.pubkey(),
+            &signer.pubkey(),
+            10_000
+        )
+        .is_err());
+
+        // fee exceeding the cap
+        let transaction = partially_sign(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(200_000),
+                ComputeBudgetInstruction::set_compute_unit_price(1_000_000),
+                memo,
+            ],
+            &relayer,
+            &signer,
+        );
+        assert!(verify_fee_payer_request(
+            &transaction,
+            &relayer.pubkey(),
+            &signer.pubkey(),
+            10_000
+        )
+        .is_err());
+    }
+}
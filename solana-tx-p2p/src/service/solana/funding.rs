@@ -0,0 +1,256 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use snafu::{ensure, ResultExt};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair as SolanaKeypair, Signature},
+    signer::Signer as _,
+    system_instruction,
+    transaction::Transaction,
+};
+use tokio::time;
+
+use crate::{
+    metrics,
+    service::{error, Result},
+    ShutdownSignal,
+};
+
+const INITIAL_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How the wallet of the node is funded, not `Debug` so that the treasury
+/// keypair is never logged
+#[derive(Clone)]
+pub enum FundingStrategy {
+    /// The wallet is never funded nor checked
+    None,
+    /// Request airdrop from the faucet of the cluster
+    Airdrop { amount: u64 },
+    /// Transfer lamports from the treasury
+    Transfer { treasury: Arc<SolanaKeypair>, amount: u64 },
+    /// The wallet is funded externally, the node fails to start if the balance
+    /// is below the minimum balance
+    MinBalance,
+}
+
+#[derive(Clone)]
+pub struct FundingConfig {
+    pub strategy: FundingStrategy,
+    /// the wallet is funded if the balance falls below this lamports
+    pub min_balance: u64,
+    /// how long funding waits for the transaction to be confirmed
+    pub timeout: Duration,
+    /// how often the balance is checked in background, the balance is only
+    /// checked at startup if not set
+    pub monitor_interval: Option<Duration>,
+}
+
+/// Fund the wallet of the node at startup and keep its balance above the
+/// minimum balance
+pub struct WalletFunding {
+    config: FundingConfig,
+    client: Arc<RpcClient>,
+    pubkey: Pubkey,
+    /// report the balance as the balance of the wallet of the node
+    report_balance: bool,
+}
+
+impl WalletFunding {
+    #[must_use]
+    pub const fn new(config: FundingConfig, client: Arc<RpcClient>, pubkey: Pubkey) -> Self {
+        Self { config, client, pubkey, report_balance: true }
+    }
+
+    /// Fund another account than the wallet of the node, its balance is not
+    /// reported in metrics
+    #[must_use]
+    pub const fn without_balance_metric(mut self) -> Self {
+        self.report_balance = false;
+        self
+    }
+
+    /// Fund the wallet if the balance is below the minimum balance
+    ///
+    /// # Errors
+    ///
+    /// * fail to get balance
+    /// * fail to request airdrop or transfer from the treasury
+    /// * funding transaction is not confirmed before timeout
+    /// * balance is below the minimum balance in `MinBalance` strategy
+    pub async fn fund(&self) -> Result<()> {
+        if matches!(self.config.strategy, FundingStrategy::None) {
+            return Ok(());
+        }
+
+        let balance = self.balance().await?;
+        if balance >= self.config.min_balance {
+            tracing::info!("Wallet `{}` has {balance} lamports", self.pubkey);
+            return Ok(());
+        }
+
+        self.top_up(balance).await
+    }
+
+    /// Check the balance every monitor interval until shutdown, the wallet is
+    /// topped up or an alert is raised if the balance is low
+    ///
+    /// # Errors
+    ///
+    /// It never returns error, failures of checks are logged
+    pub async fn start(self, mut shutdown_signal: ShutdownSignal) -> Result<()> {
+        let Some(monitor_interval) = self.config.monitor_interval else {
+            return Ok(());
+        };
+
+        let mut monitor_timer = time::interval(monitor_interval);
+        loop {
+            tokio::select! {
+                () = shutdown_signal.wait() => break,
+                _ = monitor_timer.tick() => self.monitor().await,
+            }
+        }
+
+        tracing::warn!("Balance monitor stopped.");
+
+        Ok(())
+    }
+
+    async fn monitor(&self) {
+        let balance = match self.balance().await {
+            Ok(balance) => balance,
+            Err(err) => {
+                tracing::warn!("Fail to check balance of wallet `{}`: {err}", self.pubkey);
+                return;
+            }
+        };
+        if balance >= self.config.min_balance {
+            return;
+        }
+
+        if matches!(self.config.strategy, FundingStrategy::None | FundingStrategy::MinBalance) {
+            tracing::error!(
+                "Balance of wallet `{}` is {balance} lamports, below {} lamports",
+                self.pubkey,
+                self.config.min_balance
+            );
+            metrics::WALLET_LOW_BALANCE.with_label_values(&["alert"]).inc();
+            return;
+        }
+
+        match self.top_up(balance).await {
+            Ok(()) => metrics::WALLET_LOW_BALANCE.with_label_values(&["topped_up"]).inc(),
+            Err(err) => {
+                tracing::error!("Fail to top up wallet `{}`: {err}", self.pubkey);
+                metrics::WALLET_LOW_BALANCE.with_label_values(&["top_up_failed"]).inc();
+            }
+        }
+    }
+
+    async fn balance(&self) -> Result<u64> {
+        let balance =
+            self.client.get_balance(&self.pubkey).await.context(error::GetBalanceSnafu)?;
+        if self.report_balance {
+            metrics::WALLET_BALANCE.set(i64::try_from(balance).unwrap_or(i64::MAX));
+        }
+        Ok(balance)
+    }
+
+    async fn top_up(&self, balance: u64) -> Result<()> {
+        let signature = match &self.config.strategy {
+            FundingStrategy::None => return Ok(()),
+            FundingStrategy::MinBalance => {
+                return error::InsufficientBalanceSnafu {
+                    balance,
+                    min_balance: self.config.min_balance,
+                }
+                .fail();
+            }
+            FundingStrategy::Airdrop { amount } => {
+                tracing::info!("Request airdrop of {amount} lamports for `{}`", self.pubkey);
+                self.client
+                    .request_airdrop(&self.pubkey, *amount)
+                    .await
+                    .context(error::RequestAirdropSnafu)?
+            }
+            FundingStrategy::Transfer { treasury, amount } => {
+                tracing::info!(
+                    "Transfer {amount} lamports from treasury `{}` to `{}`",
+                    treasury.pubkey(),
+                    self.pubkey
+                );
+                let recent_blockhash =
+                    self.client.get_latest_blockhash().await.context(error::TransferFundsSnafu)?;
+                let transaction = Transaction::new_signed_with_payer(
+                    &[system_instruction::transfer(&treasury.pubkey(), &self.pubkey, *amount)],
+                    Some(&treasury.pubkey()),
+                    &[treasury.as_ref()],
+                    recent_blockhash,
+                );
+                self.client
+                    .send_transaction(&transaction)
+                    .await
+                    .context(error::TransferFundsSnafu)?
+            }
+        };
+
+        self.wait_confirmation(&signature).await?;
+        tracing::info!("Wallet `{}` is funded by `{signature}`", self.pubkey);
+
+        Ok(())
+    }
+
+    /// Poll the status of the funding transaction with backoff until it is
+    /// confirmed or the timeout elapses
+    async fn wait_confirmation(&self, signature: &Signature) -> Result<()> {
+        let deadline = Instant::now() + self.config.timeout;
+        let mut poll_interval = INITIAL_POLL_INTERVAL;
+        loop {
+            match self.client.get_signature_status(signature).await {
+                Ok(Some(result)) => {
+                    return result
+                        .context(error::FundingTransactionFailedSnafu { signature: *signature });
+                }
+                Ok(None) => {}
+                Err(err) => tracing::warn!("Fail to get status of `{signature}`: {err}"),
+            }
+
+            let now = Instant::now();
+            ensure!(now < deadline, error::FundingTimeoutSnafu { signature: *signature });
+            time::sleep(poll_interval.min(deadline - now)).await;
+            poll_interval = next_poll_interval(poll_interval);
+        }
+    }
+}
+
+fn next_poll_interval(poll_interval: Duration) -> Duration {
+    poll_interval.saturating_mul(2).min(MAX_POLL_INTERVAL)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{next_poll_interval, INITIAL_POLL_INTERVAL, MAX_POLL_INTERVAL};
+
+    #[test]
+    fn test_next_poll_interval() {
+        let intervals = std::iter::successors(Some(INITIAL_POLL_INTERVAL), |interval| {
+            Some(next_poll_interval(*interval))
+        })
+        .take(6)
+        .collect::<Vec<_>>();
+        assert_eq!(intervals, [
+            Duration::from_millis(500),
+            Duration::from_secs(1),
+            Duration::from_secs(2),
+            Duration::from_secs(4),
+            MAX_POLL_INTERVAL,
+            MAX_POLL_INTERVAL,
+        ]);
+    }
+}
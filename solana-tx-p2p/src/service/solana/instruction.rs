@@ -0,0 +1,134 @@
+use libp2p::PeerId;
+use snafu::ResultExt;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    model::RawMessage,
+    service::{error, Result},
+};
+
+/// Program ID of SPL Memo program v2
+pub const MEMO_PROGRAM_ID: Pubkey = pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+
+/// Signer of the transaction built from a message
+#[derive(Clone, Copy, Debug)]
+pub struct InstructionSigner {
+    pub peer_id: PeerId,
+    pub pubkey: Pubkey,
+}
+
+/// Turn a raw message into the instructions of a transaction.
+///
+/// Each instruction carries its accounts with their signer and writable
+/// metadata in [`AccountMeta`], the signer signs the transaction if any
+/// instruction requires it
+pub trait InstructionBuilder: Send + Sync {
+    fn build(&self, message: &RawMessage, signer: &InstructionSigner) -> Result<Vec<Instruction>>;
+}
+
+/// Borsh encoded string `{message}, Signer: {peer_id}` for `solana-hello-world`
+/// program
+#[derive(Clone, Copy, Debug)]
+pub struct HelloWorldInstructionBuilder {
+    program_id: Pubkey,
+}
+
+impl HelloWorldInstructionBuilder {
+    #[must_use]
+    pub const fn new(program_id: Pubkey) -> Self { Self { program_id } }
+}
+
+impl InstructionBuilder for HelloWorldInstructionBuilder {
+    fn build(&self, message: &RawMessage, signer: &InstructionSigner) -> Result<Vec<Instruction>> {
+        let message = String::from_utf8_lossy(&message.data);
+        let data = format!("{message}, Signer: {}", signer.peer_id);
+
+        Ok(vec![Instruction::new_with_borsh(
+            self.program_id,
+            &data,
+            vec![], // No accounts needed
+        )])
+    }
+}
+
+/// UTF-8 message for SPL Memo program, signed by the transaction signer
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MemoInstructionBuilder;
+
+impl InstructionBuilder for MemoInstructionBuilder {
+    fn build(&self, message: &RawMessage, signer: &InstructionSigner) -> Result<Vec<Instruction>> {
+        let memo = std::str::from_utf8(&message.data).context(error::InvalidMemoSnafu)?;
+
+        Ok(vec![Instruction::new_with_bytes(MEMO_PROGRAM_ID, memo.as_bytes(), vec![
+            AccountMeta::new_readonly(signer.pubkey, true),
+        ])])
+    }
+}
+
+/// Bincode serialized `Vec<Instruction>` supplied by client.
+///
+/// Instructions must not use the signer as a signer or writable account,
+/// otherwise any client could transfer the funds or authorities of the signer
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RawInstructionBuilder;
+
+impl InstructionBuilder for RawInstructionBuilder {
+    fn build(&self, message: &RawMessage, signer: &InstructionSigner) -> Result<Vec<Instruction>> {
+        let instructions: Vec<Instruction> =
+            bincode::deserialize(&message.data).context(error::DeserializeInstructionsSnafu)?;
+        for instruction in &instructions {
+            let uses_signer = instruction.accounts.iter().any(|account| {
+                account.pubkey == signer.pubkey && (account.is_signer || account.is_writable)
+            });
+            if uses_signer {
+                return error::InstructionUsesSignerSnafu {
+                    program_id: instruction.program_id,
+                    signer: signer.pubkey,
+                }
+                .fail();
+            }
+        }
+
+        Ok(instructions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libp2p::PeerId;
+    use solana_sdk::{
+        instruction::{AccountMeta, Instruction},
+        pubkey::Pubkey,
+        system_instruction, system_program,
+    };
+
+    use super::{InstructionBuilder, InstructionSigner, RawInstructionBuilder, MEMO_PROGRAM_ID};
+    use crate::{model::RawMessage, service::error::Error};
+
+    #[test]
+    fn test_raw_instructions_must_not_use_signer() {
+        let signer = InstructionSigner { peer_id: PeerId::random(), pubkey: Pubkey::new_unique() };
+        let build = |instructions: Vec<_>| {
+            let data = bincode::serialize(&instructions).unwrap();
+            RawInstructionBuilder.build(&RawMessage::new(String::new(), data), &signer)
+        };
+
+        let memo = Instruction::new_with_bytes(MEMO_PROGRAM_ID, b"hello", vec![
+            AccountMeta::new_readonly(signer.pubkey, false),
+        ]);
+        assert_eq!(build(vec![memo.clone()]).unwrap(), vec![memo]);
+
+        let transfer = system_instruction::transfer(&signer.pubkey, &Pubkey::new_unique(), 1);
+        assert!(matches!(
+            build(vec![transfer]),
+            Err(Error::InstructionUsesSigner { program_id, .. }) if program_id == system_program::ID
+        ));
+
+        let credit = system_instruction::transfer(&Pubkey::new_unique(), &signer.pubkey, 1);
+        assert!(matches!(build(vec![credit]), Err(Error::InstructionUsesSigner { .. })));
+    }
+}
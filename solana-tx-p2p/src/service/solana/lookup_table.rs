@@ -0,0 +1,41 @@
+use snafu::{OptionExt, ResultExt};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    address_lookup_table::{state::AddressLookupTable, AddressLookupTableAccount},
+    pubkey::Pubkey,
+};
+
+use crate::service::{error, error::Result};
+
+/// Fetch address lookup tables used to compile v0 messages, the tables must be
+/// created and extended in advance
+///
+/// # Errors
+///
+/// * fail to get lookup table accounts
+/// * lookup table account does not exist or is invalid
+pub async fn fetch_address_lookup_tables(
+    client: &RpcClient,
+    addresses: &[Pubkey],
+) -> Result<Vec<AddressLookupTableAccount>> {
+    let accounts = client
+        .get_multiple_accounts(addresses)
+        .await
+        .context(error::GetAddressLookupTablesSnafu)?;
+
+    addresses
+        .iter()
+        .zip(accounts)
+        .map(|(key, account)| {
+            let account =
+                account.context(error::AddressLookupTableNotFoundSnafu { address: *key })?;
+            let table = AddressLookupTable::deserialize(&account.data)
+                .context(error::DeserializeAddressLookupTableSnafu { address: *key })?;
+            tracing::info!(
+                "Use address lookup table `{key}` with {} addresses",
+                table.addresses.len()
+            );
+            Ok(AddressLookupTableAccount { key: *key, addresses: table.addresses.to_vec() })
+        })
+        .collect()
+}
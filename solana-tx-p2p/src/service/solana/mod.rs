@@ -1,48 +1,54 @@
+mod batch;
+mod blockhash;
+mod compute_budget;
+mod confirmation;
+mod fee_payer;
+mod funding;
+mod instruction;
+mod lookup_table;
+mod multisig;
+mod nonce;
+mod policy;
+mod relay_queue;
 mod relayer;
+mod rpc_pool;
 mod signer;
 
 use std::sync::Arc;
 
-use snafu::ResultExt;
-use solana_client::nonblocking::rpc_client::RpcClient;
-use solana_sdk::{
-    commitment_config::CommitmentConfig, signature::Keypair as SolanaKeypair, signer::Signer as _,
-};
+use solana_client::{nonblocking::rpc_client::RpcClient, rpc_client::RpcClientConfig};
+use solana_sdk::commitment_config::CommitmentConfig;
 
 pub use self::{
+    batch::BatchConfig,
+    blockhash::BlockhashCache,
+    compute_budget::{
+        leading_compute_budget_instructions, ComputeBudget, ComputeBudgetConfig, ComputeUnitPrice,
+        MAX_COMPUTE_UNIT_LIMIT,
+    },
+    confirmation::ConfirmationTracker,
+    fee_payer::RelayerFeePayerConfig,
+    funding::{FundingConfig, FundingStrategy, WalletFunding},
+    instruction::{
+        HelloWorldInstructionBuilder, InstructionBuilder, InstructionSigner,
+        MemoInstructionBuilder, RawInstructionBuilder, MEMO_PROGRAM_ID,
+    },
+    lookup_table::fetch_address_lookup_tables,
+    multisig::MultisigConfig,
+    nonce::{DurableNonce, DurableNonceConfig},
+    policy::{SigningPolicy, SigningPolicyConfig},
+    relay_queue::RelayRetryConfig,
     relayer::{RelayerInboundEvent, SolanaRelayer},
+    rpc_pool::{is_endpoint_unavailable, RpcPool, RpcPoolConfig},
     signer::{SignerInboundEvent, SolanaSigner},
 };
-use crate::service::{error, error::Result};
-
-pub async fn create_solana_client(
-    rpc_url: &str,
-    keypair: Arc<SolanaKeypair>,
-) -> Result<Arc<RpcClient>> {
-    // Connect to the Solana devnet
-    let client = RpcClient::new_with_commitment(rpc_url.to_string(), CommitmentConfig::confirmed());
-
-    // Request airdrop
-    tracing::debug!("Request airdrop");
-    let airdrop_amount = 1_000_000_000; // 1 SOL
-    let signature = client
-        .request_airdrop(&keypair.pubkey(), airdrop_amount)
-        .await
-        .context(error::RequestAirdropSnafu)?;
-
-    // Wait for airdrop confirmation
-    tracing::debug!("Wait airdrop confirmation");
-    loop {
-        let confirmed = client
-            .confirm_transaction(&signature)
-            .await
-            .context(error::ConfirmSolanaTransactionSnafu)?;
-        if confirmed {
-            break;
-        }
-    }
-
-    tracing::debug!("Complete airdrop confirmation");
 
-    Ok(Arc::new(client))
+/// Create the Solana client shared by all workers, requests are sent through
+/// the endpoints of the pool
+#[must_use]
+pub fn create_solana_client(rpc_pool: RpcPool) -> Arc<RpcClient> {
+    Arc::new(RpcClient::new_sender(
+        rpc_pool,
+        RpcClientConfig::with_commitment(CommitmentConfig::confirmed()),
+    ))
 }
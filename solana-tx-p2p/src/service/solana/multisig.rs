@@ -0,0 +1,219 @@
+use std::time::Duration;
+
+use libp2p::PeerId;
+use solana_sdk::{
+    hash::Hash,
+    instruction::{AccountMeta, Instruction},
+    message::Message,
+    pubkey::Pubkey,
+    system_instruction,
+};
+
+use crate::{
+    model::{peer_id_to_solana_pubkey, CosignRequest, MessageId},
+    service::solana::{
+        compute_budget::leading_compute_budget_instructions, nonce::advance_nonce_accounts,
+        InstructionBuilder, InstructionSigner, MEMO_PROGRAM_ID,
+    },
+};
+
+/// M-of-N multisig signing, the transaction requires `threshold` (M) signers
+/// out of the connected peers (N)
+#[derive(Clone, Copy, Debug)]
+pub struct MultisigConfig {
+    /// number of required signers, including the coordinator
+    pub threshold: usize,
+    /// how long the coordinator waits for a co-signer
+    pub timeout: Duration,
+    /// signing rounds a message waits for enough co-signers before it is
+    /// rejected
+    pub max_attempts: usize,
+}
+
+/// Memo instruction requiring all `signers` to sign the transaction
+pub fn multisig_instruction(message_id: &MessageId, signers: &[Pubkey]) -> Instruction {
+    let memo = format!("multisig: {message_id}");
+    let accounts = signers.iter().map(|signer| AccountMeta::new_readonly(*signer, true)).collect();
+
+    Instruction::new_with_bytes(MEMO_PROGRAM_ID, memo.as_bytes(), accounts)
+}
+
+/// Build the transaction message which requires `cosigners` to sign besides the
+/// coordinator
+pub fn build_multisig_message(
+    message_id: &MessageId,
+    mut instructions: Vec<Instruction>,
+    coordinator: &Pubkey,
+    cosigners: &[Pubkey],
+    recent_blockhash: &Hash,
+) -> Message {
+    // co-signers are sorted so that the co-signers can rebuild the same message
+    // from the account keys
+    let mut cosigners = cosigners.to_vec();
+    cosigners.sort_unstable();
+    let signers = std::iter::once(*coordinator).chain(cosigners).collect::<Vec<_>>();
+    instructions.push(multisig_instruction(message_id, &signers));
+
+    Message::new_with_blockhash(&instructions, Some(coordinator), recent_blockhash)
+}
+
+/// Verify that the message of a co-sign request is built from the raw message
+/// by the coordinator and requires `cosigner` to sign, return the reason of
+/// rejection otherwise
+pub fn verify_cosign_request(
+    instruction_builder: &dyn InstructionBuilder,
+    coordinator: PeerId,
+    cosigner: &Pubkey,
+    request: &CosignRequest,
+) -> Result<(), String> {
+    let CosignRequest { raw_message, message } = request;
+    if !raw_message.verify_id() {
+        return Err(format!("message ID `{}` does not match its content", raw_message.id));
+    }
+
+    let coordinator_pubkey = peer_id_to_solana_pubkey(&coordinator)
+        .ok_or_else(|| format!("peer `{coordinator}` does not have ed25519 public key"))?;
+
+    let num_signers = usize::from(message.header.num_required_signatures);
+    let Some((fee_payer, cosigners)) =
+        message.account_keys.get(..num_signers).and_then(<[Pubkey]>::split_first)
+    else {
+        return Err("transaction does not have any signer".to_string());
+    };
+    if *fee_payer != coordinator_pubkey {
+        return Err("fee payer is not the coordinator".to_string());
+    }
+    if !cosigners.contains(cosigner) {
+        return Err("transaction does not require this peer to sign".to_string());
+    }
+
+    let signer = InstructionSigner { peer_id: coordinator, pubkey: coordinator_pubkey };
+    let mut instructions =
+        instruction_builder.build(raw_message, &signer).map_err(|err| err.to_string())?;
+    // the coordinator sets the compute budget and signs against its durable
+    // nonce
+    instructions.splice(
+        0..0,
+        leading_compute_budget_instructions(&message.account_keys, &message.instructions),
+    );
+    if let Some((nonce_account, authority)) =
+        advance_nonce_accounts(&message.account_keys, &message.instructions)
+    {
+        if authority != coordinator_pubkey {
+            return Err("nonce authority is not the coordinator".to_string());
+        }
+        instructions
+            .insert(0, system_instruction::advance_nonce_account(&nonce_account, &authority));
+    }
+    let expected = build_multisig_message(
+        &raw_message.id,
+        instructions,
+        &coordinator_pubkey,
+        cosigners,
+        &message.recent_blockhash,
+    );
+    if expected != *message {
+        return Err("transaction does not match the message".to_string());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::{
+        compute_budget::ComputeBudgetInstruction, hash::Hash, signer::Signer as _,
+        system_instruction,
+    };
+
+    use super::{build_multisig_message, verify_cosign_request};
+    use crate::{
+        model::{CosignRequest, RawMessage},
+        service::{
+            solana::{nonce::nonce_account_address, MemoInstructionBuilder},
+            InstructionBuilder, InstructionSigner, PeerWorker,
+        },
+    };
+
+    #[test]
+    fn test_verify_cosign_request() {
+        let (coordinator_key, coordinator_keypair) = PeerWorker::generate_keypair();
+        let coordinator = coordinator_key.public().to_peer_id();
+        let (_, cosigner_keypair) = PeerWorker::generate_keypair();
+        let (_, other_keypair) = PeerWorker::generate_keypair();
+        let cosigner = cosigner_keypair.pubkey();
+
+        let raw_message = RawMessage::new(coordinator.to_string(), b"hello".to_vec());
+        let signer =
+            InstructionSigner { peer_id: coordinator, pubkey: coordinator_keypair.pubkey() };
+        let instructions = MemoInstructionBuilder.build(&raw_message, &signer).unwrap();
+        let message = build_multisig_message(
+            &raw_message.id,
+            instructions,
+            &signer.pubkey,
+            &[other_keypair.pubkey(), cosigner],
+            &Hash::new_unique(),
+        );
+        let request = CosignRequest { raw_message: raw_message.clone(), message };
+
+        assert_eq!(
+            verify_cosign_request(&MemoInstructionBuilder, coordinator, &cosigner, &request),
+            Ok(())
+        );
+
+        // compute budget and durable nonce of the coordinator
+        let nonce_account = nonce_account_address(&signer.pubkey, 0);
+        let mut instructions = MemoInstructionBuilder.build(&raw_message, &signer).unwrap();
+        instructions.insert(0, ComputeBudgetInstruction::set_compute_unit_price(1_000));
+        instructions
+            .insert(0, system_instruction::advance_nonce_account(&nonce_account, &signer.pubkey));
+        let message = build_multisig_message(
+            &raw_message.id,
+            instructions,
+            &signer.pubkey,
+            &[cosigner],
+            &Hash::new_unique(),
+        );
+        let nonce_request = CosignRequest { raw_message: raw_message.clone(), message };
+        assert_eq!(
+            verify_cosign_request(&MemoInstructionBuilder, coordinator, &cosigner, &nonce_request),
+            Ok(())
+        );
+
+        // durable nonce of another authority
+        let mut instructions = MemoInstructionBuilder.build(&raw_message, &signer).unwrap();
+        instructions
+            .insert(0, system_instruction::advance_nonce_account(&nonce_account, &cosigner));
+        let message = build_multisig_message(
+            &raw_message.id,
+            instructions,
+            &signer.pubkey,
+            &[cosigner],
+            &Hash::new_unique(),
+        );
+        let nonce_request = CosignRequest { raw_message, message };
+        assert!(verify_cosign_request(
+            &MemoInstructionBuilder,
+            coordinator,
+            &cosigner,
+            &nonce_request
+        )
+        .is_err());
+
+        // peers which are not required to sign
+        let (_, stranger_keypair) = PeerWorker::generate_keypair();
+        assert!(verify_cosign_request(
+            &MemoInstructionBuilder,
+            coordinator,
+            &stranger_keypair.pubkey(),
+            &request
+        )
+        .is_err());
+
+        // message which is not built from the raw message
+        let mut tampered = request;
+        tampered.raw_message = RawMessage::new(coordinator.to_string(), b"evil".to_vec());
+        assert!(verify_cosign_request(&MemoInstructionBuilder, coordinator, &cosigner, &tampered)
+            .is_err());
+    }
+}
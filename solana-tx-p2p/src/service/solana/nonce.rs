@@ -0,0 +1,234 @@
+use std::{sync::Arc, time::Duration};
+
+use snafu::ResultExt;
+use solana_client::{nonblocking::rpc_client::RpcClient, nonce_utils};
+use solana_sdk::{
+    account::Account,
+    hash::Hash,
+    instruction::{CompiledInstruction, Instruction},
+    nonce::State as NonceState,
+    pubkey::Pubkey,
+    signature::Keypair as SolanaKeypair,
+    signer::Signer as _,
+    system_instruction::{self, SystemInstruction},
+    system_program,
+    transaction::Transaction,
+};
+use tokio::time::Instant;
+
+use crate::service::{error, error::Result};
+
+/// Sign transactions against durable nonces instead of recent blockhashes, so
+/// that signed transactions stay valid until they are relayed
+#[derive(Clone, Copy, Debug)]
+pub struct DurableNonceConfig {
+    /// number of nonce accounts owned by the node, each nonce is leased by a
+    /// signed transaction until the transaction advances it
+    pub accounts: u8,
+    /// how often nonce values are refreshed from the cluster
+    pub refresh_interval: Duration,
+    /// leases of nonces which are not advanced in time are released, so that
+    /// nonces of transactions which are never relayed can be reused
+    pub lease_timeout: Duration,
+}
+
+/// Nonce leased by a signed transaction
+#[derive(Clone, Copy, Debug)]
+pub struct NonceLease {
+    pub account: Pubkey,
+    pub authority: Pubkey,
+    /// durable nonce used as the recent blockhash of the transaction
+    pub value: Hash,
+}
+
+impl NonceLease {
+    /// Instruction advancing the leased nonce, which must be the first
+    /// instruction of the transaction
+    #[must_use]
+    pub fn advance_instruction(&self) -> Instruction {
+        system_instruction::advance_nonce_account(&self.account, &self.authority)
+    }
+}
+
+struct NonceAccount {
+    pubkey: Pubkey,
+    value: Option<Hash>,
+    leased_at: Option<Instant>,
+}
+
+/// Nonce accounts owned by the node, the node keypair is the nonce authority
+pub struct DurableNonce {
+    config: DurableNonceConfig,
+    authority: Arc<SolanaKeypair>,
+    client: Arc<RpcClient>,
+
+    accounts: Vec<NonceAccount>,
+    refreshed_at: Option<Instant>,
+}
+
+impl DurableNonce {
+    /// Create nonce accounts which do not exist yet, funded by the authority
+    ///
+    /// # Errors
+    ///
+    /// * fail to get nonce accounts
+    /// * fail to create nonce accounts
+    pub async fn new(
+        config: DurableNonceConfig,
+        authority: Arc<SolanaKeypair>,
+        client: Arc<RpcClient>,
+    ) -> Result<Self> {
+        let authority_pubkey = authority.pubkey();
+        let pubkeys = (0..config.accounts)
+            .map(|index| nonce_account_address(&authority_pubkey, index))
+            .collect::<Vec<_>>();
+        let existing =
+            client.get_multiple_accounts(&pubkeys).await.context(error::GetNonceAccountsSnafu)?;
+
+        let lamports = client
+            .get_minimum_balance_for_rent_exemption(NonceState::size())
+            .await
+            .context(error::CreateNonceAccountSnafu)?;
+        for (index, (pubkey, account)) in (0..).zip(pubkeys.iter().zip(existing)) {
+            if account.is_some() {
+                continue;
+            }
+
+            tracing::info!("Create nonce account `{pubkey}`");
+            let instructions = system_instruction::create_nonce_account_with_seed(
+                &authority_pubkey,
+                pubkey,
+                &authority_pubkey,
+                &nonce_account_seed(index),
+                &authority_pubkey,
+                lamports,
+            );
+            let recent_blockhash =
+                client.get_latest_blockhash().await.context(error::CreateNonceAccountSnafu)?;
+            let transaction = Transaction::new_signed_with_payer(
+                &instructions,
+                Some(&authority_pubkey),
+                &[&authority],
+                recent_blockhash,
+            );
+            client
+                .send_and_confirm_transaction(&transaction)
+                .await
+                .context(error::CreateNonceAccountSnafu)?;
+        }
+
+        let accounts = pubkeys
+            .into_iter()
+            .map(|pubkey| NonceAccount { pubkey, value: None, leased_at: None })
+            .collect();
+        Ok(Self { config, authority, client, accounts, refreshed_at: None })
+    }
+
+    /// Lease an available nonce, nonce values are refreshed if they are stale
+    /// or all nonces are leased. Return `None` if all nonces are leased
+    pub async fn lease(&mut self) -> Option<NonceLease> {
+        let now = Instant::now();
+        let stale = self.refreshed_at.map_or(true, |refreshed_at| {
+            now.duration_since(refreshed_at) >= self.config.refresh_interval
+        });
+        if stale || self.available().is_none() {
+            if let Err(err) = self.refresh(now).await {
+                tracing::error!("Fail to refresh durable nonces: {err}");
+            }
+        }
+
+        let authority = self.authority.pubkey();
+        let account = self.available()?;
+        let account = &mut self.accounts[account];
+        account.leased_at = Some(now);
+        Some(NonceLease { account: account.pubkey, authority, value: account.value? })
+    }
+
+    /// Release the lease of a nonce which is not used by any transaction
+    pub fn release(&mut self, lease: &NonceLease) {
+        if let Some(account) = self
+            .accounts
+            .iter_mut()
+            .find(|account| account.pubkey == lease.account && account.value == Some(lease.value))
+        {
+            account.leased_at = None;
+        }
+    }
+
+    fn available(&self) -> Option<usize> {
+        self.accounts
+            .iter()
+            .position(|account| account.value.is_some() && account.leased_at.is_none())
+    }
+
+    /// Fetch nonce values, leases of advanced nonces or expired leases are
+    /// released
+    async fn refresh(&mut self, now: Instant) -> Result<()> {
+        let pubkeys = self.accounts.iter().map(|account| account.pubkey).collect::<Vec<_>>();
+        let accounts = self
+            .client
+            .get_multiple_accounts(&pubkeys)
+            .await
+            .context(error::GetNonceAccountsSnafu)?;
+
+        for (account, fetched) in self.accounts.iter_mut().zip(accounts) {
+            let value = fetched.as_ref().and_then(|fetched| match nonce_value(fetched) {
+                Ok(value) => Some(value),
+                Err(err) => {
+                    tracing::error!("Invalid nonce account `{}`: {err}", account.pubkey);
+                    None
+                }
+            });
+
+            let advanced = value != account.value;
+            let expired = account.leased_at.is_some_and(|leased_at| {
+                now.duration_since(leased_at) >= self.config.lease_timeout
+            });
+            if advanced || expired {
+                account.leased_at = None;
+            }
+            account.value = value;
+        }
+        self.refreshed_at = Some(now);
+
+        Ok(())
+    }
+}
+
+/// Nonce account `index` of `authority`, derived with seed so that the node
+/// reuses its nonce accounts after restart
+#[must_use]
+pub fn nonce_account_address(authority: &Pubkey, index: u8) -> Pubkey {
+    Pubkey::create_with_seed(authority, &nonce_account_seed(index), &system_program::id())
+        .expect("seed is shorter than `MAX_SEED_LEN`")
+}
+
+fn nonce_account_seed(index: u8) -> String { format!("durable-nonce-{index}") }
+
+fn nonce_value(account: &Account) -> Result<Hash, nonce_utils::Error> {
+    nonce_utils::data_from_account(account).map(|data| data.blockhash())
+}
+
+/// The nonce account and its authority if the first instruction of the message
+/// advances a durable nonce, accounts of the instruction must be static account
+/// keys of the message
+#[must_use]
+pub fn advance_nonce_accounts(
+    account_keys: &[Pubkey],
+    instructions: &[CompiledInstruction],
+) -> Option<(Pubkey, Pubkey)> {
+    let instruction = instructions.first()?;
+    if *account_keys.get(usize::from(instruction.program_id_index))? != system_program::id() {
+        return None;
+    }
+    let Ok(SystemInstruction::AdvanceNonceAccount) = bincode::deserialize(&instruction.data) else {
+        return None;
+    };
+
+    let account = |position: usize| {
+        let index = *instruction.accounts.get(position)?;
+        account_keys.get(usize::from(index)).copied()
+    };
+    // accounts: nonce account, recent blockhashes sysvar, nonce authority
+    Some((account(0)?, account(2)?))
+}
@@ -0,0 +1,332 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+    time::Duration,
+};
+
+use solana_sdk::{
+    fee::FeeStructure, instruction::Instruction, message::Message, pubkey::Pubkey,
+    system_instruction::SystemInstruction, system_program,
+};
+use tokio::time::Instant;
+
+use crate::model::RawMessage;
+
+/// Policies checked by the signer before signing a message, policies which
+/// are not set are not enforced
+#[derive(Clone, Debug, Default)]
+pub struct SigningPolicyConfig {
+    /// programs which instructions can invoke, any program is allowed if empty
+    pub allowed_program_ids: HashSet<Pubkey>,
+    pub max_instructions: Option<usize>,
+    /// max size of message data in bytes
+    pub max_payload_size: Option<usize>,
+    /// max messages signed for each origin peer in `rate_limit_window`
+    pub rate_limit: Option<usize>,
+    pub rate_limit_window: Duration,
+    /// max lamports spent by the fee payer in `spend_window`, including
+    /// signature fees, priority fees and lamports transferred by System Program
+    pub spend_cap: Option<u64>,
+    pub spend_window: Duration,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PolicyViolation {
+    ProgramNotAllowed(Pubkey),
+    TooManyInstructions { count: usize, max: usize },
+    PayloadTooLarge { size: usize, max: usize },
+    RateLimited { origin: String, limit: usize },
+    SpendCapExceeded { spend: u64, remaining: u64 },
+}
+
+impl PolicyViolation {
+    pub const fn as_label(&self) -> &'static str {
+        match self {
+            Self::ProgramNotAllowed(_) => "program_not_allowed",
+            Self::TooManyInstructions { .. } => "too_many_instructions",
+            Self::PayloadTooLarge { .. } => "payload_too_large",
+            Self::RateLimited { .. } => "rate_limited",
+            Self::SpendCapExceeded { .. } => "spend_cap_exceeded",
+        }
+    }
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ProgramNotAllowed(program_id) => {
+                write!(f, "program `{program_id}` is not allowed")
+            }
+            Self::TooManyInstructions { count, max } => {
+                write!(f, "{count} instructions exceed the limit of {max}")
+            }
+            Self::PayloadTooLarge { size, max } => {
+                write!(f, "payload of {size} bytes exceeds the limit of {max} bytes")
+            }
+            Self::RateLimited { origin, limit } => {
+                write!(f, "origin `{origin}` exceeds the rate limit of {limit} messages")
+            }
+            Self::SpendCapExceeded { spend, remaining } => {
+                write!(
+                    f,
+                    "spending {spend} lamports exceeds the remaining cap of {remaining} lamports"
+                )
+            }
+        }
+    }
+}
+
+/// Stateful signing policy, messages are checked before signing and recorded
+/// after being signed, so that messages which fail to be signed do not count
+/// against the limits
+pub struct SigningPolicy {
+    config: SigningPolicyConfig,
+    signed_by_origin: HashMap<String, VecDeque<Instant>>,
+    spending: VecDeque<(Instant, u64)>,
+}
+
+impl SigningPolicy {
+    #[must_use]
+    pub fn new(config: SigningPolicyConfig) -> Self {
+        Self { config, signed_by_origin: HashMap::new(), spending: VecDeque::new() }
+    }
+
+    /// Check the message and its instructions, return the lamports spent by
+    /// `fee_payer` including `priority_fee`
+    ///
+    /// # Errors
+    ///
+    /// * the first policy violated by the message
+    pub fn check(
+        &mut self,
+        raw_message: &RawMessage,
+        instructions: &[Instruction],
+        fee_payer: &Pubkey,
+        priority_fee: u64,
+        now: Instant,
+    ) -> Result<u64, PolicyViolation> {
+        self.evict(now);
+        let config = &self.config;
+
+        if let Some(max) = config.max_payload_size {
+            if raw_message.data.len() > max {
+                return Err(PolicyViolation::PayloadTooLarge { size: raw_message.data.len(), max });
+            }
+        }
+
+        if let Some(max) = config.max_instructions {
+            if instructions.len() > max {
+                return Err(PolicyViolation::TooManyInstructions {
+                    count: instructions.len(),
+                    max,
+                });
+            }
+        }
+
+        if !config.allowed_program_ids.is_empty() {
+            if let Some(instruction) = instructions
+                .iter()
+                .find(|instruction| !config.allowed_program_ids.contains(&instruction.program_id))
+            {
+                return Err(PolicyViolation::ProgramNotAllowed(instruction.program_id));
+            }
+        }
+
+        if let Some(limit) = config.rate_limit {
+            let signed = self.signed_by_origin.get(&raw_message.origin).map_or(0, VecDeque::len);
+            if signed >= limit {
+                return Err(PolicyViolation::RateLimited {
+                    origin: raw_message.origin.clone(),
+                    limit,
+                });
+            }
+        }
+
+        let spend = spend(instructions, fee_payer).saturating_add(priority_fee);
+        if let Some(cap) = config.spend_cap {
+            let remaining =
+                cap.saturating_sub(self.spending.iter().map(|(_, lamports)| lamports).sum::<u64>());
+            if spend > remaining {
+                return Err(PolicyViolation::SpendCapExceeded { spend, remaining });
+            }
+        }
+
+        Ok(spend)
+    }
+
+    /// Check messages signed in one transaction, each message counts against
+    /// the limits checked for the messages after it. Nothing is recorded
+    pub fn check_batch(
+        &mut self,
+        messages: &[(&RawMessage, &[Instruction])],
+        fee_payer: &Pubkey,
+        priority_fee: u64,
+        now: Instant,
+    ) -> Vec<Result<u64, PolicyViolation>> {
+        let mut results = Vec::with_capacity(messages.len());
+        for (raw_message, instructions) in messages {
+            let result = self.check(raw_message, instructions, fee_payer, priority_fee, now);
+            if let Ok(spend) = result {
+                self.record(&raw_message.origin, spend, now);
+            }
+            results.push(result);
+        }
+
+        // the messages are recorded once the transaction is signed
+        for ((raw_message, _), result) in messages.iter().zip(&results).rev() {
+            if result.is_ok() {
+                self.unrecord(&raw_message.origin);
+            }
+        }
+
+        results
+    }
+
+    /// Record a signed message of `origin` which spends `spend` lamports
+    pub fn record(&mut self, origin: &str, spend: u64, now: Instant) {
+        if self.config.rate_limit.is_some() {
+            self.signed_by_origin.entry(origin.to_string()).or_default().push_back(now);
+        }
+        if self.config.spend_cap.is_some() {
+            self.spending.push_back((now, spend));
+        }
+    }
+
+    /// Remove the last record of `origin`
+    fn unrecord(&mut self, origin: &str) {
+        if let Some(signed) = self.signed_by_origin.get_mut(origin) {
+            signed.pop_back();
+            if signed.is_empty() {
+                self.signed_by_origin.remove(origin);
+            }
+        }
+        if self.config.spend_cap.is_some() {
+            self.spending.pop_back();
+        }
+    }
+
+    fn evict(&mut self, now: Instant) {
+        let rate_limit_window = self.config.rate_limit_window;
+        self.signed_by_origin.retain(|_, signed| {
+            while signed.front().is_some_and(|at| now.duration_since(*at) >= rate_limit_window) {
+                signed.pop_front();
+            }
+            !signed.is_empty()
+        });
+
+        let spend_window = self.config.spend_window;
+        while self.spending.front().is_some_and(|(at, _)| now.duration_since(*at) >= spend_window) {
+            self.spending.pop_front();
+        }
+    }
+}
+
+/// Lamports spent by `fee_payer`: signature fees and lamports transferred or
+/// funded by System Program instructions
+fn spend(instructions: &[Instruction], fee_payer: &Pubkey) -> u64 {
+    let message = Message::new(instructions, Some(fee_payer));
+    let signature_fee = FeeStructure::default().lamports_per_signature;
+    let mut spend = u64::from(message.header.num_required_signatures).saturating_mul(signature_fee);
+
+    for instruction in instructions {
+        if instruction.program_id != system_program::id()
+            || instruction.accounts.first().map(|account| &account.pubkey) != Some(fee_payer)
+        {
+            continue;
+        }
+
+        let lamports = match bincode::deserialize::<SystemInstruction>(&instruction.data) {
+            Ok(
+                SystemInstruction::Transfer { lamports }
+                | SystemInstruction::TransferWithSeed { lamports, .. }
+                | SystemInstruction::CreateAccount { lamports, .. }
+                | SystemInstruction::CreateAccountWithSeed { lamports, .. },
+            ) => lamports,
+            _ => 0,
+        };
+        spend = spend.saturating_add(lamports);
+    }
+
+    spend
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, time::Duration};
+
+    use solana_sdk::{pubkey::Pubkey, system_instruction};
+    use tokio::time::Instant;
+
+    use super::{PolicyViolation, SigningPolicy, SigningPolicyConfig};
+    use crate::{
+        model::RawMessage,
+        service::solana::{
+            InstructionBuilder, InstructionSigner, MemoInstructionBuilder, MEMO_PROGRAM_ID,
+        },
+    };
+
+    #[test]
+    fn test_signing_policy() {
+        let fee_payer = Pubkey::new_unique();
+        let signer = InstructionSigner { peer_id: libp2p::PeerId::random(), pubkey: fee_payer };
+        let mut policy = SigningPolicy::new(SigningPolicyConfig {
+            allowed_program_ids: HashSet::from([MEMO_PROGRAM_ID]),
+            max_instructions: Some(1),
+            max_payload_size: Some(8),
+            rate_limit: Some(1),
+            rate_limit_window: Duration::from_secs(60),
+            spend_cap: Some(10_000),
+            spend_window: Duration::from_secs(60),
+        });
+        let now = Instant::now();
+
+        let raw_message = RawMessage::new("origin".to_string(), b"hello".to_vec());
+        let instructions = MemoInstructionBuilder.build(&raw_message, &signer).unwrap();
+        let spend = policy.check(&raw_message, &instructions, &fee_payer, 0, now).unwrap();
+        assert_eq!(spend, 5_000);
+        policy.record(&raw_message.origin, spend, now);
+
+        // rate limit of the origin is reached, other origins are not limited
+        assert!(matches!(
+            policy.check(&raw_message, &instructions, &fee_payer, 0, now),
+            Err(PolicyViolation::RateLimited { .. })
+        ));
+        let other = RawMessage::new("other".to_string(), b"hello".to_vec());
+        assert!(policy.check(&other, &instructions, &fee_payer, 0, now).is_ok());
+        assert_eq!(
+            policy.check(&other, &instructions, &fee_payer, 5_001, now),
+            Err(PolicyViolation::SpendCapExceeded { spend: 10_001, remaining: 5_000 })
+        );
+
+        let large = RawMessage::new("other".to_string(), b"hello world".to_vec());
+        assert_eq!(
+            policy.check(&large, &instructions, &fee_payer, 0, now),
+            Err(PolicyViolation::PayloadTooLarge { size: 11, max: 8 })
+        );
+
+        let transfer = system_instruction::transfer(&fee_payer, &Pubkey::new_unique(), 1);
+        assert_eq!(
+            policy.check(&other, std::slice::from_ref(&transfer), &fee_payer, 0, now),
+            Err(PolicyViolation::ProgramNotAllowed(transfer.program_id))
+        );
+        assert_eq!(
+            policy.check(&other, &[instructions[0].clone(), transfer], &fee_payer, 0, now),
+            Err(PolicyViolation::TooManyInstructions { count: 2, max: 1 })
+        );
+
+        // spend cap is reached until the window passes
+        policy.record(&other.origin, spend, now);
+        assert_eq!(
+            policy.check(
+                &RawMessage::new("third".to_string(), vec![]),
+                &instructions,
+                &fee_payer,
+                0,
+                now
+            ),
+            Err(PolicyViolation::SpendCapExceeded { spend: 5_000, remaining: 0 })
+        );
+        let later = now + Duration::from_secs(60);
+        assert!(policy.check(&raw_message, &instructions, &fee_payer, 0, later).is_ok());
+    }
+}
@@ -0,0 +1,484 @@
+use std::{
+    collections::VecDeque,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use solana_client::client_error::{ClientError, ClientErrorKind};
+use solana_sdk::{signature::Signature, transaction::TransactionError};
+
+use crate::{
+    model::{DeadLetter, SignedMessage},
+    service::Store,
+};
+
+/// Dead letters kept by the relayer, the oldest are dropped when exceeded
+const MAX_DEAD_LETTERS: usize = 1_000;
+/// Transactions held for other relayers, the oldest are dropped when exceeded
+const MAX_STANDBY_TRANSACTIONS: usize = 10_000;
+/// Signatures of transactions which are done, the oldest are dropped when
+/// exceeded
+const MAX_DONE_TRANSACTIONS: usize = 10_000;
+
+/// How the relayer retries transactions which fail to be sent
+#[derive(Clone, Copy, Debug)]
+pub struct RelayRetryConfig {
+    /// attempts to send a transaction before it is dead-lettered
+    pub max_attempts: u32,
+    /// backoff after the first failed attempt, doubled after each attempt
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+/// How a failure to send a transaction is handled
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RelayFailure {
+    /// The transaction has landed already
+    AlreadyProcessed,
+    /// The blockhash or the durable nonce of the transaction is no longer
+    /// valid, the messages must be signed again
+    BlockhashNotFound,
+    /// The transaction may succeed if sent again later
+    Transient,
+    /// The transaction never succeeds as it is
+    Permanent,
+}
+
+impl RelayFailure {
+    #[must_use]
+    pub fn classify(err: &ClientError) -> Self {
+        if let Some(err) = err.get_transaction_error() {
+            return match err {
+                TransactionError::AlreadyProcessed => Self::AlreadyProcessed,
+                TransactionError::BlockhashNotFound => Self::BlockhashNotFound,
+                TransactionError::AccountInUse
+                | TransactionError::ClusterMaintenance
+                | TransactionError::WouldExceedMaxBlockCostLimit
+                | TransactionError::WouldExceedMaxAccountCostLimit
+                | TransactionError::WouldExceedAccountDataBlockLimit
+                | TransactionError::WouldExceedAccountDataTotalLimit
+                | TransactionError::WouldExceedMaxVoteCostLimit
+                | TransactionError::ProgramCacheHitMaxLimit => Self::Transient,
+                _ => Self::Permanent,
+            };
+        }
+
+        match err.kind() {
+            ClientErrorKind::SigningError(_) => Self::Permanent,
+            _ => Self::Transient,
+        }
+    }
+}
+
+/// A transaction waiting to be sent
+pub struct RelayTask {
+    pub signed_message: SignedMessage,
+    /// failed attempts to send the transaction
+    pub attempts: u32,
+    /// the transaction is taken over from another relayer which may have sent
+    /// it, so that it is checked on chain before being sent
+    pub handed_over: bool,
+    next_attempt_at: Instant,
+}
+
+/// Transactions waiting to be sent by the relayer with their retry state, and
+/// the transactions which fail to be sent. Transactions signed while another
+/// peer is the relayer are held on standby until they are done, and taken
+/// over if this peer is elected as the relayer. Dead letters are persisted in
+/// the store, so that they are kept across restarts
+pub struct RelayQueue {
+    config: RelayRetryConfig,
+    store: Arc<dyn Store>,
+    tasks: Vec<RelayTask>,
+    dead_letters: VecDeque<(SignedMessage, DeadLetter)>,
+    /// transactions held for the relayer of another peer
+    standby: VecDeque<SignedMessage>,
+    done: VecDeque<Signature>,
+}
+
+impl RelayQueue {
+    /// Queue with the dead letters persisted in the store
+    #[must_use]
+    pub fn new(config: RelayRetryConfig, store: Arc<dyn Store>) -> Self {
+        let dead_letters = store.list_dead_letters().unwrap_or_else(|err| {
+            tracing::error!("Fail to load dead letters from store: {err}");
+            Vec::new()
+        });
+        let mut queue = Self {
+            config,
+            store,
+            tasks: Vec::new(),
+            dead_letters: VecDeque::with_capacity(dead_letters.len()),
+            standby: VecDeque::new(),
+            done: VecDeque::new(),
+        };
+        for (signed_message, dead_letter) in dead_letters {
+            queue.push_dead_letter(signed_message, dead_letter);
+        }
+
+        queue
+    }
+
+    /// Queue a transaction to be sent immediately
+    pub fn push(&mut self, signed_message: SignedMessage, now: Instant) {
+        self.tasks.push(RelayTask {
+            signed_message,
+            attempts: 0,
+            handed_over: false,
+            next_attempt_at: now,
+        });
+    }
+
+    /// Queue a transaction which is not known yet, return whether it is queued
+    pub fn accept(&mut self, signed_message: SignedMessage, now: Instant) -> bool {
+        if self.contains(&signed_message.transaction.signatures[0]) {
+            return false;
+        }
+
+        self.push(signed_message, now);
+        true
+    }
+
+    /// Hold a transaction which is not known yet for the relayer of another
+    /// peer
+    pub fn hold(&mut self, signed_message: SignedMessage) {
+        if self.contains(&signed_message.transaction.signatures[0]) {
+            return;
+        }
+
+        if self.standby.len() >= MAX_STANDBY_TRANSACTIONS {
+            self.standby.pop_front();
+        }
+        self.standby.push_back(signed_message);
+    }
+
+    /// Record that the transaction is done, it is no longer sent or held
+    pub fn mark_done(&mut self, signature: &Signature) {
+        self.tasks.retain(|task| task.signed_message.transaction.signatures[0] != *signature);
+        self.standby.retain(|standby| standby.transaction.signatures[0] != *signature);
+        if self.done.contains(signature) {
+            return;
+        }
+
+        if self.done.len() >= MAX_DONE_TRANSACTIONS {
+            self.done.pop_front();
+        }
+        self.done.push_back(*signature);
+    }
+
+    /// Queue the transactions on standby to be sent immediately, in held
+    /// order. Return the signatures of the transactions taken over.
+    ///
+    /// Any of them may have been sent by the previous relayer even if its
+    /// `claimed` marker is lost, so they are all checked on chain first
+    pub fn take_over(&mut self, now: Instant) -> Vec<Signature> {
+        self.standby
+            .drain(..)
+            .map(|signed_message| {
+                let signature = signed_message.transaction.signatures[0];
+                self.tasks.push(RelayTask {
+                    signed_message,
+                    attempts: 0,
+                    handed_over: true,
+                    next_attempt_at: now,
+                });
+                signature
+            })
+            .collect()
+    }
+
+    /// Hold the queued transactions for the next relayer, they may have been
+    /// sent by this peer
+    pub fn release(&mut self) {
+        self.standby.extend(self.tasks.drain(..).map(|task| task.signed_message));
+    }
+
+    fn contains(&self, signature: &Signature) -> bool {
+        self.done.contains(signature)
+            || self
+                .tasks
+                .iter()
+                .any(|task| task.signed_message.transaction.signatures[0] == *signature)
+            || self.standby.iter().any(|standby| standby.transaction.signatures[0] == *signature)
+            || self
+                .dead_letters
+                .iter()
+                .any(|(signed_message, _)| signed_message.transaction.signatures[0] == *signature)
+    }
+
+    /// Take the transactions whose backoff has elapsed, in queued order
+    pub fn take_due(&mut self, now: Instant) -> Vec<RelayTask> {
+        let (due, waiting) = self.tasks.drain(..).partition(|task| task.next_attempt_at <= now);
+        self.tasks = waiting;
+        due
+    }
+
+    /// Record a failed attempt, the transaction is retried after its backoff
+    /// if the failure is transient and attempts remain, dead-lettered
+    /// otherwise. Return whether the transaction is dead-lettered
+    pub fn fail(
+        &mut self,
+        mut task: RelayTask,
+        transient: bool,
+        error: String,
+        now: Instant,
+    ) -> bool {
+        task.attempts += 1;
+        if transient && task.attempts < self.config.max_attempts {
+            task.next_attempt_at = now + self.backoff(task.attempts);
+            self.tasks.push(task);
+            return false;
+        }
+
+        let RelayTask { signed_message, attempts, .. } = task;
+        let dead_letter = DeadLetter {
+            signature: signed_message.transaction.signatures[0].to_string(),
+            message_ids: signed_message.message_ids.iter().map(ToString::to_string).collect(),
+            attempts,
+            error,
+            failed_at: Utc::now(),
+        };
+        if let Err(err) = self.store.insert_dead_letter(&signed_message, &dead_letter) {
+            tracing::error!("Fail to store dead letter `{}`: {err}", dead_letter.signature);
+        }
+        self.push_dead_letter(signed_message, dead_letter);
+        true
+    }
+
+    /// Keep the dead letter, the oldest dead letter is dropped from the queue
+    /// and the store if the queue is full
+    fn push_dead_letter(&mut self, signed_message: SignedMessage, dead_letter: DeadLetter) {
+        if self.dead_letters.len() >= MAX_DEAD_LETTERS {
+            if let Some((evicted, _)) = self.dead_letters.pop_front() {
+                let signature = evicted.transaction.signatures[0];
+                tracing::warn!(
+                    "Drop dead letter `{signature}` as more than {MAX_DEAD_LETTERS} transactions \
+                     are dead-lettered"
+                );
+                self.remove_stored_dead_letter(&signature);
+            }
+        }
+        self.dead_letters.push_back((signed_message, dead_letter));
+    }
+
+    fn remove_stored_dead_letter(&self, signature: &Signature) {
+        if let Err(err) = self.store.remove_dead_letter(signature) {
+            tracing::error!("Fail to remove dead letter `{signature}` from store: {err}");
+        }
+    }
+
+    /// Dead letters ordered by the time they failed
+    #[must_use]
+    pub fn dead_letters(&self) -> Vec<DeadLetter> {
+        self.dead_letters.iter().map(|(_, dead_letter)| dead_letter.clone()).collect()
+    }
+
+    /// Move the dead-lettered transaction back to the queue with its attempts
+    /// reset if this peer is the relayer, hold it for the relayer otherwise.
+    /// Return the dead letter if found
+    pub fn replay(
+        &mut self,
+        signature: &Signature,
+        active: bool,
+        now: Instant,
+    ) -> Option<DeadLetter> {
+        let position = self.dead_letters.iter().position(|(signed_message, _)| {
+            signed_message.transaction.signatures[0] == *signature
+        })?;
+        let (signed_message, dead_letter) = self.dead_letters.remove(position)?;
+        self.remove_stored_dead_letter(signature);
+        if active {
+            self.push(signed_message, now);
+        } else {
+            self.hold(signed_message);
+        }
+        Some(dead_letter)
+    }
+
+    fn backoff(&self, attempts: u32) -> Duration {
+        let factor = 2_u32.saturating_pow(attempts.saturating_sub(1));
+        self.config.initial_backoff.saturating_mul(factor).min(self.config.max_backoff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io,
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    use solana_client::client_error::ClientError;
+    use solana_sdk::{
+        hash::Hash,
+        instruction::InstructionError,
+        message::{Message, VersionedMessage},
+        signature::Keypair,
+        signer::Signer as _,
+        system_instruction,
+        transaction::{TransactionError, VersionedTransaction},
+    };
+
+    use super::{RelayFailure, RelayQueue, RelayRetryConfig};
+    use crate::{
+        model::{RawMessage, SignedMessage, TransactionFee},
+        service::{MemoryStore, Store},
+    };
+
+    #[test]
+    fn test_classify() {
+        let cases = [
+            (ClientError::from(TransactionError::AlreadyProcessed), RelayFailure::AlreadyProcessed),
+            (
+                ClientError::from(TransactionError::BlockhashNotFound),
+                RelayFailure::BlockhashNotFound,
+            ),
+            (ClientError::from(TransactionError::AccountInUse), RelayFailure::Transient),
+            (
+                ClientError::from(TransactionError::InstructionError(
+                    0,
+                    InstructionError::Custom(1),
+                )),
+                RelayFailure::Permanent,
+            ),
+            (ClientError::from(TransactionError::InsufficientFundsForFee), RelayFailure::Permanent),
+            (ClientError::from(io::Error::other("connection reset")), RelayFailure::Transient),
+        ];
+        for (err, expected) in cases {
+            assert_eq!(RelayFailure::classify(&err), expected, "{err}");
+        }
+    }
+
+    fn signed_message() -> SignedMessage {
+        let keypair = Keypair::new();
+        let message = VersionedMessage::Legacy(Message::new_with_blockhash(
+            &[system_instruction::transfer(&keypair.pubkey(), &keypair.pubkey(), 1)],
+            Some(&keypair.pubkey()),
+            &Hash::new_unique(),
+        ));
+        SignedMessage {
+            message_ids: vec![RawMessage::new("origin".to_string(), vec![]).id],
+            transaction: VersionedTransaction::try_new(message, &[&keypair]).unwrap(),
+            fee: TransactionFee::default(),
+            signer: "origin".to_string(),
+        }
+    }
+
+    const fn retry_config() -> RelayRetryConfig {
+        RelayRetryConfig {
+            max_attempts: 3,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+
+    #[test]
+    fn test_relay_queue() {
+        let signed_message = signed_message();
+        let signature = signed_message.transaction.signatures[0];
+
+        let store: Arc<dyn Store> = Arc::new(MemoryStore::new());
+        let mut queue = RelayQueue::new(retry_config(), store.clone());
+        let now = Instant::now();
+        queue.push(signed_message, now);
+
+        // transient failures are retried with exponential backoff
+        let task = queue.take_due(now).pop().unwrap();
+        assert!(!queue.fail(task, true, "timeout".to_string(), now));
+        assert!(queue.take_due(now + Duration::from_millis(999)).is_empty());
+        let task = queue.take_due(now + Duration::from_secs(1)).pop().unwrap();
+        assert!(!queue.fail(task, true, "timeout".to_string(), now));
+        assert!(queue.take_due(now + Duration::from_millis(1_999)).is_empty());
+        let task = queue.take_due(now + Duration::from_secs(2)).pop().unwrap();
+        assert_eq!(task.attempts, 2);
+
+        // dead-lettered after max attempts
+        assert!(queue.fail(task, true, "timeout".to_string(), now));
+        assert!(queue.take_due(now + Duration::from_secs(60)).is_empty());
+        let dead_letters = queue.dead_letters();
+        assert_eq!(dead_letters.len(), 1);
+        assert_eq!(dead_letters[0].attempts, 3);
+        assert_eq!(dead_letters[0].signature, signature.to_string());
+        // dead letters are restored from the store
+        assert_eq!(RelayQueue::new(retry_config(), store.clone()).dead_letters().len(), 1);
+
+        // replayed with attempts reset
+        assert!(queue.replay(&signature, true, now).is_some());
+        assert!(queue.dead_letters().is_empty());
+        assert!(store.list_dead_letters().unwrap().is_empty());
+        let task = queue.take_due(now).pop().unwrap();
+        assert_eq!(task.attempts, 0);
+
+        // permanent failures are dead-lettered immediately
+        assert!(queue.fail(task, false, "insufficient funds".to_string(), now));
+        assert_eq!(queue.dead_letters().len(), 1);
+
+        // replayed on a peer which is not the relayer, held for the relayer
+        assert!(queue.replay(&signature, false, now).is_some());
+        assert!(queue.take_due(now).is_empty());
+        assert_eq!(queue.take_over(now), [signature]);
+    }
+
+    #[test]
+    fn test_relayer_fails_in_middle_of_batch() {
+        let batch = [signed_message(), signed_message(), signed_message()];
+        let signatures = batch.iter().map(|sm| sm.transaction.signatures[0]).collect::<Vec<_>>();
+        let now = Instant::now();
+
+        // the relayer claims the batch, the other peer holds it on standby
+        let store: Arc<dyn Store> = Arc::new(MemoryStore::new());
+        let mut relayer = RelayQueue::new(retry_config(), store.clone());
+        let mut standby = RelayQueue::new(retry_config(), store.clone());
+        for signed_message in &batch {
+            assert!(relayer.accept(signed_message.clone(), now));
+            standby.hold(signed_message.clone());
+        }
+        // transactions received again through gossip are not queued twice
+        assert!(!relayer.accept(batch[0].clone(), now));
+
+        // the relayer relays the first transaction and fails while sending
+        // the second one
+        let mut tasks = relayer.take_due(now);
+        assert_eq!(tasks.len(), 3);
+        relayer.mark_done(&tasks.remove(0).signed_message.transaction.signatures[0]);
+        standby.mark_done(&signatures[0]);
+
+        // the newly elected relayer takes over the transactions which are not
+        // done, and checks them on chain as they may have been sent
+        assert_eq!(standby.take_over(now), signatures[1..]);
+        let tasks = standby.take_due(now);
+        assert_eq!(
+            tasks
+                .iter()
+                .map(|task| task.signed_message.transaction.signatures[0])
+                .collect::<Vec<_>>(),
+            signatures[1..]
+        );
+        assert!(tasks.iter().all(|task| task.handed_over && task.attempts == 0));
+
+        // done transactions are not held or sent again
+        standby.hold(batch[0].clone());
+        assert!(!standby.accept(batch[0].clone(), now));
+        assert!(standby.take_over(now).is_empty());
+
+        // transactions which are done by another relayer are dropped from the
+        // queue
+        let mut queue = RelayQueue::new(retry_config(), store);
+        queue.hold(batch[1].clone());
+        assert_eq!(queue.take_over(now), [signatures[1]]);
+        // checked on chain even if its `claimed` marker is not received
+        assert!(queue.take_due(now)[0].handed_over);
+        queue.push(batch[1].clone(), now);
+        queue.mark_done(&signatures[1]);
+        assert!(queue.take_due(now).is_empty());
+
+        // transactions queued by a relayer losing the role are handed over
+        queue.push(batch[2].clone(), now);
+        queue.release();
+        assert!(queue.take_due(now).is_empty());
+        assert_eq!(queue.take_over(now), [signatures[2]]);
+        assert!(queue.take_due(now)[0].handed_over);
+    }
+}
@@ -1,92 +1,213 @@
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use libp2p::PeerId;
-use solana_client::nonblocking::rpc_client::RpcClient;
-use solana_sdk::transaction::Transaction;
-use tokio::sync::{mpsc, RwLock};
+use chrono::Utc;
+use libp2p::{request_response::ResponseChannel, PeerId};
+use solana_client::{
+    client_error::ClientError,
+    nonblocking::rpc_client::RpcClient,
+    rpc_config::{RpcSendTransactionConfig, RpcSimulateTransactionConfig},
+};
+use solana_sdk::{
+    signature::{Keypair as SolanaKeypair, Signature},
+    signer::Signer as _,
+    transaction::VersionedTransaction,
+};
+use tokio::{
+    sync::{mpsc, oneshot, RwLock},
+    time,
+};
 
 use crate::{
-    service::{PeerWorkerInboundEvent, Result},
+    model::{
+        peer_id_to_solana_pubkey, CommitmentStatus, DeadLetter, FeePayerRequest, FeePayerResponse,
+        MessageId, MessageLifecycleEvent, MessageStage, NodeEventKind, PeerResponse, RelayMarker,
+        RelayMarkerState, RelayedTransaction, SignedMessage, SimulationResult,
+    },
+    service::{
+        solana::{
+            confirmation::ConfirmationTrackerInboundEvent,
+            fee_payer::{verify_fee_payer_request, RelayerFeePayerConfig},
+            relay_queue::{RelayFailure, RelayQueue, RelayRetryConfig, RelayTask},
+            BlockhashCache,
+        },
+        EventBroadcaster, PeerWorkerInboundEvent, Result, Store,
+    },
     ShutdownSignal,
 };
 
+const RELAY_RETRY_INTERVAL: Duration = Duration::from_millis(250);
+
 pub enum RelayerInboundEvent {
-    Transaction(Transaction),
+    /// Signed transaction of this peer or gossiped by other peers
+    Transaction(SignedMessage),
+    /// Relay state gossiped by the relayer of another peer
+    Marker(RelayMarker),
+    /// The signer asks this peer to pay the fee of a transaction
+    FeePayerRequest((PeerId, FeePayerRequest, ResponseChannel<PeerResponse>)),
+    ListDeadLetters(oneshot::Sender<Vec<DeadLetter>>),
+    /// Send the dead-lettered transaction again, the dead letter is replied if
+    /// found
+    ReplayDeadLetter((Signature, oneshot::Sender<Option<DeadLetter>>)),
 }
 
 enum Action {
     Stop,
     Inbound(Option<RelayerInboundEvent>),
+    Retry,
 }
 
 pub struct SolanaRelayer {
     peer_id: PeerId,
     relayer: Arc<RwLock<String>>,
+    signer: Arc<RwLock<String>>,
+    keypair: Arc<SolanaKeypair>,
 
     peer_worker_inbound_sender: mpsc::Sender<PeerWorkerInboundEvent>,
+    events: EventBroadcaster,
 
     client: Arc<RpcClient>,
+    blockhash_cache: BlockhashCache,
 
     inbound_receiver: mpsc::Receiver<RelayerInboundEvent>,
+    confirmation_tracker_inbound_sender: mpsc::Sender<ConfirmationTrackerInboundEvent>,
+
+    /// transactions accepted by this relayer are retried until relayed or
+    /// dead-lettered, and handed over if another peer is elected as the relayer
+    queue: RelayQueue,
+    /// whether this peer is the relayer
+    active: bool,
+    /// simulate transactions before sending them, transactions failing the
+    /// simulation are not sent
+    simulate: bool,
+    /// pay the fee of transactions signed by the signer if set
+    fee_payer: Option<RelayerFeePayerConfig>,
 }
 
 impl SolanaRelayer {
     #[must_use]
-    pub const fn new(
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
         peer_id: PeerId,
         relayer: Arc<RwLock<String>>,
+        signer: Arc<RwLock<String>>,
+        keypair: Arc<SolanaKeypair>,
         peer_worker_inbound_sender: mpsc::Sender<PeerWorkerInboundEvent>,
+        events: EventBroadcaster,
         client: Arc<RpcClient>,
+        blockhash_cache: BlockhashCache,
         inbound_receiver: mpsc::Receiver<RelayerInboundEvent>,
+        confirmation_tracker_inbound_sender: mpsc::Sender<ConfirmationTrackerInboundEvent>,
+        store: Arc<dyn Store>,
+        retry: RelayRetryConfig,
+        simulate: bool,
+        fee_payer: Option<RelayerFeePayerConfig>,
     ) -> Self {
-        Self { peer_id, relayer, peer_worker_inbound_sender, client, inbound_receiver }
+        Self {
+            peer_id,
+            relayer,
+            signer,
+            keypair,
+            peer_worker_inbound_sender,
+            events,
+            client,
+            blockhash_cache,
+            inbound_receiver,
+            confirmation_tracker_inbound_sender,
+            queue: RelayQueue::new(retry, store),
+            active: false,
+            simulate,
+            fee_payer,
+        }
     }
 
-    /// # Panics
+    /// Relay signed transactions while this peer is the relayer until
+    /// shutdown, or until the peer worker stops
     ///
-    /// * fail to request airdrop
-    /// * fail to confirm airdrop transaction
+    /// # Errors
+    ///
+    /// It never returns error, failures of relaying are logged
     pub async fn start(mut self, mut shutdown_signal: ShutdownSignal) -> Result<()> {
+        let mut retry_timer = time::interval(RELAY_RETRY_INTERVAL);
         loop {
             let action = tokio::select! {
                 () = shutdown_signal.wait() => Action::Stop,
                 result = self.inbound_receiver.recv() => Action::Inbound(result),
+                _ = retry_timer.tick() => Action::Retry,
             };
 
             match action {
                 Action::Stop => break,
+                Action::Retry => {
+                    if self.sync_role().await.is_err() || self.relay_due().await.is_err() {
+                        break;
+                    }
+                }
                 Action::Inbound(result) => match result {
                     None => {
                         tracing::warn!("`relayer_worker_inbound_receiver` is closed",);
                         break;
                     }
-                    Some(RelayerInboundEvent::Transaction(transaction)) => {
-                        if *self.relayer.read().await == self.peer_id.to_string() {
-                            // Send and confirm the transaction
-                            match self.client.send_and_confirm_transaction(&transaction).await {
-                                Ok(signature) => {
-                                    tracing::debug!("Transaction Signature: {}", signature);
-
-                                    // send relayed transaction to p2p network
-                                    if let Err(err) = self
-                                        .peer_worker_inbound_sender
-                                        .send(PeerWorkerInboundEvent::RelayedTransaction(
-                                            signature.to_string(),
-                                        ))
-                                        .await
-                                    {
-                                        tracing::error!(
-                                            "Fail to send relayed transaction to peer worker: \
-                                             {err}"
-                                        );
-                                        break;
-                                    }
-                                }
-                                Err(err) => {
-                                    tracing::error!("Fail to send transaction to solana: {}", err);
-                                }
+                    Some(RelayerInboundEvent::Transaction(signed_message)) => {
+                        if self.accept(signed_message).await.is_err() {
+                            break;
+                        }
+                    }
+                    Some(RelayerInboundEvent::Marker(marker)) => {
+                        let Ok(signature) = marker.signature.parse::<Signature>() else {
+                            tracing::error!("Invalid signature of relay marker");
+                            continue;
+                        };
+                        // transactions taken over are checked on chain whether
+                        // they are claimed or not, `claimed` markers may be lost
+                        if marker.state == RelayMarkerState::Done {
+                            self.queue.mark_done(&signature);
+                        }
+                    }
+                    Some(RelayerInboundEvent::FeePayerRequest((signer, request, channel))) => {
+                        let response = self.pay_fee(signer, &request).await;
+                        if let Err(err) = self
+                            .peer_worker_inbound_sender
+                            .send(PeerWorkerInboundEvent::Response((
+                                channel,
+                                PeerResponse::FeePayer(response),
+                            )))
+                            .await
+                        {
+                            tracing::error!(
+                                "Fail to send fee payer response to peer worker: {err}"
+                            );
+                            break;
+                        }
+                    }
+                    Some(RelayerInboundEvent::ListDeadLetters(sender)) => {
+                        drop(sender.send(self.queue.dead_letters()));
+                    }
+                    Some(RelayerInboundEvent::ReplayDeadLetter((signature, sender))) => {
+                        if self.sync_role().await.is_err() {
+                            break;
+                        }
+                        let dead_letter =
+                            self.queue.replay(&signature, self.active, Instant::now());
+                        // only the relayer claims and sends the transaction
+                        if dead_letter.is_some() && self.active {
+                            tracing::info!("Replay dead-lettered transaction `{signature}`");
+                            if self
+                                .send_marker(&signature, RelayMarkerState::Claimed)
+                                .await
+                                .is_err()
+                            {
+                                break;
                             }
+                        } else if dead_letter.is_some() {
+                            tracing::info!(
+                                "Hold dead-lettered transaction `{signature}` until this peer is \
+                                 the relayer"
+                            );
                         }
+                        drop(sender.send(dead_letter));
                     }
                 },
             }
@@ -96,4 +217,377 @@ impl SolanaRelayer {
 
         Ok(())
     }
+
+    /// Add the fee payer signature of this peer to the transaction partially
+    /// signed by the signer
+    async fn pay_fee(&self, signer: PeerId, request: &FeePayerRequest) -> FeePayerResponse {
+        let Some(config) = self.fee_payer else {
+            return FeePayerResponse::Rejected("relayer fee payer is disabled".to_string());
+        };
+        if *self.relayer.read().await != self.peer_id.to_string() {
+            return FeePayerResponse::Rejected("this peer is not the relayer".to_string());
+        }
+        if *self.signer.read().await != signer.to_string() {
+            return FeePayerResponse::Rejected("peer is not the signer".to_string());
+        }
+        let Some(signer_pubkey) = peer_id_to_solana_pubkey(&signer) else {
+            return FeePayerResponse::Rejected(format!(
+                "peer `{signer}` does not have ed25519 public key"
+            ));
+        };
+
+        let transaction = &request.transaction;
+        if let Err(reason) = verify_fee_payer_request(
+            transaction,
+            &self.keypair.pubkey(),
+            &signer_pubkey,
+            config.max_fee,
+        ) {
+            tracing::warn!("Reject to pay the fee for {signer}: {reason}");
+            return FeePayerResponse::Rejected(reason);
+        }
+
+        tracing::debug!("Pay the fee of transaction for {signer}");
+        FeePayerResponse::Signed(self.keypair.sign_message(&transaction.message.serialize()))
+    }
+
+    /// Queue the transaction if this peer is the relayer, hold it for the
+    /// relayer of another peer otherwise
+    async fn accept(&mut self, signed_message: SignedMessage) -> Result<(), ()> {
+        self.sync_role().await?;
+        if !self.active {
+            self.queue.hold(signed_message);
+            return Ok(());
+        }
+
+        let signature = signed_message.transaction.signatures[0];
+        if self.queue.accept(signed_message, Instant::now()) {
+            self.send_marker(&signature, RelayMarkerState::Claimed).await?;
+            self.relay_due().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Take over the transactions on standby if this peer is elected as the
+    /// relayer, and hand over the queued transactions if it is no longer the
+    /// relayer
+    async fn sync_role(&mut self) -> Result<(), ()> {
+        let active = *self.relayer.read().await == self.peer_id.to_string();
+        if active == self.active {
+            return Ok(());
+        }
+
+        self.active = active;
+        if !active {
+            tracing::info!("Hand over queued transactions to the next relayer");
+            self.queue.release();
+            return Ok(());
+        }
+
+        let signatures = self.queue.take_over(Instant::now());
+        if !signatures.is_empty() {
+            tracing::info!("Take over {} transactions from the previous relayer", signatures.len());
+        }
+        for signature in signatures {
+            self.send_marker(&signature, RelayMarkerState::Claimed).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Gossip the relay state of the transaction
+    async fn send_marker(&self, signature: &Signature, state: RelayMarkerState) -> Result<(), ()> {
+        let marker = RelayMarker {
+            signature: signature.to_string(),
+            relayer: self.peer_id.to_string(),
+            state,
+        };
+        if let Err(err) =
+            self.peer_worker_inbound_sender.send(PeerWorkerInboundEvent::RelayMarker(marker)).await
+        {
+            tracing::error!("Fail to send relay marker to peer worker: {err}");
+            return Err(());
+        }
+
+        Ok(())
+    }
+
+    /// Record that the transaction is done, so that no relayer sends it again
+    async fn finish(&mut self, signature: &Signature) -> Result<(), ()> {
+        self.queue.mark_done(signature);
+        self.send_marker(signature, RelayMarkerState::Done).await
+    }
+
+    /// Send the queued transactions whose backoff has elapsed if this peer is
+    /// the relayer
+    async fn relay_due(&mut self) -> Result<(), ()> {
+        if !self.active {
+            return Ok(());
+        }
+
+        for task in self.queue.take_due(Instant::now()) {
+            self.relay(task).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Send the transaction signed from the messages. The signer
+    /// signs the messages again if the blockhash has expired, transient
+    /// failures are retried with backoff and the others are dead-lettered
+    async fn relay(&mut self, task: RelayTask) -> Result<(), ()> {
+        let message_ids = task.signed_message.message_ids.clone();
+        let signature = task.signed_message.transaction.signatures[0];
+        // transactions sent by the previous relayer are not sent again
+        if task.handed_over && self.is_landed(&signature).await {
+            tracing::info!("Transaction `{signature}` has been sent by the previous relayer");
+            return self.relayed(task.signed_message).await;
+        }
+
+        if self.blockhash_cache.is_expired(&task.signed_message.transaction).await {
+            tracing::warn!(
+                "Blockhash of transaction `{signature}` has expired, request signing again"
+            );
+            self.finish(&signature).await?;
+            return send_lifecycle_events(
+                &self.peer_worker_inbound_sender,
+                &self.peer_id,
+                &message_ids,
+                MessageStage::Expired,
+                &signature,
+                None,
+            )
+            .await;
+        }
+
+        let result = if self.simulate {
+            match self.simulate(&task.signed_message.transaction).await? {
+                // the preflight check is skipped as the transaction has been simulated
+                None => self
+                    .client
+                    .send_transaction_with_config(
+                        &task.signed_message.transaction,
+                        RpcSendTransactionConfig { skip_preflight: true, ..Default::default() },
+                    )
+                    .await
+                    .map(|_| ())
+                    .map_err(|err| (err, false)),
+                Some(failure) => Err(failure),
+            }
+        } else {
+            self.client
+                .send_transaction(&task.signed_message.transaction)
+                .await
+                .map(|_| ())
+                .map_err(|err| (err, false))
+        };
+
+        // the confirmation is tracked by the confirmation tracker
+        let Err((err, simulated)) = result else {
+            return self.relayed(task.signed_message).await;
+        };
+        self.handle_failure(task, &err, simulated).await
+    }
+
+    /// Handle the failure to simulate or send the transaction
+    async fn handle_failure(
+        &mut self,
+        task: RelayTask,
+        err: &ClientError,
+        simulated: bool,
+    ) -> Result<(), ()> {
+        let message_ids = task.signed_message.message_ids.clone();
+        let signature = task.signed_message.transaction.signatures[0];
+        let failure = RelayFailure::classify(err);
+        match failure {
+            RelayFailure::AlreadyProcessed => {
+                tracing::debug!("Transaction `{signature}` has been processed");
+                self.relayed(task.signed_message).await
+            }
+            RelayFailure::BlockhashNotFound => {
+                tracing::warn!(
+                    "Blockhash of transaction `{signature}` is not found, request signing again"
+                );
+                self.finish(&signature).await?;
+                send_lifecycle_events(
+                    &self.peer_worker_inbound_sender,
+                    &self.peer_id,
+                    &message_ids,
+                    MessageStage::Expired,
+                    &signature,
+                    None,
+                )
+                .await
+            }
+            // transactions failing the simulation are rejected without paying fees
+            RelayFailure::Permanent if simulated => {
+                tracing::error!("Transaction `{signature}` fails the simulation: {err}");
+                self.finish(&signature).await?;
+                send_lifecycle_events(
+                    &self.peer_worker_inbound_sender,
+                    &self.peer_id,
+                    &message_ids,
+                    MessageStage::SimulationFailed,
+                    &signature,
+                    Some(&err.to_string()),
+                )
+                .await
+            }
+            RelayFailure::Transient | RelayFailure::Permanent => {
+                tracing::warn!(
+                    "Fail to send transaction `{signature}` to solana in attempt {}: {err}",
+                    task.attempts + 1
+                );
+                let transient = failure == RelayFailure::Transient;
+                if !self.queue.fail(task, transient, err.to_string(), Instant::now()) {
+                    return Ok(());
+                }
+
+                tracing::error!("Transaction `{signature}` is dead-lettered: {err}");
+                self.finish(&signature).await?;
+                send_lifecycle_events(
+                    &self.peer_worker_inbound_sender,
+                    &self.peer_id,
+                    &message_ids,
+                    MessageStage::DeadLettered,
+                    &signature,
+                    Some(&err.to_string()),
+                )
+                .await
+            }
+        }
+    }
+
+    /// Simulate the transaction and record the result on peer worker, return
+    /// the error if the simulation fails
+    async fn simulate(
+        &self,
+        transaction: &VersionedTransaction,
+    ) -> Result<Option<(ClientError, bool)>, ()> {
+        let config = RpcSimulateTransactionConfig { sig_verify: true, ..Default::default() };
+        let simulation =
+            match self.client.simulate_transaction_with_config(transaction, config).await {
+                Ok(response) => response.value,
+                // failures to request the simulation are handled like failures to send
+                Err(err) => return Ok(Some((err, false))),
+            };
+
+        let result = SimulationResult {
+            logs: simulation.logs.unwrap_or_default(),
+            units_consumed: simulation.units_consumed,
+            error: simulation.err.as_ref().map(ToString::to_string),
+            simulated_at: Utc::now(),
+        };
+        if let Err(err) = self
+            .peer_worker_inbound_sender
+            .send(PeerWorkerInboundEvent::Simulation((transaction.clone(), result)))
+            .await
+        {
+            tracing::error!("Fail to send simulation result to peer worker: {err}");
+            return Err(());
+        }
+
+        Ok(simulation.err.map(|err| (ClientError::from(err), true)))
+    }
+
+    /// Whether the transaction is found on chain, transactions which fail to be
+    /// checked are sent again as sending a transaction twice does not land it
+    /// twice
+    async fn is_landed(&self, signature: &Signature) -> bool {
+        match self.client.get_signature_statuses(&[*signature]).await {
+            Ok(response) => response.value.first().is_some_and(Option::is_some),
+            Err(err) => {
+                tracing::warn!("Fail to get status of transaction `{signature}`: {err}");
+                false
+            }
+        }
+    }
+
+    /// Report the sent transaction and track its confirmation
+    async fn relayed(&mut self, signed_message: SignedMessage) -> Result<(), ()> {
+        let SignedMessage { message_ids, transaction, fee, signer } = signed_message;
+        let signature = transaction.signatures[0];
+        tracing::debug!("Transaction Signature: {}", signature);
+        self.finish(&signature).await?;
+
+        send_lifecycle_events(
+            &self.peer_worker_inbound_sender,
+            &self.peer_id,
+            &message_ids,
+            MessageStage::Relayed,
+            &signature,
+            None,
+        )
+        .await?;
+
+        let relayed_transaction = RelayedTransaction {
+            signature: signature.to_string(),
+            message_ids: message_ids.iter().map(ToString::to_string).collect(),
+            signer,
+            relayer: self.peer_id.to_string(),
+            slot: None,
+            submitted_at: Utc::now(),
+            confirmed_at: None,
+            status: CommitmentStatus::Submitted,
+            fee: fee.signature_fee.saturating_add(fee.priority_fee),
+            error: None,
+        };
+        self.events.publish(NodeEventKind::TransactionRelayed(relayed_transaction.clone()));
+        if let Err(err) = self
+            .confirmation_tracker_inbound_sender
+            .send(ConfirmationTrackerInboundEvent::Track((
+                message_ids,
+                transaction,
+                relayed_transaction.clone(),
+            )))
+            .await
+        {
+            tracing::error!("Fail to send transaction to confirmation tracker: {err}");
+            return Err(());
+        }
+
+        // send relayed transaction to p2p network
+        if let Err(err) = self
+            .peer_worker_inbound_sender
+            .send(PeerWorkerInboundEvent::RelayedTransaction(relayed_transaction))
+            .await
+        {
+            tracing::error!("Fail to send relayed transaction to peer worker: {err}");
+            return Err(());
+        }
+
+        Ok(())
+    }
+}
+
+/// Send lifecycle events of the messages signed in the transaction to peer
+/// worker
+pub(super) async fn send_lifecycle_events(
+    peer_worker_inbound_sender: &mpsc::Sender<PeerWorkerInboundEvent>,
+    peer_id: &PeerId,
+    message_ids: &[MessageId],
+    stage: MessageStage,
+    signature: &Signature,
+    reason: Option<&str>,
+) -> Result<(), ()> {
+    for message_id in message_ids {
+        let mut event = MessageLifecycleEvent::new(
+            *message_id,
+            stage,
+            peer_id.to_string(),
+            Some(signature.to_string()),
+        );
+        if let Some(reason) = reason {
+            event = event.with_reason(reason.to_string());
+        }
+        if let Err(err) =
+            peer_worker_inbound_sender.send(PeerWorkerInboundEvent::Lifecycle(event)).await
+        {
+            tracing::error!("Fail to send lifecycle event to peer worker: {err}");
+            return Err(());
+        }
+    }
+
+    Ok(())
 }
@@ -0,0 +1,280 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use futures_util::future;
+use serde_json::Value;
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind, Result as ClientResult},
+    nonblocking::rpc_client::RpcClient,
+    rpc_custom_error::JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY,
+    rpc_request::{RpcError, RpcRequest},
+    rpc_sender::{RpcSender, RpcTransportStats},
+};
+use solana_sdk::commitment_config::CommitmentConfig;
+use tokio::time;
+use url::Url;
+
+use crate::{metrics, service::Result, ShutdownSignal};
+
+/// Solana RPC endpoints used by the Solana client
+#[derive(Clone, Debug)]
+pub struct RpcPoolConfig {
+    /// endpoints of requests other than sending transactions, in order of
+    /// preference
+    pub read_urls: Vec<String>,
+    /// endpoints of sending transactions in order of preference, the read
+    /// endpoints are used if empty
+    pub send_urls: Vec<String>,
+    pub health_check_interval: Duration,
+    /// endpoints lagging behind the highest slot of all endpoints by more
+    /// slots are unhealthy
+    pub max_slot_lag: u64,
+}
+
+struct RpcEndpoint {
+    /// origin of the URL, the path and the query which may contain API keys
+    /// are not logged
+    name: String,
+    client: RpcClient,
+    /// endpoints are healthy until a health check or a request fails
+    healthy: AtomicBool,
+}
+
+impl RpcEndpoint {
+    fn new(url: &str) -> Self {
+        let name = Url::parse(url)
+            .map_or_else(|_| "invalid".to_string(), |url| url.origin().ascii_serialization());
+        metrics::RPC_ENDPOINT_HEALTHY.with_label_values(&[&name]).set(1);
+        Self {
+            name,
+            client: RpcClient::new_with_commitment(url.to_string(), CommitmentConfig::confirmed()),
+            healthy: AtomicBool::new(true),
+        }
+    }
+
+    fn is_healthy(&self) -> bool { self.healthy.load(Ordering::Relaxed) }
+
+    fn set_healthy(&self, healthy: bool) {
+        if self.healthy.swap(healthy, Ordering::Relaxed) != healthy {
+            if healthy {
+                tracing::info!("RPC endpoint `{}` is healthy", self.name);
+            } else {
+                tracing::warn!("RPC endpoint `{}` is unhealthy", self.name);
+            }
+        }
+        metrics::RPC_ENDPOINT_HEALTHY.with_label_values(&[&self.name]).set(i64::from(healthy));
+    }
+
+    /// The processed slot of the endpoint if it reports healthy
+    async fn check(&self) -> Option<u64> {
+        if let Err(err) = self.client.get_health().await {
+            tracing::warn!("Health check of RPC endpoint `{}` fails: {err}", self.name);
+            return None;
+        }
+
+        match self.client.get_slot_with_commitment(CommitmentConfig::processed()).await {
+            Ok(slot) => Some(slot),
+            Err(err) => {
+                tracing::warn!("Fail to get slot of RPC endpoint `{}`: {err}", self.name);
+                None
+            }
+        }
+    }
+}
+
+/// Transport of the Solana client over multiple RPC endpoints
+///
+/// Transactions are sent through the send endpoints and the other requests
+/// through the read endpoints, requests fail over to the next endpoint if the
+/// endpoint is unreachable or unhealthy. Healthy endpoints are tried first, and
+/// the health of endpoints is checked in background
+#[derive(Clone)]
+pub struct RpcPool {
+    read_endpoints: Arc<Vec<RpcEndpoint>>,
+    send_endpoints: Arc<Vec<RpcEndpoint>>,
+    health_check_interval: Duration,
+    max_slot_lag: u64,
+}
+
+impl RpcPool {
+    #[must_use]
+    pub fn new(config: RpcPoolConfig) -> Self {
+        let RpcPoolConfig { read_urls, send_urls, health_check_interval, max_slot_lag } = config;
+        Self {
+            read_endpoints: Arc::new(read_urls.iter().map(|url| RpcEndpoint::new(url)).collect()),
+            send_endpoints: Arc::new(send_urls.iter().map(|url| RpcEndpoint::new(url)).collect()),
+            health_check_interval,
+            max_slot_lag,
+        }
+    }
+
+    /// Check the health of endpoints until shutdown
+    ///
+    /// # Errors
+    ///
+    /// It never returns error, failures of health checks are logged
+    pub async fn start(self, mut shutdown_signal: ShutdownSignal) -> Result<()> {
+        let mut health_check_timer = time::interval(self.health_check_interval);
+        loop {
+            tokio::select! {
+                () = shutdown_signal.wait() => break,
+                _ = health_check_timer.tick() => self.check_health().await,
+            }
+        }
+
+        tracing::warn!("RPC pool stopped.");
+
+        Ok(())
+    }
+
+    /// Endpoints reporting unhealthy, failing to report their slot or lagging
+    /// behind the highest slot are marked unhealthy
+    async fn check_health(&self) {
+        let endpoints =
+            self.read_endpoints.iter().chain(self.send_endpoints.iter()).collect::<Vec<_>>();
+        let slots = future::join_all(endpoints.iter().map(|endpoint| endpoint.check())).await;
+        let highest_slot = slots.iter().flatten().max().copied().unwrap_or_default();
+        for (endpoint, slot) in endpoints.into_iter().zip(slots) {
+            let Some(slot) = slot else {
+                endpoint.set_healthy(false);
+                continue;
+            };
+
+            let lag = highest_slot - slot;
+            metrics::RPC_ENDPOINT_SLOT_LAG
+                .with_label_values(&[&endpoint.name])
+                .set(i64::try_from(lag).unwrap_or(i64::MAX));
+            if lag > self.max_slot_lag {
+                tracing::warn!("RPC endpoint `{}` lags behind by {lag} slots", endpoint.name);
+            }
+            endpoint.set_healthy(lag <= self.max_slot_lag);
+        }
+    }
+
+    fn endpoints(&self, request: RpcRequest) -> &[RpcEndpoint] {
+        if request == RpcRequest::SendTransaction && !self.send_endpoints.is_empty() {
+            &self.send_endpoints
+        } else {
+            &self.read_endpoints
+        }
+    }
+}
+
+#[async_trait]
+impl RpcSender for RpcPool {
+    async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+        let endpoints = self.endpoints(request);
+        let method = request.to_string();
+        let healthy = endpoints.iter().map(RpcEndpoint::is_healthy).collect::<Vec<_>>();
+        let mut last_err = None;
+        for index in failover_order(&healthy) {
+            let endpoint = &endpoints[index];
+            let started_at = Instant::now();
+            let result = endpoint.client.send::<Value>(request, params.clone()).await;
+            metrics::RPC_REQUEST_DURATION
+                .with_label_values(&[&endpoint.name, &method])
+                .observe(started_at.elapsed().as_secs_f64());
+
+            let err = match result {
+                Ok(response) => return Ok(response),
+                Err(err) => err,
+            };
+            metrics::RPC_REQUEST_ERRORS.with_label_values(&[&endpoint.name, &method]).inc();
+            if !is_endpoint_unavailable(&err) {
+                return Err(err);
+            }
+
+            tracing::warn!("Request `{method}` to RPC endpoint `{}` fails: {err}", endpoint.name);
+            endpoint.set_healthy(false);
+            last_err = Some(err);
+        }
+
+        Err(last_err
+            .unwrap_or_else(|| ClientErrorKind::Custom("no RPC endpoint".to_string()).into()))
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.read_endpoints.iter().chain(self.send_endpoints.iter()).fold(
+            RpcTransportStats::default(),
+            |mut stats, endpoint| {
+                let endpoint_stats = endpoint.client.get_transport_stats();
+                stats.request_count += endpoint_stats.request_count;
+                stats.elapsed_time += endpoint_stats.elapsed_time;
+                stats.rate_limited_time += endpoint_stats.rate_limited_time;
+                stats
+            },
+        )
+    }
+
+    fn url(&self) -> String {
+        self.read_endpoints.first().map(|endpoint| endpoint.name.clone()).unwrap_or_default()
+    }
+}
+
+/// Indexes of endpoints in the order they are tried, healthy endpoints are
+/// tried first and unhealthy endpoints are the last resort
+fn failover_order(healthy: &[bool]) -> Vec<usize> {
+    let (mut order, unhealthy): (Vec<_>, Vec<_>) =
+        (0..healthy.len()).partition(|index| healthy[*index]);
+    order.extend(unhealthy);
+    order
+}
+
+/// Whether the endpoint is unavailable and the request may succeed on another
+/// endpoint, errors replied by a healthy endpoint are returned as they are
+pub fn is_endpoint_unavailable(err: &ClientError) -> bool {
+    match err.kind() {
+        ClientErrorKind::Io(_) | ClientErrorKind::Reqwest(_) | ClientErrorKind::Middleware(_) => {
+            true
+        }
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) => {
+            *code == JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use solana_client::{
+        client_error::ClientError,
+        rpc_custom_error::JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY,
+        rpc_request::{RpcError, RpcResponseErrorData},
+    };
+    use solana_sdk::transaction::TransactionError;
+
+    use super::{failover_order, is_endpoint_unavailable};
+
+    #[test]
+    fn test_failover_order() {
+        assert_eq!(failover_order(&[true, false, true]), [0, 2, 1]);
+        assert_eq!(failover_order(&[false, false]), [0, 1]);
+        assert!(failover_order(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_is_endpoint_unavailable() {
+        let response_error = |code| {
+            ClientError::from(RpcError::RpcResponseError {
+                code,
+                message: String::new(),
+                data: RpcResponseErrorData::Empty,
+            })
+        };
+
+        assert!(is_endpoint_unavailable(&ClientError::from(io::Error::other(
+            "connection refused"
+        ))));
+        assert!(is_endpoint_unavailable(&response_error(JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY)));
+        assert!(!is_endpoint_unavailable(&response_error(-32602)));
+        assert!(!is_endpoint_unavailable(&ClientError::from(TransactionError::AlreadyProcessed)));
+    }
+}
@@ -1,72 +1,300 @@
-use std::sync::Arc;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Arc,
+    time::Duration,
+};
 
-use bytes::Bytes;
-use libp2p::PeerId;
+use async_trait::async_trait;
+use futures_util::future;
+use libp2p::{request_response::ResponseChannel, PeerId};
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{
-    instruction::Instruction, pubkey::Pubkey, signature::Keypair as SolanaKeypair,
-    signer::Signer as _, transaction::Transaction,
+    address_lookup_table::AddressLookupTableAccount,
+    hash::Hash,
+    instruction::Instruction,
+    message::{v0, CompileError, Message, VersionedMessage},
+    packet::PACKET_DATA_SIZE,
+    pubkey::Pubkey,
+    signature::{Keypair as SolanaKeypair, Signature},
+    signer::Signer as _,
+    system_instruction,
+    transaction::{Transaction, VersionedTransaction},
+};
+use tokio::{
+    sync::{mpsc, oneshot, Mutex, RwLock},
+    time,
+    time::Instant,
 };
-use tokio::sync::{mpsc, RwLock};
 
 use crate::{
-    service::{PeerWorkerInboundEvent, Result},
+    metrics,
+    model::{
+        peer_id_to_solana_pubkey, CosignRequest, CosignResponse, FeePayerRequest, FeePayerResponse,
+        FrostRequest, FrostResponse, MessageId, MessageLifecycleEvent, MessageStage, NodeEventKind,
+        PeerRequest, PeerResponse, RawMessage, SignedMessage, TransactionSigned,
+    },
+    service::{
+        frost::{self, FrostConfig, FrostParticipant, FrostTransport},
+        seen_cache::SeenCache,
+        solana::{
+            batch::{self, Batch, BatchConfig},
+            compute_budget::{priority_fee, transaction_fee},
+            fee_payer::{require_signer, RelayerFeePayerConfig},
+            multisig::{build_multisig_message, verify_cosign_request},
+            BlockhashCache, ComputeBudget, ComputeBudgetConfig, DurableNonce, FundingConfig,
+            InstructionBuilder, InstructionSigner, MultisigConfig, SigningPolicy,
+            SigningPolicyConfig, WalletFunding,
+        },
+        EventBroadcaster, PeerWorkerInboundEvent, Result,
+    },
     ShutdownSignal,
 };
 
+const SEEN_CACHE_CAPACITY: usize = 10_000;
+const SEEN_CACHE_TTL: Duration = Duration::from_secs(600);
+const SIGN_PENDING_INTERVAL: Duration = Duration::from_millis(500);
+const MIN_SIGN_PENDING_INTERVAL: Duration = Duration::from_millis(10);
+
 pub enum SignerInboundEvent {
-    RawMessage(Bytes),
+    RawMessage(RawMessage),
+    /// The message has been signed by a signer in the p2p network
+    MessageSigned(MessageId),
+    /// The blockhash of the signed transaction has expired before being
+    /// relayed, the message must be signed again
+    MessageExpired(MessageId),
+    /// The message has been rejected by a signer in the p2p network, it is
+    /// never signed
+    MessageRejected(MessageId),
+    /// The multisig coordinator asks this peer to co-sign a transaction
+    CosignRequest((PeerId, CosignRequest, ResponseChannel<PeerResponse>)),
+    /// The FROST coordinator sends a request to this peer
+    FrostRequest((PeerId, FrostRequest, ResponseChannel<PeerResponse>)),
+    /// Run FROST distributed key generation among connected peers
+    StartDkg,
 }
 
 enum Action {
     Stop,
     Inbound(Option<SignerInboundEvent>),
+    SignPending,
+}
+
+struct PendingMessage {
+    message: RawMessage,
+    received_at: Instant,
+    /// signing rounds which find not enough co-signers in multisig mode
+    unsigned_attempts: usize,
+}
+
+impl PendingMessage {
+    fn new(message: RawMessage) -> Self {
+        Self { message, received_at: Instant::now(), unsigned_attempts: 0 }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum SignOutcome {
+    Signed,
+    /// The message can not be signed now, retry later
+    Retry,
+    /// Not enough co-signers are available, labeled by the reason. Retry
+    /// until the attempts run out
+    Unsigned(&'static str),
+    /// The message can never be signed
+    Rejected,
+}
+
+enum CosignResult {
+    Signed(Signature),
+    Rejected(String),
+    Timeout,
+    Failed,
+}
+
+struct Frost {
+    config: FrostConfig,
+    participant: Mutex<FrostParticipant>,
+}
+
+/// Deliver FROST requests to peers over the request-response protocol, requests
+/// to this peer are handled locally
+struct FrostPeerTransport<'a> {
+    signer: &'a SolanaSigner,
+    timeout: Duration,
+}
+
+#[async_trait]
+impl FrostTransport for FrostPeerTransport<'_> {
+    async fn request(&self, peer_id: PeerId, request: FrostRequest) -> Option<FrostResponse> {
+        if peer_id == self.signer.peer_id {
+            let frost = self.signer.frost.as_ref()?;
+            return Some(frost.participant.lock().await.handle(peer_id, request));
+        }
+
+        let (sender, receiver) = oneshot::channel();
+        if let Err(err) = self
+            .signer
+            .peer_worker_inbound_sender
+            .send(PeerWorkerInboundEvent::Request((peer_id, PeerRequest::Frost(request), sender)))
+            .await
+        {
+            tracing::error!("Fail to send FROST request to peer worker: {err}");
+            return None;
+        }
+
+        match time::timeout(self.timeout, receiver).await {
+            Ok(Ok(Some(PeerResponse::Frost(response)))) => Some(response),
+            _ => None,
+        }
+    }
+}
+
+impl CosignResult {
+    const fn as_label(&self) -> &'static str {
+        match self {
+            Self::Signed(_) => "signed",
+            Self::Rejected(_) => "rejected",
+            Self::Timeout => "timeout",
+            Self::Failed => "failed",
+        }
+    }
 }
 
 pub struct SolanaSigner {
     peer_id: PeerId,
     signer: Arc<RwLock<String>>,
+    relayer: Arc<RwLock<String>>,
     keypair: Arc<SolanaKeypair>,
+    peers: Arc<RwLock<Vec<String>>>,
 
     peer_worker_inbound_sender: mpsc::Sender<PeerWorkerInboundEvent>,
+    events: EventBroadcaster,
 
-    program_id: Pubkey,
+    instruction_builder: Arc<dyn InstructionBuilder>,
     client: Arc<RpcClient>,
 
     inbound_receiver: mpsc::Receiver<SignerInboundEvent>,
+
+    /// messages which are not signed yet, taken over by the newly elected
+    /// signer
+    pending_messages: VecDeque<PendingMessage>,
+    seen_messages: SeenCache<MessageId>,
+    signed_messages: SeenCache<MessageId>,
+    /// signed messages which are kept until relayed, so that they can be
+    /// signed again if the blockhash expires
+    relaying_messages: HashMap<MessageId, (RawMessage, Instant)>,
+
+    /// delay before taking over pending messages received before being elected,
+    /// so that signed events from the previous signer can arrive
+    takeover_delay: Duration,
+    leader_since: Option<Instant>,
+
+    /// sign transactions with co-signers if multisig is enabled
+    multisig: Option<MultisigConfig>,
+    /// sign transactions with FROST key shares if FROST is enabled
+    frost: Option<Frost>,
+    /// fund the FROST group public key after distributed key generation
+    funding: FundingConfig,
+    /// messages violating the policy are rejected
+    policy: Mutex<SigningPolicy>,
+    /// sign transactions against durable nonces if set, recent blockhashes
+    /// otherwise
+    durable_nonce: Option<Mutex<DurableNonce>>,
+    blockhash_cache: BlockhashCache,
+    /// sign v0 transactions with the address lookup tables if set, legacy
+    /// transactions otherwise
+    address_lookup_tables: Option<Vec<AddressLookupTableAccount>>,
+    compute_budget: ComputeBudget,
+    /// pack pending messages into one transaction if set
+    batch: Option<BatchConfig>,
+    /// the relayer pays the fee of signed transactions if set, this peer
+    /// otherwise
+    relayer_fee_payer: Option<RelayerFeePayerConfig>,
 }
 
 impl SolanaSigner {
     #[must_use]
-    pub const fn new(
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
         peer_id: PeerId,
         signer: Arc<RwLock<String>>,
+        relayer: Arc<RwLock<String>>,
         keypair: Arc<SolanaKeypair>,
+        peers: Arc<RwLock<Vec<String>>>,
         peer_worker_inbound_sender: mpsc::Sender<PeerWorkerInboundEvent>,
-        program_id: Pubkey,
+        events: EventBroadcaster,
+        instruction_builder: Arc<dyn InstructionBuilder>,
         client: Arc<RpcClient>,
         inbound_receiver: mpsc::Receiver<SignerInboundEvent>,
+        takeover_delay: Duration,
+        multisig: Option<MultisigConfig>,
+        frost: Option<FrostConfig>,
+        funding: FundingConfig,
+        policy: SigningPolicyConfig,
+        durable_nonce: Option<DurableNonce>,
+        blockhash_cache: BlockhashCache,
+        address_lookup_tables: Option<Vec<AddressLookupTableAccount>>,
+        compute_budget: ComputeBudgetConfig,
+        batch: Option<BatchConfig>,
+        relayer_fee_payer: Option<RelayerFeePayerConfig>,
     ) -> Self {
+        let frost = frost.map(|config| {
+            let participant = FrostParticipant::new(
+                peer_id,
+                keypair.clone(),
+                instruction_builder.clone(),
+                Some(config.key_share_dir.clone()),
+                config.allow_rekey,
+            );
+            Frost { config, participant: Mutex::new(participant) }
+        });
+
         Self {
             peer_id,
             signer,
+            relayer,
             keypair,
+            peers,
             peer_worker_inbound_sender,
-            program_id,
+            events,
+            instruction_builder,
+            compute_budget: ComputeBudget::new(compute_budget, client.clone()),
             client,
             inbound_receiver,
+            pending_messages: VecDeque::new(),
+            seen_messages: SeenCache::new(SEEN_CACHE_CAPACITY, SEEN_CACHE_TTL),
+            signed_messages: SeenCache::new(SEEN_CACHE_CAPACITY, SEEN_CACHE_TTL),
+            relaying_messages: HashMap::new(),
+            takeover_delay,
+            leader_since: None,
+            multisig,
+            frost,
+            funding,
+            policy: Mutex::new(SigningPolicy::new(policy)),
+            durable_nonce: durable_nonce.map(Mutex::new),
+            blockhash_cache,
+            address_lookup_tables,
+            batch,
+            relayer_fee_payer,
         }
     }
 
-    /// # Panics
+    /// Sign pending messages while this peer is the signer until shutdown, or
+    /// until the peer worker stops
+    ///
+    /// # Errors
     ///
-    /// * fail to request airdrop
-    /// * fail to confirm airdrop transaction
+    /// It never returns error, failures of signing are logged
     pub async fn start(mut self, mut shutdown_signal: ShutdownSignal) -> Result<()> {
+        let mut sign_pending_timer =
+            time::interval(self.batch.map_or(SIGN_PENDING_INTERVAL, |batch| {
+                batch.max_delay.clamp(MIN_SIGN_PENDING_INTERVAL, SIGN_PENDING_INTERVAL)
+            }));
         loop {
             let action = tokio::select! {
                 () = shutdown_signal.wait() => Action::Stop,
                 result = self.inbound_receiver.recv() => Action::Inbound(result),
+                _ = sign_pending_timer.tick() => Action::SignPending,
             };
 
             match action {
@@ -77,45 +305,72 @@ impl SolanaSigner {
                         break;
                     }
                     Some(SignerInboundEvent::RawMessage(raw_message)) => {
-                        if *self.signer.read().await == self.peer_id.to_string() {
-                            let message = String::from_utf8_lossy(&raw_message);
-                            let data = format!("{message}, Signer: {}", self.peer_id);
-
-                            // Create the instruction
-                            let instruction = Instruction::new_with_borsh(
-                                self.program_id,
-                                &data,
-                                vec![], // No accounts needed
-                            );
-
-                            // Add the instruction to new transaction
-                            let mut transaction = Transaction::new_with_payer(
-                                &[instruction],
-                                Some(&self.keypair.pubkey()),
-                            );
-
-                            match self.client.get_latest_blockhash().await {
-                                Ok(recent_blockhash) => {
-                                    transaction.sign(&[&self.keypair], recent_blockhash);
-                                }
-                                Err(err) => {
-                                    tracing::error!("Fail to get recent blockhash: {err}");
-                                    continue;
-                                }
-                            }
-
-                            // send transaction to p2p network
-                            if let Err(err) = self
-                                .peer_worker_inbound_sender
-                                .send(PeerWorkerInboundEvent::Transaction(transaction))
-                                .await
-                            {
-                                tracing::error!("Fail to send transaction to peer worker: {err}");
-                                break;
-                            }
+                        if !self.seen_messages.insert(raw_message.id) {
+                            tracing::debug!("Ignore duplicated message `{}`", raw_message.id);
+                            continue;
+                        }
+                        if self.signed_messages.contains(&raw_message.id) {
+                            tracing::debug!("Ignore signed message `{}`", raw_message.id);
+                            continue;
+                        }
+
+                        self.pending_messages.push_back(PendingMessage::new(raw_message));
+                        if self.sign_pending_messages().await.is_err() {
+                            break;
+                        }
+                    }
+                    Some(SignerInboundEvent::MessageSigned(message_id)) => {
+                        if let Some(message) = self.settle_pending(message_id) {
+                            self.mark_signed(message);
+                        }
+                    }
+                    Some(SignerInboundEvent::MessageRejected(message_id)) => {
+                        if self.settle_pending(message_id).is_some() {
+                            tracing::info!("Drop pending message `{message_id}` which is rejected");
+                        }
+                    }
+                    Some(SignerInboundEvent::MessageExpired(message_id)) => {
+                        if let Some((message, _)) = self.relaying_messages.remove(&message_id) {
+                            tracing::info!("Sign expired message `{message_id}` again");
+                            self.signed_messages.remove(&message_id);
+                            self.pending_messages.push_back(PendingMessage::new(message));
+                        }
+                    }
+                    Some(SignerInboundEvent::CosignRequest((coordinator, request, channel))) => {
+                        let response = self.cosign(coordinator, &request).await;
+                        if let Err(err) = self
+                            .peer_worker_inbound_sender
+                            .send(PeerWorkerInboundEvent::Response((
+                                channel,
+                                PeerResponse::Cosign(response),
+                            )))
+                            .await
+                        {
+                            tracing::error!("Fail to send co-sign response to peer worker: {err}");
+                            break;
+                        }
+                    }
+                    Some(SignerInboundEvent::FrostRequest((coordinator, request, channel))) => {
+                        let response = self.handle_frost_request(coordinator, request).await;
+                        if let Err(err) = self
+                            .peer_worker_inbound_sender
+                            .send(PeerWorkerInboundEvent::Response((
+                                channel,
+                                PeerResponse::Frost(response),
+                            )))
+                            .await
+                        {
+                            tracing::error!("Fail to send FROST response to peer worker: {err}");
+                            break;
                         }
                     }
+                    Some(SignerInboundEvent::StartDkg) => self.start_dkg().await,
                 },
+                Action::SignPending => {
+                    if self.sign_pending_messages().await.is_err() {
+                        break;
+                    }
+                }
             }
         }
 
@@ -123,4 +378,845 @@ impl SolanaSigner {
 
         Ok(())
     }
+
+    /// Sign pending messages if this peer is the signer, messages which fail to
+    /// be signed are kept for next round. Messages are packed into batches if
+    /// batching is enabled
+    async fn sign_pending_messages(&mut self) -> Result<(), ()> {
+        if *self.signer.read().await != self.peer_id.to_string() {
+            self.leader_since = None;
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        let leader_since = *self.leader_since.get_or_insert(now);
+        let taking_over = now.duration_since(leader_since) < self.takeover_delay;
+
+        // wait for signed events of messages received before being elected
+        let (mut remaining, ready): (VecDeque<_>, VecDeque<_>) = self
+            .pending_messages
+            .drain(..)
+            .partition(|pending| taking_over && pending.received_at < leader_since);
+        if ready.is_empty() {
+            self.pending_messages = remaining;
+            return Ok(());
+        }
+
+        // wait for more messages until the batch is full or the oldest message
+        // has waited long enough
+        if let Some(batch) = self.batch {
+            let full = ready.len() >= batch.max_messages;
+            let due = ready
+                .iter()
+                .any(|pending| now.duration_since(pending.received_at) >= batch.max_delay);
+            if !full && !due {
+                remaining.extend(ready);
+                self.pending_messages = remaining;
+                return Ok(());
+            }
+        }
+
+        let Ok(outcomes) =
+            self.sign_messages(ready.iter().map(|pending| &pending.message).collect()).await
+        else {
+            remaining.extend(ready);
+            self.pending_messages = remaining;
+            return Err(());
+        };
+        for mut pending in ready {
+            match outcomes.get(&pending.message.id) {
+                Some(SignOutcome::Signed) => self.mark_signed(pending.message),
+                Some(SignOutcome::Retry) | None => remaining.push_back(pending),
+                Some(SignOutcome::Unsigned(reason)) => {
+                    pending.unsigned_attempts += 1;
+                    let max_attempts = self.multisig.map_or(0, |multisig| multisig.max_attempts);
+                    if pending.unsigned_attempts < max_attempts {
+                        remaining.push_back(pending);
+                        continue;
+                    }
+
+                    metrics::MULTISIG_UNSIGNED_MESSAGES.with_label_values(&[reason]).inc();
+                    let reason = format!("not enough co-signers after {max_attempts} attempts");
+                    self.publish_rejected(&pending.message, reason).await?;
+                }
+                Some(SignOutcome::Rejected) => {}
+            }
+        }
+        self.pending_messages = remaining;
+
+        Ok(())
+    }
+
+    fn settle_pending(&mut self, message_id: MessageId) -> Option<RawMessage> {
+        settle_pending(&mut self.pending_messages, &mut self.signed_messages, message_id)
+    }
+
+    /// Keep the signed message until it is relayed
+    fn mark_signed(&mut self, message: RawMessage) {
+        let now = Instant::now();
+        self.signed_messages.insert(message.id);
+        self.relaying_messages
+            .retain(|_, (_, signed_at)| now.duration_since(*signed_at) < SEEN_CACHE_TTL);
+        self.relaying_messages.insert(message.id, (message, now));
+    }
+
+    /// The fee payer of signed transactions, the group public key in FROST
+    /// mode and the relayer in relayer fee payer mode. Return `None` if FROST
+    /// key share is not generated or the relayer is not elected yet
+    async fn fee_payer(&self) -> Option<Pubkey> {
+        if self.relayer_fee_payer.is_some() {
+            return self.relayer_pubkey().await.map(|(_, pubkey)| pubkey);
+        }
+        let Some(frost) = &self.frost else {
+            return Some(self.keypair.pubkey());
+        };
+        let key_package = frost.participant.lock().await.key_package().cloned();
+        if key_package.is_none() {
+            tracing::warn!("FROST key share is not generated, run `dkg` on the signer");
+        }
+        key_package.map(|key_package| key_package.group_public_key)
+    }
+
+    /// Sign messages and send the transactions to peer worker, messages are
+    /// packed into batches if batching is enabled, each message is signed in
+    /// its own transaction otherwise
+    async fn sign_messages(
+        &self,
+        raw_messages: Vec<&RawMessage>,
+    ) -> Result<HashMap<MessageId, SignOutcome>, ()> {
+        let Some(fee_payer) = self.fee_payer().await else {
+            return Ok(HashMap::new());
+        };
+        // the relayer only pays the fee in relayer fee payer mode
+        let pubkey =
+            if self.relayer_fee_payer.is_some() { self.keypair.pubkey() } else { fee_payer };
+        let signer = InstructionSigner { peer_id: self.peer_id, pubkey };
+
+        let mut outcomes = HashMap::with_capacity(raw_messages.len());
+        let mut prepared = Vec::with_capacity(raw_messages.len());
+        for raw_message in raw_messages {
+            match self.instruction_builder.build(raw_message, &signer) {
+                Ok(instructions) => prepared.push((raw_message, instructions)),
+                Err(err) => {
+                    let reason = format!("fail to build instructions: {err}");
+                    self.reject(raw_message, "invalid_instructions", reason).await?;
+                    outcomes.insert(raw_message.id, SignOutcome::Rejected);
+                }
+            }
+        }
+
+        let batches = match self.batch {
+            Some(batch) => {
+                let (batches, oversized) =
+                    batch::pack(prepared, batch.max_messages, |instructions| {
+                        self.fits_in_transaction(instructions, &fee_payer)
+                    });
+                for raw_message in oversized {
+                    let reason = "instructions exceed the transaction size limit".to_string();
+                    self.reject(raw_message, "transaction_too_large", reason).await?;
+                    outcomes.insert(raw_message.id, SignOutcome::Rejected);
+                }
+                batches
+            }
+            None => prepared.into_iter().map(|message| Batch { messages: vec![message] }).collect(),
+        };
+
+        for batch in batches {
+            outcomes.extend(self.sign_batch(batch, &fee_payer).await?);
+        }
+
+        Ok(outcomes)
+    }
+
+    /// Whether the transaction with the instructions, its compute budget and
+    /// durable nonce fits into a packet
+    fn fits_in_transaction(&self, instructions: &[Instruction], fee_payer: &Pubkey) -> bool {
+        let mut placeholder = self.compute_budget.placeholder_instructions();
+        if self.durable_nonce.is_some() {
+            placeholder.insert(
+                0,
+                system_instruction::advance_nonce_account(&Pubkey::new_unique(), fee_payer),
+            );
+        }
+        placeholder.extend(instructions.iter().cloned());
+
+        self.compile_message(&placeholder, fee_payer, Hash::default())
+            .is_ok_and(|message| batch::transaction_size(&message) <= PACKET_DATA_SIZE)
+    }
+
+    /// Sign the messages of the batch in one transaction and send it to peer
+    /// worker, messages violating the signing policy are removed from the
+    /// batch
+    async fn sign_batch(
+        &self,
+        mut batch: Batch<&RawMessage>,
+        fee_payer: &Pubkey,
+    ) -> Result<Vec<(MessageId, SignOutcome)>, ()> {
+        let mut outcomes = Vec::with_capacity(batch.messages.len());
+        let (mut instructions, spends) = loop {
+            let Some((first, _)) = batch.messages.first() else {
+                return Ok(outcomes);
+            };
+            let first = *first;
+
+            // compute budget instructions go first, the policy only applies to
+            // instructions built from the messages
+            let mut instructions = batch.instructions();
+            let compute_budget = self.compute_budget.instructions(&instructions, fee_payer).await;
+            instructions.splice(0..0, compute_budget);
+            let count = u64::try_from(batch.messages.len()).unwrap_or(u64::MAX);
+            let priority_fee = priority_fee(&instructions).div_ceil(count);
+
+            let checks = batch
+                .messages
+                .iter()
+                .map(|(raw_message, instructions)| (*raw_message, instructions.as_slice()))
+                .collect::<Vec<_>>();
+            let checked = self.policy.lock().await.check_batch(
+                &checks,
+                fee_payer,
+                priority_fee,
+                Instant::now(),
+            );
+            if checked.iter().all(Result::is_ok) {
+                tracing::debug!("Sign {} messages from `{}` in one transaction", count, first.id);
+                break (instructions, checked.into_iter().flatten().collect::<Vec<_>>());
+            }
+
+            let mut messages = Vec::with_capacity(batch.messages.len());
+            for (message, checked) in batch.messages.into_iter().zip(checked) {
+                if let Err(violation) = checked {
+                    self.reject(message.0, violation.as_label(), violation.to_string()).await?;
+                    outcomes.push((message.0.id, SignOutcome::Rejected));
+                } else {
+                    messages.push(message);
+                }
+            }
+            batch.messages = messages;
+        };
+        let raw_messages =
+            batch.messages.into_iter().map(|(raw_message, _)| raw_message).collect::<Vec<_>>();
+        let message_ids = raw_messages.iter().map(|raw_message| raw_message.id).collect::<Vec<_>>();
+
+        let nonce = match &self.durable_nonce {
+            Some(durable_nonce) => {
+                let Some(lease) = durable_nonce.lock().await.lease().await else {
+                    tracing::warn!(
+                        "No durable nonce is available for message `{}`",
+                        message_ids[0]
+                    );
+                    outcomes.extend(message_ids.into_iter().map(|id| (id, SignOutcome::Retry)));
+                    return Ok(outcomes);
+                };
+                instructions.insert(0, lease.advance_instruction());
+                Some(lease)
+            }
+            None => None,
+        };
+        let recent_blockhash = if let Some(lease) = nonce {
+            lease.value
+        } else if let Some(recent_blockhash) = self.blockhash_cache.latest().await {
+            recent_blockhash
+        } else {
+            tracing::warn!("Recent blockhash is not available yet");
+            outcomes.extend(message_ids.into_iter().map(|id| (id, SignOutcome::Retry)));
+            return Ok(outcomes);
+        };
+
+        // co-signers and FROST participants sign a single message, batching is
+        // disabled in these modes
+        let transaction = match self
+            .sign_transaction(raw_messages[0], instructions, fee_payer, recent_blockhash)
+            .await?
+        {
+            Ok(transaction) => transaction,
+            Err(outcome) => {
+                // the nonce is not used by any transaction
+                if let (Some(durable_nonce), Some(lease)) = (&self.durable_nonce, nonce) {
+                    durable_nonce.lock().await.release(&lease);
+                }
+                outcomes.extend(message_ids.into_iter().map(|id| (id, outcome)));
+                return Ok(outcomes);
+            }
+        };
+
+        self.send_signed_message(message_ids.clone(), transaction).await?;
+        let now = Instant::now();
+        let mut policy = self.policy.lock().await;
+        for (raw_message, spend) in raw_messages.iter().zip(spends) {
+            policy.record(&raw_message.origin, spend, now);
+        }
+        drop(policy);
+
+        outcomes.extend(message_ids.into_iter().map(|id| (id, SignOutcome::Signed)));
+        Ok(outcomes)
+    }
+
+    /// Publish the signed lifecycle events and send the transaction to the p2p
+    /// network with the fee it pays
+    async fn send_signed_message(
+        &self,
+        message_ids: Vec<MessageId>,
+        transaction: VersionedTransaction,
+    ) -> Result<(), ()> {
+        let signature = transaction.signatures[0].to_string();
+        for message_id in &message_ids {
+            let lifecycle_event = MessageLifecycleEvent::new(
+                *message_id,
+                MessageStage::Signed,
+                self.peer_id.to_string(),
+                Some(signature.clone()),
+            );
+            self.publish_lifecycle_event(lifecycle_event).await?;
+        }
+
+        let fee = transaction_fee(&transaction.message);
+        tracing::debug!("Transaction `{signature}` is signed with fee {fee:?}");
+        self.events.publish(NodeEventKind::TransactionSigned(TransactionSigned {
+            signature: signature.clone(),
+            message_ids: message_ids.iter().map(ToString::to_string).collect(),
+            signer: self.peer_id.to_string(),
+        }));
+        let signed_message =
+            SignedMessage { message_ids, transaction, fee, signer: self.peer_id.to_string() };
+        if let Err(err) = self
+            .peer_worker_inbound_sender
+            .send(PeerWorkerInboundEvent::Transaction(signed_message))
+            .await
+        {
+            tracing::error!("Fail to send transaction to peer worker: {err}");
+            return Err(());
+        }
+
+        Ok(())
+    }
+
+    /// Sign the transaction by this peer alone, with co-signers or with FROST
+    /// participants, return the outcome if the transaction is not signed.
+    /// Co-signed transactions are always legacy transactions
+    async fn sign_transaction(
+        &self,
+        raw_message: &RawMessage,
+        instructions: Vec<Instruction>,
+        fee_payer: &Pubkey,
+        recent_blockhash: Hash,
+    ) -> Result<Result<VersionedTransaction, SignOutcome>, ()> {
+        if let Some(frost) = &self.frost {
+            return Ok(self
+                .frost_sign(frost, raw_message, &instructions, recent_blockhash)
+                .await
+                .map(VersionedTransaction::from)
+                .ok_or(SignOutcome::Retry));
+        }
+        if let Some(multisig) = self.multisig {
+            return Ok(self
+                .multisign(raw_message, instructions, recent_blockhash, multisig)
+                .await?
+                .map(VersionedTransaction::from));
+        }
+
+        // Add the instructions to new transaction
+        let message = match self.compile_message(&instructions, fee_payer, recent_blockhash) {
+            Ok(message) => message,
+            Err(err) => {
+                tracing::error!("Fail to compile message `{}`: {err}", raw_message.id);
+                return Ok(Err(SignOutcome::Rejected));
+            }
+        };
+        if let Some(config) = self.relayer_fee_payer {
+            if *fee_payer != self.keypair.pubkey() {
+                return self.request_fee_payer(raw_message, message, fee_payer, config).await;
+            }
+        }
+        match VersionedTransaction::try_new(message, &[self.keypair.as_ref()]) {
+            Ok(transaction) => Ok(Ok(transaction)),
+            Err(err) => {
+                tracing::error!("Fail to sign message `{}`: {err}", raw_message.id);
+                Ok(Err(SignOutcome::Rejected))
+            }
+        }
+    }
+
+    /// Compile a v0 message with the address lookup tables if set, a legacy
+    /// message otherwise. Messages paid by the relayer require this peer to
+    /// sign
+    fn compile_message(
+        &self,
+        instructions: &[Instruction],
+        fee_payer: &Pubkey,
+        recent_blockhash: Hash,
+    ) -> Result<VersionedMessage, CompileError> {
+        let signed_instructions;
+        let instructions = if self.relayer_fee_payer.is_some() {
+            signed_instructions = require_signer(instructions, &self.keypair.pubkey());
+            &signed_instructions
+        } else {
+            instructions
+        };
+        Ok(match &self.address_lookup_tables {
+            Some(address_lookup_tables) => VersionedMessage::V0(v0::Message::try_compile(
+                fee_payer,
+                instructions,
+                address_lookup_tables,
+                recent_blockhash,
+            )?),
+            None => VersionedMessage::Legacy(Message::new_with_blockhash(
+                instructions,
+                Some(fee_payer),
+                &recent_blockhash,
+            )),
+        })
+    }
+
+    /// The current relayer and its public key
+    async fn relayer_pubkey(&self) -> Option<(PeerId, Pubkey)> {
+        let relayer = self.relayer.read().await.parse::<PeerId>().ok();
+        let pubkey = relayer.as_ref().and_then(peer_id_to_solana_pubkey);
+        if pubkey.is_none() {
+            tracing::warn!("Relayer is not elected yet, it pays the fee of signed transactions");
+        }
+        relayer.zip(pubkey)
+    }
+
+    /// Sign the message partially and ask the relayer to add its fee payer
+    /// signature, return the outcome if the relayer does not sign. Messages
+    /// are signed again if the relayer changes in the meantime
+    async fn request_fee_payer(
+        &self,
+        raw_message: &RawMessage,
+        message: VersionedMessage,
+        fee_payer: &Pubkey,
+        config: RelayerFeePayerConfig,
+    ) -> Result<Result<VersionedTransaction, SignOutcome>, ()> {
+        let Some((relayer, pubkey)) = self.relayer_pubkey().await else {
+            return Ok(Err(SignOutcome::Retry));
+        };
+        if pubkey != *fee_payer {
+            tracing::warn!("Relayer changes while signing message `{}`", raw_message.id);
+            return Ok(Err(SignOutcome::Retry));
+        }
+
+        let signers = usize::from(message.header().num_required_signatures);
+        let mut transaction =
+            VersionedTransaction { signatures: vec![Signature::default(); signers], message };
+        let message_data = transaction.message.serialize();
+        let Some(position) = transaction.message.static_account_keys()[..signers]
+            .iter()
+            .position(|key| *key == self.keypair.pubkey())
+        else {
+            tracing::error!("Message `{}` does not require this peer to sign", raw_message.id);
+            return Ok(Err(SignOutcome::Rejected));
+        };
+        transaction.signatures[position] = self.keypair.sign_message(&message_data);
+
+        let (sender, receiver) = oneshot::channel();
+        if let Err(err) = self
+            .peer_worker_inbound_sender
+            .send(PeerWorkerInboundEvent::Request((
+                relayer,
+                PeerRequest::FeePayer(FeePayerRequest { transaction: transaction.clone() }),
+                sender,
+            )))
+            .await
+        {
+            tracing::error!("Fail to send fee payer request to peer worker: {err}");
+            return Err(());
+        }
+
+        match time::timeout(config.timeout, receiver).await {
+            Ok(Ok(Some(PeerResponse::FeePayer(FeePayerResponse::Signed(signature)))))
+                if signature.verify(fee_payer.as_ref(), &message_data) =>
+            {
+                transaction.signatures[0] = signature;
+                Ok(Ok(transaction))
+            }
+            Ok(Ok(Some(PeerResponse::FeePayer(FeePayerResponse::Rejected(reason))))) => {
+                tracing::warn!(
+                    "Relayer {relayer} rejects to pay the fee of message `{}`: {reason}",
+                    raw_message.id
+                );
+                Ok(Err(SignOutcome::Retry))
+            }
+            _ => {
+                tracing::warn!(
+                    "Relayer {relayer} does not pay the fee of message `{}`",
+                    raw_message.id
+                );
+                Ok(Err(SignOutcome::Retry))
+            }
+        }
+    }
+
+    /// Collect signatures of `threshold - 1` co-signers, co-signers which do
+    /// not sign are replaced by other peers. Return the outcome if the
+    /// transaction is not signed
+    async fn multisign(
+        &self,
+        raw_message: &RawMessage,
+        instructions: Vec<Instruction>,
+        recent_blockhash: Hash,
+        MultisigConfig { threshold, timeout, .. }: MultisigConfig,
+    ) -> Result<Result<Transaction, SignOutcome>, ()> {
+        let pubkey = self.keypair.pubkey();
+        let mut missing = HashSet::new();
+        loop {
+            let cosigners = self.select_cosigners(threshold.saturating_sub(1), &missing).await;
+            if cosigners.len() + 1 < threshold {
+                let reason =
+                    if missing.is_empty() { "insufficient_signers" } else { "missing_signers" };
+                tracing::warn!(
+                    "Not enough co-signers for message `{}`, {} of {threshold} signers are \
+                     available",
+                    raw_message.id,
+                    cosigners.len() + 1
+                );
+                return Ok(Err(SignOutcome::Unsigned(reason)));
+            }
+
+            let cosigner_pubkeys = cosigners.iter().map(|(_, pubkey)| *pubkey).collect::<Vec<_>>();
+            let message = build_multisig_message(
+                &raw_message.id,
+                instructions.clone(),
+                &pubkey,
+                &cosigner_pubkeys,
+                &recent_blockhash,
+            );
+            let request = CosignRequest { raw_message: raw_message.clone(), message };
+            let results =
+                future::join_all(cosigners.iter().map(|(peer_id, pubkey)| {
+                    self.request_cosign(*peer_id, pubkey, &request, timeout)
+                }))
+                .await;
+
+            let mut signatures = Vec::with_capacity(cosigners.len());
+            for ((peer_id, pubkey), result) in cosigners.into_iter().zip(results) {
+                let result = result?;
+                metrics::MULTISIG_COSIGN_REQUESTS.with_label_values(&[result.as_label()]).inc();
+                match result {
+                    CosignResult::Signed(signature) => {
+                        signatures.push((peer_id, pubkey, signature));
+                        continue;
+                    }
+                    CosignResult::Rejected(reason) => tracing::warn!(
+                        "Co-signer {peer_id} rejects message `{}`: {reason}",
+                        raw_message.id
+                    ),
+                    CosignResult::Timeout | CosignResult::Failed => tracing::warn!(
+                        "Co-signer {peer_id} does not sign message `{}`",
+                        raw_message.id
+                    ),
+                }
+
+                missing.insert(peer_id);
+                self.send_lifecycle_event(raw_message.id, MessageStage::CosignerMissing, &peer_id)
+                    .await?;
+            }
+
+            // retry with other co-signers
+            if signatures.len() + 1 < threshold {
+                continue;
+            }
+
+            let mut transaction = Transaction::new_unsigned(request.message);
+            if let Err(err) = transaction.try_partial_sign(&[&self.keypair], recent_blockhash) {
+                tracing::error!("Fail to sign message `{}`: {err}", raw_message.id);
+                return Ok(Err(SignOutcome::Retry));
+            }
+            for (peer_id, pubkey, signature) in signatures {
+                if let Some(idx) =
+                    transaction.message.account_keys.iter().position(|key| *key == pubkey)
+                {
+                    transaction.signatures[idx] = signature;
+                }
+                self.send_lifecycle_event(raw_message.id, MessageStage::Cosigned, &peer_id).await?;
+            }
+
+            return Ok(Ok(transaction));
+        }
+    }
+
+    /// Connected peers other than this peer, sorted by `PeerId`
+    async fn connected_peers(&self) -> Vec<PeerId> {
+        let mut peers = self
+            .peers
+            .read()
+            .await
+            .iter()
+            .filter_map(|peer_id| peer_id.parse::<PeerId>().ok())
+            .filter(|peer_id| *peer_id != self.peer_id)
+            .collect::<Vec<_>>();
+        peers.sort_unstable();
+        peers.dedup();
+        peers
+    }
+
+    /// Connected peers which are not missing
+    async fn select_cosigners(
+        &self,
+        count: usize,
+        missing: &HashSet<PeerId>,
+    ) -> Vec<(PeerId, Pubkey)> {
+        self.connected_peers()
+            .await
+            .into_iter()
+            .filter(|peer_id| !missing.contains(peer_id))
+            .filter_map(|peer_id| Some((peer_id, peer_id_to_solana_pubkey(&peer_id)?)))
+            .take(count)
+            .collect()
+    }
+
+    async fn request_cosign(
+        &self,
+        peer_id: PeerId,
+        pubkey: &Pubkey,
+        request: &CosignRequest,
+        timeout: Duration,
+    ) -> Result<CosignResult, ()> {
+        let (sender, receiver) = oneshot::channel();
+        if let Err(err) = self
+            .peer_worker_inbound_sender
+            .send(PeerWorkerInboundEvent::Request((
+                peer_id,
+                PeerRequest::Cosign(request.clone()),
+                sender,
+            )))
+            .await
+        {
+            tracing::error!("Fail to send co-sign request to peer worker: {err}");
+            return Err(());
+        }
+
+        let result = match time::timeout(timeout, receiver).await {
+            Err(_) => CosignResult::Timeout,
+            Ok(Ok(Some(PeerResponse::Cosign(CosignResponse::Signed(signature))))) => {
+                if signature.verify(pubkey.as_ref(), &request.message.serialize()) {
+                    CosignResult::Signed(signature)
+                } else {
+                    CosignResult::Rejected("invalid signature".to_string())
+                }
+            }
+            Ok(Ok(Some(PeerResponse::Cosign(CosignResponse::Rejected(reason))))) => {
+                CosignResult::Rejected(reason)
+            }
+            Ok(
+                Ok(
+                    None
+                    | Some(
+                        PeerResponse::Frost(_)
+                        | PeerResponse::FeePayer(_)
+                        | PeerResponse::Catchup(_),
+                    ),
+                )
+                | Err(_),
+            ) => CosignResult::Failed,
+        };
+
+        Ok(result)
+    }
+
+    /// Sign the transaction of a co-sign request from the coordinator
+    async fn cosign(&self, coordinator: PeerId, request: &CosignRequest) -> CosignResponse {
+        if self.multisig.is_none() {
+            return CosignResponse::Rejected("multisig is disabled".to_string());
+        }
+        if *self.signer.read().await != coordinator.to_string() {
+            return CosignResponse::Rejected("coordinator is not the signer".to_string());
+        }
+
+        let pubkey = self.keypair.pubkey();
+        if let Err(reason) =
+            verify_cosign_request(self.instruction_builder.as_ref(), coordinator, &pubkey, request)
+        {
+            tracing::warn!(
+                "Reject co-sign request of message `{}` from {coordinator}: {reason}",
+                request.raw_message.id
+            );
+            return CosignResponse::Rejected(reason);
+        }
+
+        tracing::debug!("Co-sign message `{}` for {coordinator}", request.raw_message.id);
+        CosignResponse::Signed(self.keypair.sign_message(&request.message.serialize()))
+    }
+
+    /// Sign the transaction with FROST participants, this peer is preferred
+    async fn frost_sign(
+        &self,
+        frost: &Frost,
+        raw_message: &RawMessage,
+        instructions: &[Instruction],
+        recent_blockhash: Hash,
+    ) -> Option<Transaction> {
+        let key_package = frost.participant.lock().await.key_package().cloned()?;
+        let message = Message::new_with_blockhash(
+            instructions,
+            Some(&key_package.group_public_key),
+            &recent_blockhash,
+        );
+
+        let mut candidates = vec![self.peer_id];
+        candidates.extend(self.connected_peers().await);
+        let transport = FrostPeerTransport { signer: self, timeout: frost.config.timeout };
+        match frost::sign(&transport, &key_package, candidates, raw_message, &message).await {
+            Ok(signature) => Some(Transaction { signatures: vec![signature], message }),
+            Err(reason) => {
+                tracing::warn!("Fail to sign message `{}` with FROST: {reason}", raw_message.id);
+                None
+            }
+        }
+    }
+
+    /// Handle request from the FROST coordinator, which must be the signer
+    async fn handle_frost_request(
+        &self,
+        coordinator: PeerId,
+        request: FrostRequest,
+    ) -> FrostResponse {
+        let Some(frost) = &self.frost else {
+            return FrostResponse::Rejected("FROST is disabled".to_string());
+        };
+        if *self.signer.read().await != coordinator.to_string() {
+            return FrostResponse::Rejected("coordinator is not the signer".to_string());
+        }
+
+        frost.participant.lock().await.handle(coordinator, request)
+    }
+
+    /// Run distributed key generation among connected peers, the group public
+    /// key is funded by the funding strategy of the wallet
+    async fn start_dkg(&self) {
+        let Some(frost) = &self.frost else {
+            tracing::error!("FROST is disabled");
+            return;
+        };
+        if *self.signer.read().await != self.peer_id.to_string() {
+            tracing::error!("Only the signer can start FROST distributed key generation");
+            return;
+        }
+
+        let rekey_allowed = frost.participant.lock().await.ensure_rekey_allowed();
+        if let Err(reason) = rekey_allowed {
+            tracing::error!("Refuse FROST distributed key generation: {reason}");
+            return;
+        }
+
+        let mut participants = vec![self.peer_id];
+        participants.extend(self.connected_peers().await);
+        tracing::info!(
+            "Start FROST distributed key generation, {} of {} participants",
+            frost.config.threshold,
+            participants.len()
+        );
+
+        let transport = FrostPeerTransport { signer: self, timeout: frost.config.timeout };
+        let group_public_key =
+            match frost::run_dkg(&transport, participants, frost.config.threshold).await {
+                Ok(group_public_key) => group_public_key,
+                Err(reason) => {
+                    tracing::error!("FROST distributed key generation failed: {reason}");
+                    return;
+                }
+            };
+        tracing::info!("FROST group public key: {group_public_key}");
+
+        let group_funding =
+            WalletFunding::new(self.funding.clone(), self.client.clone(), group_public_key)
+                .without_balance_metric();
+        tokio::spawn(async move {
+            if let Err(err) = group_funding.fund().await {
+                tracing::error!("Fail to fund FROST group public key: {err}");
+            }
+        });
+    }
+
+    /// Reject message violating the signing policy or which can not be signed,
+    /// the reason is recorded in the lifecycle of the message
+    async fn reject(
+        &self,
+        raw_message: &RawMessage,
+        label: &'static str,
+        reason: String,
+    ) -> Result<(), ()> {
+        metrics::POLICY_REJECTED_MESSAGES.with_label_values(&[label]).inc();
+        self.publish_rejected(raw_message, reason).await
+    }
+
+    /// Record the reason of the rejection in the lifecycle of the message
+    async fn publish_rejected(&self, raw_message: &RawMessage, reason: String) -> Result<(), ()> {
+        tracing::warn!("Reject message `{}`: {reason}", raw_message.id);
+        let lifecycle_event = MessageLifecycleEvent::new(
+            raw_message.id,
+            MessageStage::Rejected,
+            self.peer_id.to_string(),
+            None,
+        )
+        .with_reason(reason);
+        self.publish_lifecycle_event(lifecycle_event).await
+    }
+
+    async fn send_lifecycle_event(
+        &self,
+        message_id: MessageId,
+        stage: MessageStage,
+        peer_id: &PeerId,
+    ) -> Result<(), ()> {
+        let lifecycle_event =
+            MessageLifecycleEvent::new(message_id, stage, peer_id.to_string(), None);
+        self.publish_lifecycle_event(lifecycle_event).await
+    }
+
+    async fn publish_lifecycle_event(
+        &self,
+        lifecycle_event: MessageLifecycleEvent,
+    ) -> Result<(), ()> {
+        if let Err(err) = self
+            .peer_worker_inbound_sender
+            .send(PeerWorkerInboundEvent::Lifecycle(lifecycle_event))
+            .await
+        {
+            tracing::error!("Fail to send lifecycle event to peer worker: {err}");
+            return Err(());
+        }
+
+        Ok(())
+    }
+}
+
+/// Remove the message from pending messages and mark it as settled, so that it
+/// is never signed by this peer. Return the message if it was pending
+fn settle_pending(
+    pending_messages: &mut VecDeque<PendingMessage>,
+    signed_messages: &mut SeenCache<MessageId>,
+    message_id: MessageId,
+) -> Option<RawMessage> {
+    signed_messages.insert(message_id);
+    let position = pending_messages.iter().position(|pending| pending.message.id == message_id)?;
+    pending_messages.remove(position).map(|pending| pending.message)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::{settle_pending, PendingMessage, SEEN_CACHE_CAPACITY, SEEN_CACHE_TTL};
+    use crate::{model::RawMessage, service::seen_cache::SeenCache};
+
+    #[test]
+    fn test_settle_rejected_message() {
+        let rejected = RawMessage::new("origin".to_string(), b"rejected".to_vec());
+        let other = RawMessage::new("origin".to_string(), b"other".to_vec());
+        let mut pending_messages = [&rejected, &other]
+            .into_iter()
+            .map(|message| PendingMessage::new(message.clone()))
+            .collect::<VecDeque<_>>();
+        let mut signed_messages = SeenCache::new(SEEN_CACHE_CAPACITY, SEEN_CACHE_TTL);
+
+        // the message rejected by the leader is dropped and never signed again
+        let settled = settle_pending(&mut pending_messages, &mut signed_messages, rejected.id);
+        assert_eq!(settled.map(|message| message.id), Some(rejected.id));
+        assert_eq!(pending_messages.len(), 1);
+        assert_eq!(pending_messages[0].message.id, other.id);
+        assert!(signed_messages.contains(&rejected.id));
+
+        assert!(settle_pending(&mut pending_messages, &mut signed_messages, rejected.id).is_none());
+    }
 }
@@ -0,0 +1,477 @@
+use std::{ops::Bound, path::Path};
+
+use chrono::{DateTime, Utc};
+use redb::{
+    Database, Durability, MultimapTableDefinition, ReadTransaction, ReadableTable as _,
+    TableDefinition, WriteTransaction,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use snafu::ResultExt;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+
+use crate::{
+    model::{
+        DeadLetter, MessageId, MessageLifecycle, MessageLifecycleEvent, RelayedTransaction,
+        SignedMessage, SortOrder,
+    },
+    service::{
+        error,
+        store::{collect_records, signers, updated_at, RecordPage, RecordQuery, Store, TimeKey},
+        Result,
+    },
+};
+
+/// signature => signed message
+const SIGNED_MESSAGES: TableDefinition<'_, &str, &[u8]> = TableDefinition::new("signed_messages");
+/// (recorded at, signature)
+const SIGNED_MESSAGES_BY_TIME: TableDefinition<'_, (i64, &str), ()> =
+    TableDefinition::new("signed_messages_by_time");
+/// message ID => (recorded at, signature)
+const SIGNED_MESSAGES_BY_MESSAGE_ID: MultimapTableDefinition<'_, &str, (i64, &str)> =
+    MultimapTableDefinition::new("signed_messages_by_message_id");
+/// signer => (recorded at, signature)
+const SIGNED_MESSAGES_BY_SIGNER: MultimapTableDefinition<'_, &str, (i64, &str)> =
+    MultimapTableDefinition::new("signed_messages_by_signer");
+
+/// signature => relayed transaction
+const RELAYED_TRANSACTIONS: TableDefinition<'_, &str, &[u8]> =
+    TableDefinition::new("relayed_transactions");
+/// (recorded at, signature)
+const RELAYED_TRANSACTIONS_BY_TIME: TableDefinition<'_, (i64, &str), ()> =
+    TableDefinition::new("relayed_transactions_by_time");
+
+/// message ID => lifecycle
+const LIFECYCLES: TableDefinition<'_, &str, &[u8]> = TableDefinition::new("lifecycles");
+/// signature => message ID
+const LIFECYCLES_BY_SIGNATURE: TableDefinition<'_, &str, &str> =
+    TableDefinition::new("lifecycles_by_signature");
+/// (updated at, message ID)
+const LIFECYCLES_BY_TIME: TableDefinition<'_, (i64, &str), ()> =
+    TableDefinition::new("lifecycles_by_time");
+
+/// signature => (signed message, dead letter)
+const DEAD_LETTERS: TableDefinition<'_, &str, &[u8]> = TableDefinition::new("dead_letters");
+
+/// All errors of `redb` are converted into `redb::Error`
+trait RedbResultExt<T> {
+    fn context_store(self) -> Result<T>;
+}
+
+impl<T, E: Into<redb::Error>> RedbResultExt<T> for Result<T, E> {
+    fn context_store(self) -> Result<T> { self.map_err(Into::into).context(error::StoreSnafu) }
+}
+
+/// Store keeping records in an embedded `redb` database file, records are
+/// kept across restarts
+pub struct RedbStore {
+    database: Database,
+}
+
+impl RedbStore {
+    /// Open the database file, the file is created if it does not exist
+    ///
+    /// # Errors
+    ///
+    /// * fail to open or create the database file
+    /// * fail to create tables
+    pub fn open(path: &Path) -> Result<Self> {
+        let database =
+            Database::create(path).context(error::OpenStoreSnafu { path: path.to_path_buf() })?;
+        let store = Self { database };
+
+        // tables are created up front, so that reads never miss tables
+        store.write(|transaction| {
+            transaction.open_table(SIGNED_MESSAGES).context_store()?;
+            transaction.open_table(SIGNED_MESSAGES_BY_TIME).context_store()?;
+            transaction.open_multimap_table(SIGNED_MESSAGES_BY_MESSAGE_ID).context_store()?;
+            transaction.open_multimap_table(SIGNED_MESSAGES_BY_SIGNER).context_store()?;
+            transaction.open_table(RELAYED_TRANSACTIONS).context_store()?;
+            transaction.open_table(RELAYED_TRANSACTIONS_BY_TIME).context_store()?;
+            transaction.open_table(LIFECYCLES).context_store()?;
+            transaction.open_table(LIFECYCLES_BY_SIGNATURE).context_store()?;
+            transaction.open_table(LIFECYCLES_BY_TIME).context_store()?;
+            transaction.open_table(DEAD_LETTERS).context_store()?;
+            Ok(())
+        })?;
+
+        Ok(store)
+    }
+
+    fn read(&self) -> Result<ReadTransaction> { self.database.begin_read().context_store() }
+
+    /// Records lost on crash are not critical, so commits are persisted
+    /// eventually instead of being synced each time
+    fn write<T>(&self, f: impl FnOnce(&WriteTransaction) -> Result<T>) -> Result<T> {
+        let mut transaction = self.database.begin_write().context_store()?;
+        transaction.set_durability(Durability::Eventual);
+        let value = f(&transaction)?;
+        transaction.commit().context_store()?;
+        Ok(value)
+    }
+
+    fn signed_messages(
+        transaction: &ReadTransaction,
+        signatures: impl IntoIterator<Item = String>,
+    ) -> Result<Vec<SignedMessage>> {
+        let table = transaction.open_table(SIGNED_MESSAGES).context_store()?;
+        let mut signed_messages = Vec::new();
+        for signature in signatures {
+            if let Some(value) = table.get(signature.as_str()).context_store()? {
+                signed_messages.push(decode(value.value())?);
+            }
+        }
+        Ok(signed_messages)
+    }
+
+    fn signed_messages_by(
+        &self,
+        definition: MultimapTableDefinition<'_, &str, (i64, &str)>,
+        key: &str,
+    ) -> Result<Vec<SignedMessage>> {
+        let transaction = self.read()?;
+        let signatures = transaction
+            .open_multimap_table(definition)
+            .context_store()?
+            .get(key)
+            .context_store()?
+            .map(|entry| entry.map(|entry| entry.value().1.to_string()))
+            .collect::<Result<Vec<_>, _>>()
+            .context_store()?;
+        Self::signed_messages(&transaction, signatures)
+    }
+
+    /// Records of the time index in the range of the query, in the order
+    fn list<T: DeserializeOwned>(
+        &self,
+        index: TableDefinition<'_, (i64, &str), ()>,
+        records: TableDefinition<'_, &str, &[u8]>,
+        query: &RecordQuery,
+        matches: impl Fn(&T) -> bool,
+    ) -> Result<RecordPage<T>> {
+        let Some((lower, upper)) = query.range() else {
+            return Ok(RecordPage { records: Vec::new(), next: None });
+        };
+        let range = (as_key_bound(&lower), as_key_bound(&upper));
+        let transaction = self.read()?;
+        let index = transaction.open_table(index).context_store()?;
+        let table = transaction.open_table(records).context_store()?;
+
+        let keys = index.range(range).context_store()?;
+        let keys: Box<dyn Iterator<Item = _>> = match query.order {
+            SortOrder::Asc => Box::new(keys),
+            SortOrder::Desc => Box::new(keys.rev()),
+        };
+        collect_records(
+            keys.map(|entry| {
+                entry.map(|(key, _)| (key.value().0, key.value().1.to_string())).context_store()
+            }),
+            query.limit,
+            |key| table.get(key).context_store()?.map(|value| decode(value.value())).transpose(),
+            matches,
+        )
+    }
+
+    /// Keys of the time index recorded before the time
+    fn time_keys_before(
+        transaction: &WriteTransaction,
+        definition: TableDefinition<'_, (i64, &str), ()>,
+        before: i64,
+    ) -> Result<Vec<TimeKey>> {
+        transaction
+            .open_table(definition)
+            .context_store()?
+            .range(..(before, ""))
+            .context_store()?
+            .map(|entry| entry.map(|(key, _)| (key.value().0, key.value().1.to_string())))
+            .collect::<Result<_, _>>()
+            .context_store()
+    }
+}
+
+impl Store for RedbStore {
+    fn insert_signed_message(
+        &self,
+        signed_message: &SignedMessage,
+        recorded_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let signature = signed_message.transaction.signatures[0].to_string();
+        let value = encode(signed_message)?;
+        let key = (recorded_at.timestamp_micros(), signature.as_str());
+        self.write(|transaction| {
+            let mut table = transaction.open_table(SIGNED_MESSAGES).context_store()?;
+            if table.get(signature.as_str()).context_store()?.is_some() {
+                return Ok(());
+            }
+            table.insert(signature.as_str(), value.as_slice()).context_store()?;
+
+            let mut by_time = transaction.open_table(SIGNED_MESSAGES_BY_TIME).context_store()?;
+            by_time.insert(key, ()).context_store()?;
+            let mut by_message_id =
+                transaction.open_multimap_table(SIGNED_MESSAGES_BY_MESSAGE_ID).context_store()?;
+            for message_id in &signed_message.message_ids {
+                by_message_id.insert(message_id.to_string().as_str(), key).context_store()?;
+            }
+            let mut by_signer =
+                transaction.open_multimap_table(SIGNED_MESSAGES_BY_SIGNER).context_store()?;
+            for signer in signers(&signed_message.transaction) {
+                by_signer.insert(signer.to_string().as_str(), key).context_store()?;
+            }
+            Ok(())
+        })
+    }
+
+    fn get_signed_message(&self, signature: &Signature) -> Result<Option<SignedMessage>> {
+        let transaction = self.read()?;
+        Ok(Self::signed_messages(&transaction, [signature.to_string()])?.pop())
+    }
+
+    fn list_signed_messages(&self, query: &RecordQuery) -> Result<RecordPage<SignedMessage>> {
+        self.list(SIGNED_MESSAGES_BY_TIME, SIGNED_MESSAGES, query, |signed_message| {
+            query.matches_signed_message(signed_message)
+        })
+    }
+
+    fn list_signed_messages_by_message_id(
+        &self,
+        message_id: &MessageId,
+    ) -> Result<Vec<SignedMessage>> {
+        self.signed_messages_by(SIGNED_MESSAGES_BY_MESSAGE_ID, &message_id.to_string())
+    }
+
+    fn list_signed_messages_by_signer(&self, signer: &Pubkey) -> Result<Vec<SignedMessage>> {
+        self.signed_messages_by(SIGNED_MESSAGES_BY_SIGNER, &signer.to_string())
+    }
+
+    fn insert_relayed_transaction(
+        &self,
+        relayed_transaction: &RelayedTransaction,
+        recorded_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let signature = relayed_transaction.signature.as_str();
+        let value = encode(relayed_transaction)?;
+        let recorded_at = recorded_at.timestamp_micros();
+        self.write(|transaction| {
+            let mut table = transaction.open_table(RELAYED_TRANSACTIONS).context_store()?;
+            let recorded = table
+                .get(signature)
+                .context_store()?
+                .map(|value| decode::<RelayedTransaction>(value.value()))
+                .transpose()?;
+            match recorded {
+                Some(recorded) if recorded.status > relayed_transaction.status => {}
+                Some(_) => {
+                    table.insert(signature, value.as_slice()).context_store()?;
+                }
+                None => {
+                    table.insert(signature, value.as_slice()).context_store()?;
+                    let mut by_time =
+                        transaction.open_table(RELAYED_TRANSACTIONS_BY_TIME).context_store()?;
+                    by_time.insert((recorded_at, signature), ()).context_store()?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn list_relayed_transactions(
+        &self,
+        query: &RecordQuery,
+    ) -> Result<RecordPage<RelayedTransaction>> {
+        self.list(
+            RELAYED_TRANSACTIONS_BY_TIME,
+            RELAYED_TRANSACTIONS,
+            query,
+            |relayed_transaction| query.matches_relayed_transaction(relayed_transaction),
+        )
+    }
+
+    fn apply_lifecycle_event(&self, event: MessageLifecycleEvent) -> Result<()> {
+        let message_id = event.message_id.to_string();
+        self.write(|transaction| {
+            let mut table = transaction.open_table(LIFECYCLES).context_store()?;
+            let mut lifecycle = match table.get(message_id.as_str()).context_store()? {
+                Some(value) => decode(value.value())?,
+                None => MessageLifecycle::new(event.message_id),
+            };
+            if let Some(signature) = &event.signature {
+                let mut by_signature =
+                    transaction.open_table(LIFECYCLES_BY_SIGNATURE).context_store()?;
+                by_signature.insert(signature.as_str(), message_id.as_str()).context_store()?;
+            }
+
+            let previous = updated_at(&lifecycle);
+            lifecycle.apply(event);
+            let current = updated_at(&lifecycle);
+            table.insert(message_id.as_str(), encode(&lifecycle)?.as_slice()).context_store()?;
+
+            if previous != current {
+                let mut by_time = transaction.open_table(LIFECYCLES_BY_TIME).context_store()?;
+                if let Some(previous) = previous {
+                    by_time
+                        .remove((previous.timestamp_micros(), message_id.as_str()))
+                        .context_store()?;
+                }
+                if let Some(current) = current {
+                    by_time
+                        .insert((current.timestamp_micros(), message_id.as_str()), ())
+                        .context_store()?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn get_lifecycle(&self, message_id: &MessageId) -> Result<Option<MessageLifecycle>> {
+        self.read()?
+            .open_table(LIFECYCLES)
+            .context_store()?
+            .get(message_id.to_string().as_str())
+            .context_store()?
+            .map(|value| decode(value.value()))
+            .transpose()
+    }
+
+    fn get_lifecycle_by_signature(&self, signature: &str) -> Result<Option<MessageLifecycle>> {
+        let transaction = self.read()?;
+        let by_signature = transaction.open_table(LIFECYCLES_BY_SIGNATURE).context_store()?;
+        let Some(message_id) = by_signature.get(signature).context_store()? else {
+            return Ok(None);
+        };
+
+        transaction
+            .open_table(LIFECYCLES)
+            .context_store()?
+            .get(message_id.value())
+            .context_store()?
+            .map(|value| decode(value.value()))
+            .transpose()
+    }
+
+    fn insert_dead_letter(
+        &self,
+        signed_message: &SignedMessage,
+        dead_letter: &DeadLetter,
+    ) -> Result<()> {
+        let signature = signed_message.transaction.signatures[0].to_string();
+        let value = encode(&(signed_message, dead_letter))?;
+        self.write(|transaction| {
+            let mut table = transaction.open_table(DEAD_LETTERS).context_store()?;
+            table.insert(signature.as_str(), value.as_slice()).context_store()?;
+            Ok(())
+        })
+    }
+
+    fn remove_dead_letter(&self, signature: &Signature) -> Result<()> {
+        let signature = signature.to_string();
+        self.write(|transaction| {
+            let mut table = transaction.open_table(DEAD_LETTERS).context_store()?;
+            table.remove(signature.as_str()).context_store()?;
+            Ok(())
+        })
+    }
+
+    fn list_dead_letters(&self) -> Result<Vec<(SignedMessage, DeadLetter)>> {
+        let mut dead_letters = self
+            .read()?
+            .open_table(DEAD_LETTERS)
+            .context_store()?
+            .iter()
+            .context_store()?
+            .map(|entry| decode(entry.context_store()?.1.value()))
+            .collect::<Result<Vec<(SignedMessage, DeadLetter)>>>()?;
+
+        dead_letters.sort_by_key(|(_, dead_letter)| dead_letter.failed_at);
+        Ok(dead_letters)
+    }
+
+    fn prune(&self, before: DateTime<Utc>) -> Result<usize> {
+        let before = before.timestamp_micros();
+        self.write(|transaction| {
+            Ok(prune_signed_messages(transaction, before)?
+                + prune_relayed_transactions(transaction, before)?
+                + prune_lifecycles(transaction, before)?)
+        })
+    }
+}
+
+fn prune_signed_messages(transaction: &WriteTransaction, before: i64) -> Result<usize> {
+    let keys = RedbStore::time_keys_before(transaction, SIGNED_MESSAGES_BY_TIME, before)?;
+
+    let mut table = transaction.open_table(SIGNED_MESSAGES).context_store()?;
+    let mut by_time = transaction.open_table(SIGNED_MESSAGES_BY_TIME).context_store()?;
+    let mut by_message_id =
+        transaction.open_multimap_table(SIGNED_MESSAGES_BY_MESSAGE_ID).context_store()?;
+    let mut by_signer =
+        transaction.open_multimap_table(SIGNED_MESSAGES_BY_SIGNER).context_store()?;
+    for (recorded_at, signature) in &keys {
+        let key = (*recorded_at, signature.as_str());
+        by_time.remove(key).context_store()?;
+        let Some(value) = table.remove(signature.as_str()).context_store()? else {
+            continue;
+        };
+        let signed_message = decode::<SignedMessage>(value.value())?;
+        for message_id in &signed_message.message_ids {
+            by_message_id.remove(message_id.to_string().as_str(), key).context_store()?;
+        }
+        for signer in signers(&signed_message.transaction) {
+            by_signer.remove(signer.to_string().as_str(), key).context_store()?;
+        }
+    }
+
+    Ok(keys.len())
+}
+
+fn prune_relayed_transactions(transaction: &WriteTransaction, before: i64) -> Result<usize> {
+    let keys = RedbStore::time_keys_before(transaction, RELAYED_TRANSACTIONS_BY_TIME, before)?;
+
+    let mut table = transaction.open_table(RELAYED_TRANSACTIONS).context_store()?;
+    let mut by_time = transaction.open_table(RELAYED_TRANSACTIONS_BY_TIME).context_store()?;
+    for (recorded_at, signature) in &keys {
+        by_time.remove((*recorded_at, signature.as_str())).context_store()?;
+        table.remove(signature.as_str()).context_store()?;
+    }
+
+    Ok(keys.len())
+}
+
+fn prune_lifecycles(transaction: &WriteTransaction, before: i64) -> Result<usize> {
+    let keys = RedbStore::time_keys_before(transaction, LIFECYCLES_BY_TIME, before)?;
+
+    let mut table = transaction.open_table(LIFECYCLES).context_store()?;
+    let mut by_time = transaction.open_table(LIFECYCLES_BY_TIME).context_store()?;
+    let mut by_signature = transaction.open_table(LIFECYCLES_BY_SIGNATURE).context_store()?;
+    for (updated_at, message_id) in &keys {
+        by_time.remove((*updated_at, message_id.as_str())).context_store()?;
+        let Some(value) = table.remove(message_id.as_str()).context_store()? else {
+            continue;
+        };
+        let Some(signature) = decode::<MessageLifecycle>(value.value())?.signature else {
+            continue;
+        };
+        drop(value);
+
+        // the signature may be indexed to another message signed in the same
+        // batch
+        let indexed = by_signature
+            .get(signature.as_str())
+            .context_store()?
+            .is_some_and(|indexed| indexed.value() == message_id);
+        if indexed {
+            by_signature.remove(signature.as_str()).context_store()?;
+        }
+    }
+
+    Ok(keys.len())
+}
+
+fn as_key_bound(bound: &Bound<TimeKey>) -> Bound<(i64, &str)> {
+    bound.as_ref().map(|(recorded_at, key)| (*recorded_at, key.as_str()))
+}
+
+fn encode<T: Serialize>(record: &T) -> Result<Vec<u8>> {
+    serde_json::to_vec(record).context(error::EncodeStoreRecordSnafu)
+}
+
+fn decode<T: DeserializeOwned>(value: &[u8]) -> Result<T> {
+    serde_json::from_slice(value).context(error::DecodeStoreRecordSnafu)
+}
@@ -0,0 +1,333 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{PoisonError, RwLock},
+};
+
+use chrono::{DateTime, Utc};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+
+use crate::{
+    model::{
+        DeadLetter, MessageId, MessageLifecycle, MessageLifecycleEvent, RelayedTransaction,
+        SignedMessage,
+    },
+    service::{
+        store::{
+            collect_records, scan, signers, updated_at, RecordPage, RecordQuery, Store, TimeKey,
+        },
+        Result,
+    },
+};
+
+#[derive(Debug, Default)]
+struct Records {
+    signed_messages: HashMap<Signature, (DateTime<Utc>, SignedMessage)>,
+    /// keyed as the disk store, so that records are paged in the same order
+    signed_messages_by_time: BTreeSet<TimeKey>,
+    signed_messages_by_message_id: HashMap<MessageId, BTreeSet<(DateTime<Utc>, Signature)>>,
+    signed_messages_by_signer: HashMap<Pubkey, BTreeSet<(DateTime<Utc>, Signature)>>,
+
+    relayed_transactions: HashMap<String, (DateTime<Utc>, RelayedTransaction)>,
+    relayed_transactions_by_time: BTreeSet<TimeKey>,
+
+    lifecycles: HashMap<MessageId, MessageLifecycle>,
+    lifecycles_by_signature: HashMap<String, MessageId>,
+    lifecycles_by_time: BTreeSet<(DateTime<Utc>, MessageId)>,
+
+    dead_letters: HashMap<Signature, (SignedMessage, DeadLetter)>,
+}
+
+impl Records {
+    fn signed_messages<'a>(
+        &'a self,
+        index: impl IntoIterator<Item = &'a (DateTime<Utc>, Signature)>,
+    ) -> Vec<SignedMessage> {
+        index
+            .into_iter()
+            .filter_map(|(_, signature)| self.signed_messages.get(signature))
+            .map(|(_, signed_message)| signed_message.clone())
+            .collect()
+    }
+
+    fn remove_signed_message(&mut self, key: &(DateTime<Utc>, Signature)) {
+        self.signed_messages_by_time.remove(&(key.0.timestamp_micros(), key.1.to_string()));
+        let Some((_, signed_message)) = self.signed_messages.remove(&key.1) else {
+            return;
+        };
+        for message_id in &signed_message.message_ids {
+            remove_from_index(&mut self.signed_messages_by_message_id, message_id, key);
+        }
+        for signer in signers(&signed_message.transaction) {
+            remove_from_index(&mut self.signed_messages_by_signer, signer, key);
+        }
+    }
+
+    fn remove_lifecycle(&mut self, key: &(DateTime<Utc>, MessageId)) {
+        self.lifecycles_by_time.remove(key);
+        let Some(lifecycle) = self.lifecycles.remove(&key.1) else {
+            return;
+        };
+        if let Some(signature) = lifecycle.signature {
+            if self.lifecycles_by_signature.get(&signature) == Some(&key.1) {
+                self.lifecycles_by_signature.remove(&signature);
+            }
+        }
+    }
+}
+
+fn remove_from_index<K, V>(
+    index: &mut HashMap<K, BTreeSet<(DateTime<Utc>, V)>>,
+    key: &K,
+    value: &(DateTime<Utc>, V),
+) where
+    K: Eq + std::hash::Hash,
+    V: Ord,
+{
+    if let Some(values) = index.get_mut(key) {
+        values.remove(value);
+        if values.is_empty() {
+            index.remove(key);
+        }
+    }
+}
+
+/// Store keeping records in the memory, records are lost on restart
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    records: RwLock<Records>,
+}
+
+impl MemoryStore {
+    #[must_use]
+    pub fn new() -> Self { Self::default() }
+}
+
+impl Store for MemoryStore {
+    fn insert_signed_message(
+        &self,
+        signed_message: &SignedMessage,
+        recorded_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let signature = signed_message.transaction.signatures[0];
+        let mut records = self.records.write().unwrap_or_else(PoisonError::into_inner);
+        if records.signed_messages.contains_key(&signature) {
+            return Ok(());
+        }
+
+        let key = (recorded_at, signature);
+        for message_id in &signed_message.message_ids {
+            records.signed_messages_by_message_id.entry(*message_id).or_default().insert(key);
+        }
+        for signer in signers(&signed_message.transaction) {
+            records.signed_messages_by_signer.entry(*signer).or_default().insert(key);
+        }
+        records
+            .signed_messages_by_time
+            .insert((recorded_at.timestamp_micros(), signature.to_string()));
+        records.signed_messages.insert(signature, (recorded_at, signed_message.clone()));
+        drop(records);
+
+        Ok(())
+    }
+
+    fn get_signed_message(&self, signature: &Signature) -> Result<Option<SignedMessage>> {
+        let records = self.records.read().unwrap_or_else(PoisonError::into_inner);
+        Ok(records.signed_messages.get(signature).map(|(_, signed_message)| signed_message.clone()))
+    }
+
+    fn list_signed_messages(&self, query: &RecordQuery) -> Result<RecordPage<SignedMessage>> {
+        let records = self.records.read().unwrap_or_else(PoisonError::into_inner);
+        collect_records(
+            scan(&records.signed_messages_by_time, query).cloned().map(Ok),
+            query.limit,
+            |signature| {
+                let signature = signature.parse::<Signature>().ok();
+                Ok(signature
+                    .and_then(|signature| records.signed_messages.get(&signature))
+                    .map(|(_, signed_message)| signed_message.clone()))
+            },
+            |signed_message| query.matches_signed_message(signed_message),
+        )
+    }
+
+    fn list_signed_messages_by_message_id(
+        &self,
+        message_id: &MessageId,
+    ) -> Result<Vec<SignedMessage>> {
+        let records = self.records.read().unwrap_or_else(PoisonError::into_inner);
+        Ok(records
+            .signed_messages_by_message_id
+            .get(message_id)
+            .map_or_else(Vec::new, |index| records.signed_messages(index)))
+    }
+
+    fn list_signed_messages_by_signer(&self, signer: &Pubkey) -> Result<Vec<SignedMessage>> {
+        let records = self.records.read().unwrap_or_else(PoisonError::into_inner);
+        Ok(records
+            .signed_messages_by_signer
+            .get(signer)
+            .map_or_else(Vec::new, |index| records.signed_messages(index)))
+    }
+
+    fn insert_relayed_transaction(
+        &self,
+        relayed_transaction: &RelayedTransaction,
+        recorded_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let signature = &relayed_transaction.signature;
+        let mut records = self.records.write().unwrap_or_else(PoisonError::into_inner);
+        if let Some((_, recorded)) = records.relayed_transactions.get_mut(signature) {
+            if recorded.status <= relayed_transaction.status {
+                recorded.clone_from(relayed_transaction);
+            }
+            return Ok(());
+        }
+
+        records
+            .relayed_transactions
+            .insert(signature.clone(), (recorded_at, relayed_transaction.clone()));
+        records
+            .relayed_transactions_by_time
+            .insert((recorded_at.timestamp_micros(), signature.clone()));
+        drop(records);
+
+        Ok(())
+    }
+
+    fn list_relayed_transactions(
+        &self,
+        query: &RecordQuery,
+    ) -> Result<RecordPage<RelayedTransaction>> {
+        let records = self.records.read().unwrap_or_else(PoisonError::into_inner);
+        collect_records(
+            scan(&records.relayed_transactions_by_time, query).cloned().map(Ok),
+            query.limit,
+            |signature| {
+                Ok(records
+                    .relayed_transactions
+                    .get(signature)
+                    .map(|(_, relayed_transaction)| relayed_transaction.clone()))
+            },
+            |relayed_transaction| query.matches_relayed_transaction(relayed_transaction),
+        )
+    }
+
+    fn apply_lifecycle_event(&self, event: MessageLifecycleEvent) -> Result<()> {
+        let mut records = self.records.write().unwrap_or_else(PoisonError::into_inner);
+        if let Some(signature) = &event.signature {
+            records.lifecycles_by_signature.insert(signature.clone(), event.message_id);
+        }
+
+        let message_id = event.message_id;
+        let lifecycle = records
+            .lifecycles
+            .entry(message_id)
+            .or_insert_with(|| MessageLifecycle::new(message_id));
+        let previous = updated_at(lifecycle);
+        lifecycle.apply(event);
+        let current = updated_at(lifecycle);
+
+        if previous != current {
+            if let Some(previous) = previous {
+                records.lifecycles_by_time.remove(&(previous, message_id));
+            }
+            if let Some(current) = current {
+                records.lifecycles_by_time.insert((current, message_id));
+            }
+        }
+        drop(records);
+
+        Ok(())
+    }
+
+    fn get_lifecycle(&self, message_id: &MessageId) -> Result<Option<MessageLifecycle>> {
+        let records = self.records.read().unwrap_or_else(PoisonError::into_inner);
+        Ok(records.lifecycles.get(message_id).cloned())
+    }
+
+    fn get_lifecycle_by_signature(&self, signature: &str) -> Result<Option<MessageLifecycle>> {
+        let records = self.records.read().unwrap_or_else(PoisonError::into_inner);
+        Ok(records
+            .lifecycles_by_signature
+            .get(signature)
+            .and_then(|message_id| records.lifecycles.get(message_id))
+            .cloned())
+    }
+
+    fn insert_dead_letter(
+        &self,
+        signed_message: &SignedMessage,
+        dead_letter: &DeadLetter,
+    ) -> Result<()> {
+        let mut records = self.records.write().unwrap_or_else(PoisonError::into_inner);
+        records.dead_letters.insert(
+            signed_message.transaction.signatures[0],
+            (signed_message.clone(), dead_letter.clone()),
+        );
+        drop(records);
+
+        Ok(())
+    }
+
+    fn remove_dead_letter(&self, signature: &Signature) -> Result<()> {
+        let mut records = self.records.write().unwrap_or_else(PoisonError::into_inner);
+        records.dead_letters.remove(signature);
+        drop(records);
+
+        Ok(())
+    }
+
+    fn list_dead_letters(&self) -> Result<Vec<(SignedMessage, DeadLetter)>> {
+        let records = self.records.read().unwrap_or_else(PoisonError::into_inner);
+        let mut dead_letters = records.dead_letters.values().cloned().collect::<Vec<_>>();
+        drop(records);
+
+        dead_letters.sort_by_key(|(_, dead_letter)| dead_letter.failed_at);
+        Ok(dead_letters)
+    }
+
+    fn prune(&self, before: DateTime<Utc>) -> Result<usize> {
+        let mut records = self.records.write().unwrap_or_else(PoisonError::into_inner);
+
+        let before_micros = before.timestamp_micros();
+        let signed_messages = records
+            .signed_messages_by_time
+            .iter()
+            .take_while(|(recorded_at, _)| *recorded_at < before_micros)
+            .filter_map(|(_, signature)| signature.parse::<Signature>().ok())
+            .filter_map(|signature| {
+                records
+                    .signed_messages
+                    .get(&signature)
+                    .map(|(recorded_at, _)| (*recorded_at, signature))
+            })
+            .collect::<Vec<_>>();
+        for key in &signed_messages {
+            records.remove_signed_message(key);
+        }
+
+        let relayed_transactions = records
+            .relayed_transactions_by_time
+            .iter()
+            .take_while(|(recorded_at, _)| *recorded_at < before_micros)
+            .cloned()
+            .collect::<Vec<_>>();
+        for key in &relayed_transactions {
+            records.relayed_transactions_by_time.remove(key);
+            records.relayed_transactions.remove(&key.1);
+        }
+
+        let lifecycles = records
+            .lifecycles_by_time
+            .iter()
+            .take_while(|(updated_at, _)| *updated_at < before)
+            .copied()
+            .collect::<Vec<_>>();
+        for key in &lifecycles {
+            records.remove_lifecycle(key);
+        }
+        drop(records);
+
+        Ok(signed_messages.len() + relayed_transactions.len() + lifecycles.len())
+    }
+}
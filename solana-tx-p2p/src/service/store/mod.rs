@@ -0,0 +1,769 @@
+mod disk;
+mod memory;
+
+use std::{collections::BTreeSet, ops::Bound, sync::Arc, time::Duration};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD as BASE64, Engine as _};
+use chrono::{DateTime, Utc};
+use snafu::OptionExt;
+use solana_sdk::{pubkey::Pubkey, signature::Signature, transaction::VersionedTransaction};
+use tokio::{sync::mpsc, task, time};
+
+pub use self::{disk::RedbStore, memory::MemoryStore};
+use crate::{
+    model::{
+        DeadLetter, ListQuery, MessageId, MessageLifecycle, MessageLifecycleEvent, Page,
+        RelayedTransaction, SignedMessage, SortOrder,
+    },
+    service::{error, Result},
+    ShutdownSignal,
+};
+
+/// Key of time indexes, the microseconds since epoch of the time a record is
+/// recorded and the key of the record
+pub type TimeKey = (i64, String);
+
+/// Range, filters and ordering of listed records. Records are scanned from
+/// the cursor in the order until the limit is reached
+#[derive(Clone, Debug)]
+pub struct RecordQuery {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// signed messages signed by the peer, transactions relayed with the
+    /// signer
+    pub signer: Option<String>,
+    /// transactions relayed by the peer, signed messages are not filtered
+    pub relayer: Option<String>,
+    pub order: SortOrder,
+    /// key of the last record of the previous page
+    pub after: Option<TimeKey>,
+    pub limit: usize,
+}
+
+impl Default for RecordQuery {
+    fn default() -> Self {
+        Self {
+            since: None,
+            until: None,
+            signer: None,
+            relayer: None,
+            order: SortOrder::Asc,
+            after: None,
+            limit: usize::MAX,
+        }
+    }
+}
+
+impl RecordQuery {
+    /// All records recorded since the time
+    #[must_use]
+    pub fn since(since: DateTime<Utc>) -> Self { Self { since: Some(since), ..Self::default() } }
+
+    /// # Errors
+    ///
+    /// * the page token is not issued by `page_token`
+    pub fn from_list_query(query: &ListQuery) -> Result<Self> {
+        let after = query.page_token.as_deref().map(parse_page_token).transpose()?;
+        Ok(Self {
+            since: query.since,
+            until: query.until,
+            signer: query.signer.clone(),
+            relayer: query.relayer.clone(),
+            order: query.order,
+            after,
+            limit: query.limit(),
+        })
+    }
+
+    /// Range of the time index to scan, `None` if the range is empty
+    fn range(&self) -> Option<(Bound<TimeKey>, Bound<TimeKey>)> {
+        let mut lower = self.since.map_or(Bound::Unbounded, |since| {
+            Bound::Included((since.timestamp_micros(), String::new()))
+        });
+        let mut upper = self.until.map_or(Bound::Unbounded, |until| {
+            Bound::Excluded((until.timestamp_micros(), String::new()))
+        });
+        if let Some(after) = &self.after {
+            match self.order {
+                SortOrder::Asc if !matches!(&lower, Bound::Included(since) if since > after) => {
+                    lower = Bound::Excluded(after.clone());
+                }
+                SortOrder::Desc if !matches!(&upper, Bound::Excluded(until) if until <= after) => {
+                    upper = Bound::Excluded(after.clone());
+                }
+                SortOrder::Asc | SortOrder::Desc => {}
+            }
+        }
+
+        // the upper bound is always excluded
+        match (&lower, &upper) {
+            (Bound::Included(lower) | Bound::Excluded(lower), Bound::Excluded(upper))
+                if lower >= upper =>
+            {
+                None
+            }
+            _ => Some((lower, upper)),
+        }
+    }
+
+    fn matches_signed_message(&self, signed_message: &SignedMessage) -> bool {
+        self.signer.as_ref().map_or(true, |signer| *signer == signed_message.signer)
+    }
+
+    fn matches_relayed_transaction(&self, relayed_transaction: &RelayedTransaction) -> bool {
+        self.signer.as_ref().map_or(true, |signer| *signer == relayed_transaction.signer)
+            && self.relayer.as_ref().map_or(true, |relayer| *relayer == relayed_transaction.relayer)
+    }
+}
+
+/// Page of records listed by a query
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecordPage<T> {
+    pub records: Vec<T>,
+    /// key of the last record if more records match the query
+    pub next: Option<TimeKey>,
+}
+
+impl<T> From<RecordPage<T>> for Page<T> {
+    fn from(RecordPage { records, next }: RecordPage<T>) -> Self {
+        Self { items: records, next_page_token: next.as_ref().map(page_token) }
+    }
+}
+
+/// Opaque token of the page after the key
+fn page_token((recorded_at, key): &TimeKey) -> String {
+    BASE64.encode(format!("{recorded_at}:{key}"))
+}
+
+fn parse_page_token(token: &str) -> Result<TimeKey> {
+    BASE64
+        .decode(token)
+        .ok()
+        .and_then(|token| String::from_utf8(token).ok())
+        .and_then(|token| {
+            let (recorded_at, key) = token.split_once(':')?;
+            Some((recorded_at.parse().ok()?, key.to_string()))
+        })
+        .context(error::InvalidPageTokenSnafu { token })
+}
+
+/// Keys of the index in the range of the query, in the order
+pub fn scan<'a>(
+    index: &'a BTreeSet<TimeKey>,
+    query: &RecordQuery,
+) -> Box<dyn Iterator<Item = &'a TimeKey> + 'a> {
+    let Some(range) = query.range() else {
+        return Box::new(std::iter::empty());
+    };
+    match query.order {
+        SortOrder::Asc => Box::new(index.range(range)),
+        SortOrder::Desc => Box::new(index.range(range).rev()),
+    }
+}
+
+/// Collect the records of the scanned keys which match the query, one more
+/// matched record is looked up to tell whether a next page exists
+pub fn collect_records<T>(
+    keys: impl Iterator<Item = Result<TimeKey>>,
+    limit: usize,
+    mut get: impl FnMut(&str) -> Result<Option<T>>,
+    matches: impl Fn(&T) -> bool,
+) -> Result<RecordPage<T>> {
+    let mut records = Vec::new();
+    let mut last = None;
+    for key in keys {
+        let key = key?;
+        let Some(record) = get(&key.1)? else {
+            continue;
+        };
+        if !matches(&record) {
+            continue;
+        }
+        if records.len() >= limit {
+            return Ok(RecordPage { records, next: last });
+        }
+        records.push(record);
+        last = Some(key);
+    }
+
+    Ok(RecordPage { records, next: None })
+}
+
+/// Records of signed messages, relayed transactions and message lifecycles
+/// kept by the peer worker
+///
+/// Signed messages are indexed by signature, message ID, signer and the time
+/// they are recorded. Records are listed in the order they are recorded, keys
+/// recorded at the same microsecond are ordered by signature
+pub trait Store: Send + Sync {
+    /// Record the signed message, signed messages which are already recorded
+    /// are ignored
+    ///
+    /// # Errors
+    ///
+    /// * fail to write the store
+    fn insert_signed_message(
+        &self,
+        signed_message: &SignedMessage,
+        recorded_at: DateTime<Utc>,
+    ) -> Result<()>;
+
+    /// # Errors
+    ///
+    /// * fail to read the store
+    fn get_signed_message(&self, signature: &Signature) -> Result<Option<SignedMessage>>;
+
+    /// Signed messages matching the query
+    ///
+    /// # Errors
+    ///
+    /// * fail to read the store
+    fn list_signed_messages(&self, query: &RecordQuery) -> Result<RecordPage<SignedMessage>>;
+
+    /// Signed messages containing the message, a message is signed again if
+    /// its transaction expires
+    ///
+    /// # Errors
+    ///
+    /// * fail to read the store
+    fn list_signed_messages_by_message_id(
+        &self,
+        message_id: &MessageId,
+    ) -> Result<Vec<SignedMessage>>;
+
+    /// Signed messages whose transactions require the signature of the signer
+    ///
+    /// # Errors
+    ///
+    /// * fail to read the store
+    fn list_signed_messages_by_signer(&self, signer: &Pubkey) -> Result<Vec<SignedMessage>>;
+
+    /// Record the relayed transaction, the record of a transaction which is
+    /// already recorded is replaced unless its commitment status is behind.
+    /// Records keep the time they are first recorded
+    ///
+    /// # Errors
+    ///
+    /// * fail to write the store
+    fn insert_relayed_transaction(
+        &self,
+        relayed_transaction: &RelayedTransaction,
+        recorded_at: DateTime<Utc>,
+    ) -> Result<()>;
+
+    /// Relayed transactions matching the query
+    ///
+    /// # Errors
+    ///
+    /// * fail to read the store
+    fn list_relayed_transactions(
+        &self,
+        query: &RecordQuery,
+    ) -> Result<RecordPage<RelayedTransaction>>;
+
+    /// # Errors
+    ///
+    /// * fail to write the store
+    fn apply_lifecycle_event(&self, event: MessageLifecycleEvent) -> Result<()>;
+
+    /// # Errors
+    ///
+    /// * fail to read the store
+    fn get_lifecycle(&self, message_id: &MessageId) -> Result<Option<MessageLifecycle>>;
+
+    /// # Errors
+    ///
+    /// * fail to read the store
+    fn get_lifecycle_by_signature(&self, signature: &str) -> Result<Option<MessageLifecycle>>;
+
+    /// Record the dead letter with its transaction, the dead letter of a
+    /// transaction which is already recorded is replaced
+    ///
+    /// # Errors
+    ///
+    /// * fail to write the store
+    fn insert_dead_letter(
+        &self,
+        signed_message: &SignedMessage,
+        dead_letter: &DeadLetter,
+    ) -> Result<()>;
+
+    /// Remove the dead letter of the transaction once it is replayed or
+    /// dropped
+    ///
+    /// # Errors
+    ///
+    /// * fail to write the store
+    fn remove_dead_letter(&self, signature: &Signature) -> Result<()>;
+
+    /// Dead letters with their transactions ordered by the time they failed
+    ///
+    /// # Errors
+    ///
+    /// * fail to read the store
+    fn list_dead_letters(&self) -> Result<Vec<(SignedMessage, DeadLetter)>>;
+
+    /// Remove records recorded before the time, lifecycles are removed if
+    /// their latest stage is before the time. Dead letters are kept until they
+    /// are replayed. Return the number of removed records
+    ///
+    /// # Errors
+    ///
+    /// * fail to write the store
+    fn prune(&self, before: DateTime<Utc>) -> Result<usize>;
+}
+
+/// Remove records older than the retention from the store periodically
+pub struct StorePruner {
+    store: Arc<dyn Store>,
+    retention: Duration,
+    prune_interval: Duration,
+}
+
+impl StorePruner {
+    #[must_use]
+    pub fn new(store: Arc<dyn Store>, retention: Duration, prune_interval: Duration) -> Self {
+        Self { store, retention, prune_interval }
+    }
+
+    /// Prune the store until shutdown
+    ///
+    /// # Errors
+    ///
+    /// It never returns error, failures of pruning are logged
+    pub async fn start(self, mut shutdown_signal: ShutdownSignal) -> Result<()> {
+        let mut prune_timer = time::interval(self.prune_interval);
+        loop {
+            tokio::select! {
+                () = shutdown_signal.wait() => break,
+                _ = prune_timer.tick() => self.prune(),
+            }
+        }
+
+        tracing::warn!("Store pruner stopped.");
+
+        Ok(())
+    }
+
+    fn prune(&self) {
+        let Ok(retention) = chrono::Duration::from_std(self.retention) else {
+            return;
+        };
+        let Some(before) = Utc::now().checked_sub_signed(retention) else {
+            return;
+        };
+
+        match self.store.prune(before) {
+            Ok(0) => {}
+            Ok(removed) => tracing::debug!("Prune {removed} records recorded before {before}"),
+            Err(err) => tracing::error!("Fail to prune store: {err}"),
+        }
+    }
+}
+
+/// Record written to the store by the store writer
+pub enum StoreWrite {
+    SignedMessage(SignedMessage, DateTime<Utc>),
+    RelayedTransaction(RelayedTransaction, DateTime<Utc>),
+    LifecycleEvent(MessageLifecycleEvent),
+}
+
+impl StoreWrite {
+    fn apply(self, store: &dyn Store) {
+        match self {
+            Self::SignedMessage(signed_message, recorded_at) => {
+                if let Err(err) = store.insert_signed_message(&signed_message, recorded_at) {
+                    tracing::error!("Fail to store signed message: {err}");
+                }
+            }
+            Self::RelayedTransaction(relayed_transaction, recorded_at) => {
+                if let Err(err) =
+                    store.insert_relayed_transaction(&relayed_transaction, recorded_at)
+                {
+                    tracing::error!("Fail to store relayed transaction: {err}");
+                }
+            }
+            Self::LifecycleEvent(event) => {
+                if let Err(err) = store.apply_lifecycle_event(event) {
+                    tracing::error!("Fail to store message lifecycle event: {err}");
+                }
+            }
+        }
+    }
+}
+
+/// Apply writes to the store in order on the blocking thread pool, so that
+/// the peer worker never waits for the store
+pub struct StoreWriter {
+    store: Arc<dyn Store>,
+    receiver: mpsc::Receiver<StoreWrite>,
+}
+
+impl StoreWriter {
+    #[must_use]
+    pub fn new(store: Arc<dyn Store>, receiver: mpsc::Receiver<StoreWrite>) -> Self {
+        Self { store, receiver }
+    }
+
+    /// Apply writes until all senders are dropped, so that writes sent before
+    /// shutdown are not lost
+    ///
+    /// # Errors
+    ///
+    /// It never returns error, failures of writes are logged
+    pub async fn start(mut self) -> Result<()> {
+        while let Some(write) = self.receiver.recv().await {
+            // apply the writes queued meanwhile at once
+            let mut writes = vec![write];
+            while let Ok(write) = self.receiver.try_recv() {
+                writes.push(write);
+            }
+
+            let store = self.store.clone();
+            let applied = task::spawn_blocking(move || {
+                for write in writes {
+                    write.apply(store.as_ref());
+                }
+            })
+            .await;
+            if let Err(err) = applied {
+                tracing::error!("Fail to apply writes to store: {err}");
+            }
+        }
+
+        tracing::warn!("Store writer stopped.");
+
+        Ok(())
+    }
+}
+
+/// Signers required by the transaction, including the fee payer
+fn signers(transaction: &VersionedTransaction) -> &[Pubkey] {
+    let message = &transaction.message;
+    let account_keys = message.static_account_keys();
+    let num_signers = usize::from(message.header().num_required_signatures);
+    &account_keys[..num_signers.min(account_keys.len())]
+}
+
+/// Time of the latest stage of the lifecycle, lifecycles are pruned by the
+/// time they are updated
+fn updated_at(lifecycle: &MessageLifecycle) -> Option<DateTime<Utc>> {
+    lifecycle.stages.last().map(|record| record.timestamp)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::{DateTime, Duration, Utc};
+    use solana_sdk::{
+        hash::Hash,
+        instruction::Instruction,
+        message::Message,
+        signature::Keypair,
+        signer::Signer as _,
+        transaction::{Transaction, VersionedTransaction},
+    };
+    use tokio::sync::mpsc;
+
+    use super::{
+        page_token, parse_page_token, MemoryStore, RecordQuery, RedbStore, Store, StoreWrite,
+        StoreWriter,
+    };
+    use crate::{
+        model::{
+            CommitmentStatus, DeadLetter, MessageId, MessageLifecycleEvent, MessageStage,
+            RelayedTransaction, SignedMessage, SortOrder, TransactionFee,
+        },
+        service::MEMO_PROGRAM_ID,
+    };
+
+    fn signed_message(message_ids: Vec<MessageId>, signer: &Keypair) -> SignedMessage {
+        let instruction = Instruction::new_with_bytes(MEMO_PROGRAM_ID, b"hello", vec![]);
+        let message = Message::new(&[instruction], Some(&signer.pubkey()));
+        let transaction = Transaction::new(&[signer], message, Hash::new_unique());
+        SignedMessage {
+            message_ids,
+            transaction: VersionedTransaction::from(transaction),
+            fee: TransactionFee::default(),
+            signer: "peer".to_string(),
+        }
+    }
+
+    fn relayed_transaction(signature: &str, status: CommitmentStatus) -> RelayedTransaction {
+        RelayedTransaction {
+            signature: signature.to_string(),
+            message_ids: vec![],
+            signer: "signer".to_string(),
+            relayer: "relayer".to_string(),
+            slot: None,
+            submitted_at: Utc::now(),
+            confirmed_at: None,
+            status,
+            fee: 5_000,
+            error: None,
+        }
+    }
+
+    fn relayed_signatures(relayed_transactions: Vec<RelayedTransaction>) -> Vec<String> {
+        relayed_transactions.into_iter().map(|rt| rt.signature).collect()
+    }
+
+    fn lifecycle_event(
+        message_id: MessageId,
+        stage: MessageStage,
+        signature: Option<String>,
+        timestamp: chrono::DateTime<Utc>,
+    ) -> MessageLifecycleEvent {
+        MessageLifecycleEvent {
+            message_id,
+            stage,
+            peer_id: "peer".to_string(),
+            signature,
+            reason: None,
+            timestamp,
+        }
+    }
+
+    fn dead_letter(signed_message: &SignedMessage, failed_at: DateTime<Utc>) -> DeadLetter {
+        DeadLetter {
+            signature: signed_message.transaction.signatures[0].to_string(),
+            message_ids: signed_message.message_ids.iter().map(ToString::to_string).collect(),
+            attempts: 3,
+            error: "error".to_string(),
+            failed_at,
+        }
+    }
+
+    #[allow(clippy::too_many_lines)]
+    fn check_store(store: &dyn Store) {
+        let now = Utc::now();
+        let signer = Keypair::new();
+        let message_id = MessageId::new("peer", &now, b"first");
+        let other_message_id = MessageId::new("peer", &now, b"second");
+
+        // the first message is signed again after its transaction expires
+        let expired = signed_message(vec![message_id], &signer);
+        let batch = signed_message(vec![message_id, other_message_id], &signer);
+        let other = signed_message(vec![other_message_id], &Keypair::new());
+        store.insert_signed_message(&expired, now - Duration::hours(2)).unwrap();
+        store.insert_signed_message(&batch, now - Duration::minutes(1)).unwrap();
+        store.insert_signed_message(&other, now).unwrap();
+        // duplicated signed messages are ignored
+        store.insert_signed_message(&batch, now).unwrap();
+
+        let signatures = |signed_messages: Vec<SignedMessage>| {
+            signed_messages.into_iter().map(|sm| sm.transaction.signatures[0]).collect::<Vec<_>>()
+        };
+        let [expired_signature, batch_signature, other_signature] =
+            [&expired, &batch, &other].map(|sm| sm.transaction.signatures[0]);
+        assert_eq!(
+            signatures(store.list_signed_messages(&RecordQuery::default()).unwrap().records),
+            [expired_signature, batch_signature, other_signature]
+        );
+        assert_eq!(
+            signatures(
+                store
+                    .list_signed_messages(&RecordQuery::since(now - Duration::hours(1)))
+                    .unwrap()
+                    .records
+            ),
+            [batch_signature, other_signature]
+        );
+        // records are paged from the cursor in the order
+        let query = RecordQuery { order: SortOrder::Desc, limit: 2, ..RecordQuery::default() };
+        let page = store.list_signed_messages(&query).unwrap();
+        assert_eq!(signatures(page.records), [other_signature, batch_signature]);
+        let page = store.list_signed_messages(&RecordQuery { after: page.next, ..query }).unwrap();
+        assert_eq!(signatures(page.records), [expired_signature]);
+        assert!(page.next.is_none());
+        let query = RecordQuery {
+            until: Some(now),
+            signer: Some("peer".to_string()),
+            ..RecordQuery::default()
+        };
+        assert_eq!(signatures(store.list_signed_messages(&query).unwrap().records), [
+            expired_signature,
+            batch_signature
+        ]);
+        assert_eq!(signatures(store.list_signed_messages_by_message_id(&message_id).unwrap()), [
+            expired_signature,
+            batch_signature
+        ]);
+        assert_eq!(signatures(store.list_signed_messages_by_signer(&signer.pubkey()).unwrap()), [
+            expired_signature,
+            batch_signature
+        ]);
+        assert_eq!(
+            store
+                .get_signed_message(&batch_signature)
+                .unwrap()
+                .map(|sm| sm.message_ids)
+                .unwrap_or_default(),
+            [message_id, other_message_id]
+        );
+
+        store
+            .insert_relayed_transaction(
+                &relayed_transaction("old", CommitmentStatus::Submitted),
+                now - Duration::hours(2),
+            )
+            .unwrap();
+        store
+            .insert_relayed_transaction(
+                &relayed_transaction("new", CommitmentStatus::Submitted),
+                now - Duration::minutes(1),
+            )
+            .unwrap();
+        // records are updated as the commitment status progresses
+        store
+            .insert_relayed_transaction(
+                &relayed_transaction("new", CommitmentStatus::Confirmed),
+                now,
+            )
+            .unwrap();
+        store
+            .insert_relayed_transaction(
+                &relayed_transaction("new", CommitmentStatus::Processed),
+                now,
+            )
+            .unwrap();
+        let relayed_transactions =
+            store.list_relayed_transactions(&RecordQuery::default()).unwrap().records;
+        assert_eq!(relayed_signatures(relayed_transactions.clone()), ["old", "new"]);
+        assert_eq!(relayed_transactions[1].status, CommitmentStatus::Confirmed);
+        let query = RecordQuery { limit: 1, ..RecordQuery::default() };
+        let page = store.list_relayed_transactions(&query).unwrap();
+        assert_eq!(relayed_signatures(page.records), ["old"]);
+        let page =
+            store.list_relayed_transactions(&RecordQuery { after: page.next, ..query }).unwrap();
+        assert_eq!(relayed_signatures(page.records), ["new"]);
+        assert!(page.next.is_none());
+        let query = RecordQuery { relayer: Some("other".to_string()), ..RecordQuery::default() };
+        assert!(store.list_relayed_transactions(&query).unwrap().records.is_empty());
+
+        let signature = batch_signature.to_string();
+        store
+            .apply_lifecycle_event(lifecycle_event(
+                message_id,
+                MessageStage::Received,
+                None,
+                now - Duration::hours(2),
+            ))
+            .unwrap();
+        store
+            .apply_lifecycle_event(lifecycle_event(
+                other_message_id,
+                MessageStage::Received,
+                None,
+                now - Duration::hours(2),
+            ))
+            .unwrap();
+        store
+            .apply_lifecycle_event(lifecycle_event(
+                message_id,
+                MessageStage::Signed,
+                Some(signature.clone()),
+                now,
+            ))
+            .unwrap();
+        let lifecycle = store.get_lifecycle_by_signature(&signature).unwrap().unwrap();
+        assert_eq!(lifecycle.message_id, message_id);
+        assert_eq!(lifecycle.stages.len(), 2);
+
+        store.insert_dead_letter(&other, &dead_letter(&other, now)).unwrap();
+        store
+            .insert_dead_letter(&expired, &dead_letter(&expired, now - Duration::hours(2)))
+            .unwrap();
+        let dead_letters = |store: &dyn Store| {
+            let dead_letters = store.list_dead_letters().unwrap();
+            signatures(dead_letters.into_iter().map(|(signed_message, _)| signed_message).collect())
+        };
+        assert_eq!(dead_letters(store), [expired_signature, other_signature]);
+
+        // records before the retention are removed, lifecycles updated
+        // recently are kept
+        assert_eq!(store.prune(now - Duration::hours(1)).unwrap(), 3);
+        assert_eq!(
+            signatures(store.list_signed_messages(&RecordQuery::default()).unwrap().records),
+            [batch_signature, other_signature]
+        );
+        assert_eq!(signatures(store.list_signed_messages_by_message_id(&message_id).unwrap()), [
+            batch_signature
+        ]);
+        assert!(store.get_signed_message(&expired_signature).unwrap().is_none());
+        assert_eq!(
+            relayed_signatures(
+                store.list_relayed_transactions(&RecordQuery::default()).unwrap().records
+            ),
+            ["new"]
+        );
+        assert!(store.get_lifecycle(&message_id).unwrap().is_some());
+        assert!(store.get_lifecycle(&other_message_id).unwrap().is_none());
+        // dead letters are kept until they are replayed
+        assert_eq!(dead_letters(store), [expired_signature, other_signature]);
+        store.remove_dead_letter(&expired_signature).unwrap();
+        assert_eq!(dead_letters(store), [other_signature]);
+    }
+
+    #[test]
+    fn test_page_token() {
+        let key =
+            (1_700_000_000_000_000, "5h6xBEauJ3PK6SWCZ1PGjBvj8vDdWG3KpwATGy1ARAXF".to_string());
+        assert_eq!(parse_page_token(&page_token(&key)).unwrap(), key);
+        assert!(parse_page_token("invalid").is_err());
+    }
+
+    #[test]
+    fn test_memory_store() { check_store(&MemoryStore::new()); }
+
+    #[test]
+    fn test_redb_store() {
+        let path = std::env::temp_dir().join(format!("store-{}.redb", Keypair::new().pubkey()));
+        let store = Arc::new(RedbStore::open(&path).unwrap());
+        check_store(store.as_ref());
+        drop(store);
+
+        // records are kept after the store is opened again
+        let store = RedbStore::open(&path).unwrap();
+        assert_eq!(
+            relayed_signatures(
+                store.list_relayed_transactions(&RecordQuery::default()).unwrap().records
+            ),
+            ["new"]
+        );
+        assert_eq!(store.list_dead_letters().unwrap().len(), 1);
+        drop(store);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_store_writer() {
+        let now = Utc::now();
+        let store = Arc::new(MemoryStore::new());
+        let message_id = MessageId::new("peer", &now, b"hello");
+        let signed_message = signed_message(vec![message_id], &Keypair::new());
+        let signature = signed_message.transaction.signatures[0];
+
+        let (sender, receiver) = mpsc::channel(10);
+        for stage in [MessageStage::Received, MessageStage::Signed] {
+            let event = lifecycle_event(message_id, stage, None, now);
+            sender.send(StoreWrite::LifecycleEvent(event)).await.unwrap();
+        }
+        sender.send(StoreWrite::SignedMessage(signed_message, now)).await.unwrap();
+        // writes sent before the senders are dropped are applied
+        drop(sender);
+        StoreWriter::new(store.clone(), receiver).start().await.unwrap();
+
+        let stages = store
+            .get_lifecycle(&message_id)
+            .unwrap()
+            .unwrap()
+            .stages
+            .into_iter()
+            .map(|record| record.stage)
+            .collect::<Vec<_>>();
+        assert_eq!(stages, [MessageStage::Received, MessageStage::Signed]);
+        assert!(store.get_signed_message(&signature).unwrap().is_some());
+    }
+}
@@ -0,0 +1,12 @@
+pub mod v1;
+
+use axum::{routing, Router};
+
+use crate::app_state::AppState;
+
+pub fn v1<S>() -> Router
+where
+    S: AppState + Clone + Send + Sync + 'static,
+{
+    Router::new().route("/v1/events", routing::get(v1::subscribe_events::<S>))
+}
@@ -0,0 +1,151 @@
+use std::{borrow::Cow, convert::Infallible, time::Duration};
+
+use axum::{
+    extract::{
+        ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        Query,
+    },
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    Extension,
+};
+use futures_util::StreamExt;
+use http::HeaderMap;
+use serde::{de::IntoDeserializer, Deserialize};
+
+use crate::{
+    app_state::AppState,
+    model::{EventFilter, EventQuery, NodeEvent, NodeEventType},
+    service::{EventSubscription, PeerService},
+    web::{
+        error::{self, Result},
+        ErrorResponse,
+    },
+};
+
+/// Interval of heartbeats keeping idle connections alive, comments on
+/// Server-Sent Events and pings on WebSocket
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+/// WebSocket clients which do not receive a message within the timeout are
+/// dropped
+const WEBSOCKET_SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Stream node events in JSON as Server-Sent Events, or through WebSocket if
+/// the connection is upgraded. Clients falling behind by more than 1000 events
+/// are dropped and should subscribe again from the next sequence number
+#[utoipa::path(
+    get,
+    path = "/api/v1/events",
+    params(EventQuery),
+    responses(
+        (status = 200, description = "Server-Sent Events, the data of each event is a `NodeEvent`", body = NodeEvent, content_type = "text/event-stream"),
+        (status = 101, description = "Switch to WebSocket, each text message is a `NodeEvent`"),
+        (status = 400, description = "Invalid event type or expired sequence number", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+pub async fn subscribe_events<S>(
+    Extension(app_state): Extension<S>,
+    Query(query): Query<EventQuery>,
+    headers: HeaderMap,
+    websocket: Option<WebSocketUpgrade>,
+) -> Result<Response>
+where
+    S: AppState + Clone + Send + Sync + 'static,
+{
+    let last_event_id =
+        headers.get("last-event-id").and_then(|value| value.to_str().ok()?.parse::<u64>().ok());
+    let filter = EventFilter {
+        types: event_types(query.types.as_deref())?,
+        from_sequence: query.from_sequence.or_else(|| last_event_id.map(|id| id + 1)),
+    };
+    let subscription = app_state.peer_service().subscribe_events(filter).await?;
+
+    match websocket {
+        Some(websocket) => {
+            Ok(websocket.on_upgrade(move |socket| send_events(socket, subscription)))
+        }
+        None => Ok(server_sent_events(subscription).into_response()),
+    }
+}
+
+fn event_types(types: Option<&str>) -> Result<Vec<NodeEventType>> {
+    types
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|event_type| !event_type.is_empty())
+        .map(|event_type| {
+            NodeEventType::deserialize(event_type.into_deserializer()).map_err(
+                |_: serde::de::value::Error| error::Error::InvalidEventType {
+                    event_type: event_type.to_string(),
+                },
+            )
+        })
+        .collect()
+}
+
+fn server_sent_events(
+    subscription: EventSubscription,
+) -> Sse<impl futures_util::Stream<Item = Result<Event, Infallible>>> {
+    let stream = subscription.into_stream().map(|result| {
+        let event = match result {
+            Ok(event) => Event::default()
+                .id(event.sequence.to_string())
+                .json_data(&event)
+                .expect("`NodeEvent` is valid JSON"),
+            // the stream ends after the error
+            Err(err) => Event::default().event("error").data(err.to_string()),
+        };
+        Ok(event)
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::new().interval(HEARTBEAT_INTERVAL))
+}
+
+async fn send_events(mut socket: WebSocket, mut subscription: EventSubscription) {
+    let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+    heartbeat.reset();
+
+    loop {
+        let message = tokio::select! {
+            result = subscription.next() => match result {
+                Ok(Some(event)) => Message::Text(
+                    serde_json::to_string(&event).expect("`NodeEvent` is valid JSON"),
+                ),
+                Ok(None) => Message::Close(Some(CloseFrame {
+                    code: close_code::AWAY,
+                    reason: Cow::from("Server is shutting down"),
+                })),
+                Err(err) => {
+                    tracing::warn!("Drop WebSocket client of events, error: {err}");
+                    Message::Close(Some(CloseFrame {
+                        code: close_code::AGAIN,
+                        reason: Cow::from("Client falls behind, subscribe again"),
+                    }))
+                }
+            },
+            _ = heartbeat.tick() => Message::Ping(Vec::new()),
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_)) | Err(_)) | None => return,
+                Some(Ok(_)) => continue,
+            },
+        };
+
+        let is_close = matches!(message, Message::Close(_));
+        match tokio::time::timeout(WEBSOCKET_SEND_TIMEOUT, socket.send(message)).await {
+            Ok(Ok(())) if !is_close => {}
+            Ok(Ok(())) => return,
+            Ok(Err(err)) => {
+                tracing::debug!("Fail to send event to WebSocket client, error: {err}");
+                return;
+            }
+            Err(_) => {
+                tracing::warn!("Drop WebSocket client of events which is too slow");
+                return;
+            }
+        }
+    }
+}
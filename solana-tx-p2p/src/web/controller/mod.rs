@@ -1,3 +1,7 @@
+// SAFETY: allow: utoipa generated code
+#![allow(clippy::needless_for_each)]
+
+mod event;
 mod peer;
 
 use axum::Router;
@@ -10,9 +14,14 @@ use utoipa::OpenApi;
 use crate::{
     app_state::AppState,
     model::{
-        CompiledInstructionForUtoipa, MessageForUtoipa, MessageHeaderForUtoipa, TransactionDetail,
-        TransactionForUtoipa,
+        CommitmentStatus, CompiledInstructionView, ConfirmationStatusChanged, DeadLetter,
+        LeaderElected, LeaderRole, MessageAddressTableLookupView, MessageHeaderView,
+        MessageLifecycle, MessageReceived, MessageStage, MessageStageRecord, MessageVersion,
+        MessageView, NodeEvent, NodeEventKind, NodeEventType, PeerConnection, RelayedTransaction,
+        SimulationResult, SortOrder, SubmitMessageRequest, SubmitMessageResponse,
+        TransactionDetail, TransactionFee, TransactionSigned, TransactionView,
     },
+    web::ErrorResponse,
 };
 
 pub fn apis<S>(_app_state: &S) -> Router
@@ -21,7 +30,7 @@ where
 {
     Router::new().nest(
         "/api",
-        Router::new().merge(self::peer::v1::<S>()).layer(
+        Router::new().merge(self::peer::v1::<S>()).merge(self::event::v1::<S>()).layer(
             TraceLayer::new_for_http()
                 .on_request(|request: &Request<Body>, _span: &Span| {
                     let request_url = request.uri();
@@ -46,16 +55,45 @@ where
 #[openapi(
     paths(
         peer::v1::discovery,
+        peer::v1::submit_message,
         peer::v1::list_signed_messages,
         peer::v1::list_relayed_transactions,
         peer::v1::get_relayed_transaction,
+        peer::v1::get_message_lifecycle,
+        peer::v1::get_message_lifecycle_by_signature,
+        peer::v1::list_dead_letters,
+        peer::v1::replay_dead_letter,
+        event::v1::subscribe_events,
     ),
     components(schemas(
-        TransactionForUtoipa,
-        MessageForUtoipa,
-        MessageHeaderForUtoipa,
-        CompiledInstructionForUtoipa,
+        TransactionView,
+        TransactionFee,
+        MessageView,
+        MessageVersion,
+        MessageHeaderView,
+        CompiledInstructionView,
+        MessageAddressTableLookupView,
         TransactionDetail,
+        MessageLifecycle,
+        MessageStageRecord,
+        MessageStage,
+        SubmitMessageRequest,
+        SubmitMessageResponse,
+        DeadLetter,
+        SimulationResult,
+        RelayedTransaction,
+        CommitmentStatus,
+        SortOrder,
+        ErrorResponse,
+        NodeEvent,
+        NodeEventKind,
+        NodeEventType,
+        MessageReceived,
+        TransactionSigned,
+        ConfirmationStatusChanged,
+        LeaderElected,
+        LeaderRole,
+        PeerConnection,
     ))
 )]
 pub struct ApiDoc;
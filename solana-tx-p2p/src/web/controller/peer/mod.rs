@@ -12,11 +12,19 @@ where
         "/v1/peer",
         Router::new()
             .route("/discovery", routing::get(v1::discovery::<S>))
+            .route("/message", routing::post(v1::submit_message::<S>))
             .route("/signed-message", routing::get(v1::list_signed_messages::<S>))
             .route("/relayed-transaction", routing::get(v1::list_relayed_transactions::<S>))
             .route(
                 "/relayed-transaction/:signature",
                 routing::get(v1::get_relayed_transaction::<S>),
-            ),
+            )
+            .route(
+                "/relayed-transaction/:signature/lifecycle",
+                routing::get(v1::get_message_lifecycle_by_signature::<S>),
+            )
+            .route("/message/:message_id/lifecycle", routing::get(v1::get_message_lifecycle::<S>))
+            .route("/dead-letter", routing::get(v1::list_dead_letters::<S>))
+            .route("/dead-letter/:signature/replay", routing::post(v1::replay_dead_letter::<S>)),
     )
 }
@@ -1,69 +1,116 @@
-use axum::{extract::Path, Extension, Json};
-use solana_sdk::transaction::Transaction;
+use axum::{
+    extract::{Path, Query},
+    Extension, Json,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use snafu::ResultExt;
 
 use crate::{
     app_state::AppState,
-    model::{TransactionDetail, TransactionForUtoipa},
+    model::{
+        DeadLetter, ListQuery, MessageLifecycle, Page, RelayedTransaction, SubmitMessageRequest,
+        SubmitMessageResponse, TransactionDetail, TransactionView,
+    },
     service::PeerService,
-    web::error::Result,
+    web::{
+        error::{self, Result},
+        ErrorResponse,
+    },
 };
 
-/// Discovery peers
+/// Submit message to be signed and relayed
 #[utoipa::path(
     post,
+    path = "/api/v1/peer/message",
+    request_body = SubmitMessageRequest,
+    responses(
+        (status = 200, body = SubmitMessageResponse),
+        (status = 400, description = "Invalid message data", body = ErrorResponse),
+        (status = 503, description = "Peer worker or Solana RPC endpoints are unavailable", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+pub async fn submit_message<S>(
+    Extension(app_state): Extension<S>,
+    Json(SubmitMessageRequest { data }): Json<SubmitMessageRequest>,
+) -> Result<Json<SubmitMessageResponse>>
+where
+    S: AppState + Clone + Send + Sync + 'static,
+{
+    let data = BASE64.decode(data).context(error::DecodeBase64Snafu)?;
+    let message_id = app_state.peer_service().submit_message(data).await?;
+
+    Ok(Json(SubmitMessageResponse { message_id: message_id.to_string() }))
+}
+
+/// Discovery peers, ordered by their IDs
+#[utoipa::path(
+    get,
     path = "/api/v1/peer/discovery",
+    params(ListQuery),
     responses(
-        (status = 200, body = Vec<String>),
-        (status = 500, description = "Internal server error")
+        (status = 200, body = Page<String>),
+        (status = 400, description = "Invalid page token", body = ErrorResponse),
+        (status = 503, description = "Peer worker or Solana RPC endpoints are unavailable", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
     )
 )]
-pub async fn discovery<S>(Extension(app_state): Extension<S>) -> Result<Json<Vec<String>>>
+pub async fn discovery<S>(
+    Extension(app_state): Extension<S>,
+    Query(query): Query<ListQuery>,
+) -> Result<Json<Page<String>>>
 where
     S: AppState + Clone + Send + Sync + 'static,
 {
-    let peers = app_state.peer_service().discovery_peers().await?;
+    let peers = app_state.peer_service().discovery_peers(&query).await?;
 
     Ok(Json(peers))
 }
 
 /// List signed messages
 #[utoipa::path(
-    post,
+    get,
     path = "/api/v1/peer/signed-message",
+    params(ListQuery),
     responses(
-        (status = 200, body = Vec<TransactionForUtoipa>),
-        (status = 500, description = "Internal server error")
+        (status = 200, body = Page<TransactionView>),
+        (status = 400, description = "Invalid page token", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
     )
 )]
 pub async fn list_signed_messages<S>(
     Extension(app_state): Extension<S>,
-) -> Result<Json<Vec<Transaction>>>
+    Query(query): Query<ListQuery>,
+) -> Result<Json<Page<TransactionView>>>
 where
     S: AppState + Clone + Send + Sync + 'static,
 {
-    let signed_messages = app_state.peer_service().list_signed_messages().await?;
+    let signed_messages = app_state.peer_service().list_signed_messages(&query).await?;
 
-    Ok(Json(signed_messages))
+    Ok(Json(signed_messages.map(Into::into)))
 }
 
 /// List relayed transactions
 #[utoipa::path(
-    post,
+    get,
     path = "/api/v1/peer/relayed-transaction",
+    params(ListQuery),
     responses(
-        (status = 200, body = Vec<String>),
-        (status = 500, description = "Internal server error")
+        (status = 200, body = Page<RelayedTransaction>),
+        (status = 400, description = "Invalid page token", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
     )
 )]
 pub async fn list_relayed_transactions<S>(
     Extension(app_state): Extension<S>,
-) -> Result<Json<Vec<String>>>
+    Query(query): Query<ListQuery>,
+) -> Result<Json<Page<RelayedTransaction>>>
 where
     S: AppState + Clone + Send + Sync + 'static,
 {
-    let signatures = app_state.peer_service().list_relayed_transactions().await?;
+    let relayed_transactions = app_state.peer_service().list_relayed_transactions(&query).await?;
 
-    Ok(Json(signatures))
+    Ok(Json(relayed_transactions))
 }
 
 /// Get relayed transaction
@@ -72,7 +119,10 @@ where
     path = "/api/v1/peer/relayed-transaction/{signature}",
     responses(
         (status = 200, body = TransactionDetail),
-        (status = 500, description = "Internal server error")
+        (status = 400, description = "Invalid signature", body = ErrorResponse),
+        (status = 404, description = "Transaction is not found", body = ErrorResponse),
+        (status = 503, description = "Peer worker or Solana RPC endpoints are unavailable", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
     ),
     params((
         "signature" = String, Path, description = "Signature of transaction")
@@ -89,3 +139,103 @@ where
 
     Ok(Json(tx))
 }
+
+/// Get lifecycle of message
+#[utoipa::path(
+    post,
+    path = "/api/v1/peer/message/{message_id}/lifecycle",
+    responses(
+        (status = 200, body = MessageLifecycle),
+        (status = 400, description = "Invalid message ID", body = ErrorResponse),
+        (status = 404, description = "Message lifecycle is not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ),
+    params((
+        "message_id" = String, Path, description = "ID of message")
+    )
+)]
+pub async fn get_message_lifecycle<S>(
+    Extension(app_state): Extension<S>,
+    Path(message_id): Path<String>,
+) -> Result<Json<MessageLifecycle>>
+where
+    S: AppState + Clone + Send + Sync + 'static,
+{
+    let lifecycle = app_state.peer_service().get_message_lifecycle(&message_id).await?;
+
+    Ok(Json(lifecycle))
+}
+
+/// Get lifecycle of message by signature of relayed transaction
+#[utoipa::path(
+    post,
+    path = "/api/v1/peer/relayed-transaction/{signature}/lifecycle",
+    responses(
+        (status = 200, body = MessageLifecycle),
+        (status = 400, description = "Invalid signature", body = ErrorResponse),
+        (status = 404, description = "Message lifecycle is not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ),
+    params((
+        "signature" = String, Path, description = "Signature of transaction")
+    )
+)]
+pub async fn get_message_lifecycle_by_signature<S>(
+    Extension(app_state): Extension<S>,
+    Path(signature): Path<String>,
+) -> Result<Json<MessageLifecycle>>
+where
+    S: AppState + Clone + Send + Sync + 'static,
+{
+    let lifecycle = app_state.peer_service().get_message_lifecycle_by_signature(&signature).await?;
+
+    Ok(Json(lifecycle))
+}
+
+/// List transactions which the relayer of this peer fails to send
+#[utoipa::path(
+    get,
+    path = "/api/v1/peer/dead-letter",
+    responses(
+        (status = 200, body = Vec<DeadLetter>),
+        (status = 503, description = "Peer worker or Solana RPC endpoints are unavailable", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+pub async fn list_dead_letters<S>(
+    Extension(app_state): Extension<S>,
+) -> Result<Json<Vec<DeadLetter>>>
+where
+    S: AppState + Clone + Send + Sync + 'static,
+{
+    let dead_letters = app_state.peer_service().list_dead_letters().await?;
+
+    Ok(Json(dead_letters))
+}
+
+/// Send a dead-lettered transaction again
+#[utoipa::path(
+    post,
+    path = "/api/v1/peer/dead-letter/{signature}/replay",
+    responses(
+        (status = 200, body = DeadLetter),
+        (status = 400, description = "Invalid signature", body = ErrorResponse),
+        (status = 404, description = "Dead letter is not found", body = ErrorResponse),
+        (status = 503, description = "Peer worker or Solana RPC endpoints are unavailable", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ),
+    params((
+        "signature" = String, Path, description = "Signature of transaction")
+    )
+)]
+pub async fn replay_dead_letter<S>(
+    Extension(app_state): Extension<S>,
+    Path(signature): Path<String>,
+) -> Result<Json<DeadLetter>>
+where
+    S: AppState + Clone + Send + Sync + 'static,
+{
+    let dead_letter = app_state.peer_service().replay_dead_letter(&signature).await?;
+
+    Ok(Json(dead_letter))
+}
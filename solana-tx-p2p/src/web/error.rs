@@ -1,6 +1,9 @@
 use axum::response::{IntoResponse, Response};
 use serde::{Deserialize, Serialize};
 use snafu::Snafu;
+use utoipa::ToSchema;
+
+use crate::service::error::ErrorKind;
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -9,6 +12,12 @@ pub type Result<T, E = Error> = std::result::Result<T, E>;
 pub enum Error {
     #[snafu(display("{source}"))]
     Service { source: crate::service::error::Error },
+
+    #[snafu(display("Fail to decode base64 data, error: {source}"))]
+    DecodeBase64 { source: base64::DecodeError },
+
+    #[snafu(display("Event type `{event_type}` is invalid"))]
+    InvalidEventType { event_type: String },
 }
 
 impl From<crate::service::error::Error> for Error {
@@ -19,11 +28,22 @@ impl IntoResponse for Error {
     fn into_response(self) -> Response {
         match self {
             Self::Service { source } => source.into_response(),
+            Self::DecodeBase64 { .. } => {
+                ErrorKind::InvalidArgument.response("INVALID_BASE64", self.to_string())
+            }
+            Self::InvalidEventType { .. } => {
+                ErrorKind::InvalidArgument.response("INVALID_EVENT_TYPE", self.to_string())
+            }
         }
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// Body of error responses
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
 pub struct ErrorResponse {
+    /// stable machine-readable code of the error, e.g. `INVALID_SIGNATURE`,
+    /// `TRANSACTION_NOT_FOUND` or `SOLANA_RPC_UNAVAILABLE`
+    pub code: String,
     pub message: String,
 }